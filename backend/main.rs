@@ -16,7 +16,6 @@ mod schema;
 async fn main() -> std::io::Result<()> {
     #[cfg(debug_assertions)] create_rust_app::setup_development().await;
     let app_data = create_rust_app::setup();
-    let game_server = ws::create_game_server();
     simple_logger::init_with_env().unwrap();
     
     // 创建用户服务专用的 SQLite 连接池
@@ -31,6 +30,11 @@ async fn main() -> std::io::Result<()> {
         .build(manager)
         .expect("Failed to create user database pool");
     
+    // 游戏服务器使用同一个连接池保存对局记录
+    let game_server = ws::create_game_server(user_db_pool.clone());
+    // 排位匹配通过 CreateRoom 在游戏服务器中创建房间
+    let matchmaker = services::matchmaking::create_matchmaker(game_server.clone(), user_db_pool.clone());
+    
     HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
@@ -49,6 +53,7 @@ async fn main() -> std::io::Result<()> {
             app_url: std::env::var("APP_URL").unwrap(),
         }));
         app = app.app_data(Data::new(game_server.clone()));
+        app = app.app_data(Data::new(matchmaker.clone()));
         
         // 添加用户服务专用的数据库连接池
         app = app.app_data(Data::new(user_db_pool.clone()));
@@ -59,10 +64,18 @@ async fn main() -> std::io::Result<()> {
         api_scope = api_scope.service(
             web::scope("/users").configure(services::user::endpoints)
         );
+        api_scope = api_scope.service(
+            web::scope("/friends").configure(services::friends::endpoints)
+        );
         // 注册 getRooms 路由
         api_scope = api_scope.service(services::get_rooms::get_rooms);
         // 注册 createRoom 路由
         api_scope = api_scope.service(services::create_room::create_room);
+        // 注册快速开始路由
+        api_scope = api_scope.service(services::quick_play::quick_play);
+        // 注册聊天记录路由
+        api_scope = api_scope.service(services::chat::get_whisper_history);
+        api_scope = api_scope.service(services::chat::get_chat_history);
         app = app.service(api_scope);
         
         // WebSocket 路由
@@ -0,0 +1,36 @@
+use diesel::prelude::*;
+use serde::Serialize;
+
+// 1. ChatMessage 结构体 - 一条聊天记录（房间聊天保存在内存中，全局聊天同时写入数据库）
+#[tsync::tsync]
+#[derive(Queryable, Selectable, Insertable, Serialize, Clone, Debug)]
+#[diesel(table_name = crate::schema::chat_messages)]
+pub struct ChatMessage {
+    pub id: i32, // 由 GameServer 分配，按时间递增，用于向前翻页
+    pub room_id: String,
+    pub sender_id: String,
+    pub username: String,
+    pub content: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+// 2. DirectMessage 结构体 - 一条私信记录
+#[derive(Queryable, Selectable, Serialize, Clone, Debug)]
+#[diesel(table_name = crate::schema::direct_messages)]
+pub struct DirectMessage {
+    pub id: i32,
+    pub sender_id: String,
+    pub recipient_id: String,
+    pub content: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+// 3. NewDirectMessage 结构体 - 用于插入数据库
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::direct_messages)]
+pub struct NewDirectMessage {
+    pub sender_id: String,
+    pub recipient_id: String,
+    pub content: String,
+    pub created_at: chrono::NaiveDateTime,
+}
@@ -0,0 +1,60 @@
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use serde::Serialize;
+
+// 1. Friendship 结构体 - 好友关系或未处理的好友请求
+#[derive(Queryable, Selectable, Serialize, Clone, Debug)]
+#[diesel(table_name = crate::schema::friends)]
+pub struct Friendship {
+    pub user_id: String,   // 发起请求的一方
+    pub friend_id: String, // 接收请求的一方
+    pub status: String,    // pending / accepted
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl Friendship {
+    /// 查找两个用户之间的关系（不区分方向）
+    pub fn find_between(db: &mut SqliteConnection, a: &str, b: &str) -> QueryResult<Option<Self>> {
+        use crate::schema::friends::dsl::*;
+
+        friends
+            .filter(user_id.eq(a).and(friend_id.eq(b)).or(user_id.eq(b).and(friend_id.eq(a))))
+            .select(Self::as_select())
+            .first::<Self>(db)
+            .optional()
+    }
+
+    /// 获取与某个用户相关的全部关系（包括双向的请求）
+    pub fn list_for_user(db: &mut SqliteConnection, param_user_id: &str) -> QueryResult<Vec<Self>> {
+        use crate::schema::friends::dsl::*;
+
+        friends
+            .filter(user_id.eq(param_user_id).or(friend_id.eq(param_user_id)))
+            .order(created_at.desc())
+            .select(Self::as_select())
+            .load::<Self>(db)
+    }
+
+    /// 获取某个用户全部已确认好友的ID
+    pub fn accepted_friend_ids(db: &mut SqliteConnection, param_user_id: &str) -> QueryResult<Vec<String>> {
+        Ok(Self::list_for_user(db, param_user_id)?
+            .into_iter()
+            .filter(|friendship| friendship.status == "accepted")
+            .map(|friendship| friendship.other(param_user_id).to_string())
+            .collect())
+    }
+
+    /// 关系中另一方的用户ID
+    pub fn other(&self, param_user_id: &str) -> &str {
+        if self.user_id == param_user_id { &self.friend_id } else { &self.user_id }
+    }
+}
+
+// 2. NewFriendship 结构体 - 用于插入数据库
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::friends)]
+pub struct NewFriendship<'a> {
+    pub user_id: &'a str,
+    pub friend_id: &'a str,
+    pub status: &'a str,
+}
@@ -0,0 +1,109 @@
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use serde::Serialize;
+use crate::models::rooms::PaginationResult;
+
+// 1. Game 结构体 - 一局已结束对局的记录
+#[derive(Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::games)]
+pub struct Game {
+    pub id: i32,
+    pub room_id: String,
+    pub winner_team: Option<String>, // 获胜队伍ID，如 "team_0"
+    pub player_count: i32,
+    pub started_at: chrono::NaiveDateTime,
+    pub ended_at: chrono::NaiveDateTime,
+    pub map_seed: Option<i64>, // 生成地图使用的seed
+}
+
+// 2. NewGame 结构体 - 用于插入数据库
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::games)]
+pub struct NewGame<'a> {
+    pub room_id: &'a str,
+    pub winner_team: Option<&'a str>,
+    pub player_count: i32,
+    pub started_at: chrono::NaiveDateTime,
+    pub ended_at: chrono::NaiveDateTime,
+    pub map_seed: Option<i64>,
+}
+
+// 3. GamePlayer 结构体 - 玩家在某局中的成绩
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize)]
+#[diesel(belongs_to(Game))]
+#[diesel(table_name = crate::schema::game_players)]
+pub struct GamePlayer {
+    pub id: i32,
+    pub game_id: i32,
+    pub user_id: String,
+    pub username: String, // 对局时使用的用户名
+    pub group_id: i32,    // 对局时所在的组（0-7）
+    pub team_id: String,
+    pub placement: i32,   // 名次，1为第一名
+    pub is_winner: bool,
+    pub cities_captured: i32,
+    pub generals_captured: i32,
+}
+
+impl GamePlayer {
+    /// 分页获取某个用户的对局成绩，按对局从新到旧排序，page 从 0 开始
+    pub fn paginate_by_user(db: &mut SqliteConnection, param_user_id: &str, page: i64, page_size: i64) -> QueryResult<PaginationResult<Self>> {
+        use crate::schema::game_players::dsl::*;
+
+        let page = page.max(0);
+        let page_size = page_size.max(1);
+        let total_items = game_players.filter(user_id.eq(param_user_id)).count().get_result(db)?;
+        let items = game_players
+            .filter(user_id.eq(param_user_id))
+            .order(game_id.desc())
+            .limit(page_size)
+            .offset(page * page_size)
+            .select(Self::as_select())
+            .load::<Self>(db)?;
+
+        Ok(PaginationResult {
+            items,
+            total_items,
+            page,
+            page_size,
+            /* ceiling division of integers */
+            num_pages: total_items / page_size + i64::from(total_items % page_size != 0)
+        })
+    }
+}
+
+// 4. NewGamePlayer 结构体 - 用于插入数据库
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::game_players)]
+pub struct NewGamePlayer<'a> {
+    pub game_id: i32,
+    pub user_id: &'a str,
+    pub username: &'a str,
+    pub group_id: i32,
+    pub team_id: &'a str,
+    pub placement: i32,
+    pub is_winner: bool,
+    pub cities_captured: i32,
+    pub generals_captured: i32,
+}
+
+// 5. GameSnapshot 结构体 - 进行中对局的快照（JSON 序列化的房间与地图状态）
+#[derive(Queryable, Selectable, Identifiable)]
+#[diesel(primary_key(room_id))]
+#[diesel(table_name = crate::schema::game_snapshots)]
+pub struct GameSnapshot {
+    pub room_id: String,
+    pub game_turn: i32,
+    pub snapshot: String,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+// 6. NewGameSnapshot 结构体 - 用于插入或覆盖快照
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = crate::schema::game_snapshots)]
+pub struct NewGameSnapshot {
+    pub room_id: String,
+    pub game_turn: i32,
+    pub snapshot: String,
+    pub updated_at: chrono::NaiveDateTime,
+}
@@ -1,3 +1,6 @@
 pub mod user;
 pub mod room;
 pub mod rooms;
+pub mod game;
+pub mod chat;
+pub mod friend;
@@ -6,15 +6,27 @@ use serde::{Deserialize, Serialize};
 pub struct Room {
     pub id: Option<i32>,
     pub name: String,
-    pub password: Option<String>,
+    pub password_hash: Option<String>,
     pub is_public: bool,
     pub created_at: chrono::NaiveDateTime,
+    pub room_id: String,
+    pub max_players: i32,
+    pub room_color: String,
+    pub host_player_id: String,
+    pub host_player_name: String,
+    pub is_persistent: bool,
 }
 
 #[derive(Insertable, Serialize, Deserialize)]
 #[diesel(table_name = crate::schema::rooms)]
 pub struct NewRoom {
     pub name: String,
-    pub password: Option<String>,
+    pub password_hash: Option<String>,
     pub is_public: bool,
+    pub room_id: String,
+    pub max_players: i32,
+    pub room_color: String,
+    pub host_player_id: String,
+    pub host_player_name: String,
+    pub is_persistent: bool,
 }
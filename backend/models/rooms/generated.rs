@@ -15,12 +15,24 @@ pub struct Rooms {
     pub id: Option<i32>,
     /// Field representing column `name`
     pub name: String,
-    /// Field representing column `password`
-    pub password: Option<String>,
+    /// Field representing column `password_hash`
+    pub password_hash: Option<String>,
     /// Field representing column `is_public`
     pub is_public: bool,
     /// Field representing column `created_at`
     pub created_at: chrono::NaiveDateTime,
+    /// Field representing column `room_id`
+    pub room_id: String,
+    /// Field representing column `max_players`
+    pub max_players: i32,
+    /// Field representing column `room_color`
+    pub room_color: String,
+    /// Field representing column `host_player_id`
+    pub host_player_id: String,
+    /// Field representing column `host_player_name`
+    pub host_player_name: String,
+    /// Field representing column `is_persistent`
+    pub is_persistent: bool,
 }
 
 /// Create Struct for a row in table `rooms` for [`Rooms`]
@@ -30,10 +42,22 @@ pub struct Rooms {
 pub struct CreateRooms {
     /// Field representing column `name`
     pub name: String,
-    /// Field representing column `password`
-    pub password: Option<String>,
+    /// Field representing column `password_hash`
+    pub password_hash: Option<String>,
     /// Field representing column `is_public`
     pub is_public: bool,
+    /// Field representing column `room_id`
+    pub room_id: String,
+    /// Field representing column `max_players`
+    pub max_players: i32,
+    /// Field representing column `room_color`
+    pub room_color: String,
+    /// Field representing column `host_player_id`
+    pub host_player_id: String,
+    /// Field representing column `host_player_name`
+    pub host_player_name: String,
+    /// Field representing column `is_persistent`
+    pub is_persistent: bool,
 }
 
 /// Update Struct for a row in table `rooms` for [`Rooms`]
@@ -43,12 +67,24 @@ pub struct CreateRooms {
 pub struct UpdateRooms {
     /// Field representing column `name`
     pub name: Option<String>,
-    /// Field representing column `password`
-    pub password: Option<Option<String>>,
+    /// Field representing column `password_hash`
+    pub password_hash: Option<Option<String>>,
     /// Field representing column `is_public`
     pub is_public: Option<bool>,
     /// Field representing column `created_at`
     pub created_at: Option<chrono::NaiveDateTime>,
+    /// Field representing column `room_id`
+    pub room_id: Option<String>,
+    /// Field representing column `max_players`
+    pub max_players: Option<i32>,
+    /// Field representing column `room_color`
+    pub room_color: Option<String>,
+    /// Field representing column `host_player_id`
+    pub host_player_id: Option<String>,
+    /// Field representing column `host_player_name`
+    pub host_player_name: Option<String>,
+    /// Field representing column `is_persistent`
+    pub is_persistent: Option<bool>,
 }
 
 /// Result of a `.paginate` function
@@ -128,11 +164,11 @@ impl Rooms {
         if let Some(filter_name) = filter.name {
             query = query.filter(crate::schema::rooms::name.eq(filter_name));
         }
-        if let Some(filter_password) = filter.password {
-            query = if filter_password.is_some() { 
-                query.filter(crate::schema::rooms::password.eq(filter_password))
+        if let Some(filter_password_hash) = filter.password_hash {
+            query = if filter_password_hash.is_some() { 
+                query.filter(crate::schema::rooms::password_hash.eq(filter_password_hash))
             } else {
-                query.filter(crate::schema::rooms::password.is_null())
+                query.filter(crate::schema::rooms::password_hash.is_null())
             };
         }
         if let Some(filter_is_public) = filter.is_public {
@@ -141,6 +177,24 @@ impl Rooms {
         if let Some(filter_created_at) = filter.created_at {
             query = query.filter(crate::schema::rooms::created_at.eq(filter_created_at));
         }
+        if let Some(filter_room_id) = filter.room_id {
+            query = query.filter(crate::schema::rooms::room_id.eq(filter_room_id));
+        }
+        if let Some(filter_max_players) = filter.max_players {
+            query = query.filter(crate::schema::rooms::max_players.eq(filter_max_players));
+        }
+        if let Some(filter_room_color) = filter.room_color {
+            query = query.filter(crate::schema::rooms::room_color.eq(filter_room_color));
+        }
+        if let Some(filter_host_player_id) = filter.host_player_id {
+            query = query.filter(crate::schema::rooms::host_player_id.eq(filter_host_player_id));
+        }
+        if let Some(filter_host_player_name) = filter.host_player_name {
+            query = query.filter(crate::schema::rooms::host_player_name.eq(filter_host_player_name));
+        }
+        if let Some(filter_is_persistent) = filter.is_persistent {
+            query = query.filter(crate::schema::rooms::is_persistent.eq(filter_is_persistent));
+        }
         
         query
     }
@@ -163,7 +217,13 @@ impl Rooms {
 pub struct RoomsFilter {
     pub id: Option<Option<i32>>,
     pub name: Option<String>,
-    pub password: Option<Option<String>>,
+    pub password_hash: Option<Option<String>>,
     pub is_public: Option<bool>,
     pub created_at: Option<chrono::NaiveDateTime>,
+    pub room_id: Option<String>,
+    pub max_players: Option<i32>,
+    pub room_color: Option<String>,
+    pub host_player_id: Option<String>,
+    pub host_player_name: Option<String>,
+    pub is_persistent: Option<bool>,
 }
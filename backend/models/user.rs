@@ -7,6 +7,10 @@ use serde::{Serialize, Deserialize};
 pub struct User {
     pub id: String,  // 匹配数据库字段名
     pub username: String,
+    #[serde(skip_serializing)]
+    pub password_hash: Option<String>, // argon2 哈希，永不返回给前端
+    pub is_guest: bool, // 游客账户
+    pub rating: i32, // 1v1 排位分
 }
 
 // 2. NewUser 结构体 - 用于插入数据库
@@ -15,6 +19,8 @@ pub struct User {
 pub struct NewUser<'a> {
     pub id: &'a str,  // 匹配数据库字段名
     pub username: &'a str,
+    pub password_hash: Option<&'a str>,
+    pub is_guest: bool,
 }
 
 // 3. UserInput 结构体 - 用于接收前端数据
@@ -22,4 +28,71 @@ pub struct NewUser<'a> {
 pub struct UserInput {
     pub user_id: String,  // 前端发送的字段名
     pub username: String,
-}
\ No newline at end of file
+    pub password: String,
+}
+
+// 4. LoginInput 结构体 - 用于接收登录数据
+#[derive(Deserialize)]
+pub struct LoginInput {
+    pub user_id: String,
+    pub password: String,
+}
+
+// 5. UpgradeGuestInput 结构体 - 游客升级为正式账户
+#[derive(Deserialize)]
+pub struct UpgradeGuestInput {
+    pub password: String,
+    pub username: Option<String>, // 可选：同时修改用户名
+}
+
+// 6. RenameInput 结构体 - 修改用户名
+#[derive(Deserialize)]
+pub struct RenameInput {
+    pub username: String,
+}
+
+// 7. UsernameChange 结构体 - 改名记录
+#[derive(Queryable, Selectable, Serialize)]
+#[diesel(table_name = crate::schema::username_history)]
+pub struct UsernameChange {
+    pub id: i32,
+    pub user_id: String,
+    pub old_username: String,
+    pub new_username: String,
+    pub changed_at: chrono::NaiveDateTime,
+}
+
+// 8. NewUsernameChange 结构体 - 用于插入数据库
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::username_history)]
+pub struct NewUsernameChange<'a> {
+    pub user_id: &'a str,
+    pub old_username: &'a str,
+    pub new_username: &'a str,
+}
+
+// 9. Session 结构体 - 登录会话
+#[derive(Queryable, Selectable, Identifiable)]
+#[diesel(primary_key(token))]
+#[diesel(table_name = crate::schema::sessions)]
+pub struct Session {
+    pub token: String,
+    pub user_id: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+// 10. NewSession 结构体 - 用于插入数据库
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::sessions)]
+pub struct NewSession<'a> {
+    pub token: &'a str,
+    pub user_id: &'a str,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+// 11. ClaimPasswordInput 结构体 - 旧账户认领密码
+#[derive(Deserialize)]
+pub struct ClaimPasswordInput {
+    pub password: String,
+}
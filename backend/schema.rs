@@ -1,12 +1,103 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    chat_messages (id) {
+        id -> Integer,
+        room_id -> Text,
+        sender_id -> Text,
+        username -> Text,
+        content -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    direct_messages (id) {
+        id -> Integer,
+        sender_id -> Text,
+        recipient_id -> Text,
+        content -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    friends (user_id, friend_id) {
+        user_id -> Text,
+        friend_id -> Text,
+        status -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    game_players (id) {
+        id -> Integer,
+        game_id -> Integer,
+        user_id -> Text,
+        username -> Text,
+        group_id -> Integer,
+        team_id -> Text,
+        placement -> Integer,
+        is_winner -> Bool,
+        cities_captured -> Integer,
+        generals_captured -> Integer,
+    }
+}
+
+diesel::table! {
+    game_snapshots (room_id) {
+        room_id -> Text,
+        game_turn -> Integer,
+        snapshot -> Text,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    games (id) {
+        id -> Integer,
+        room_id -> Text,
+        winner_team -> Nullable<Text>,
+        player_count -> Integer,
+        started_at -> Timestamp,
+        ended_at -> Timestamp,
+        map_seed -> Nullable<BigInt>,
+    }
+}
+
 diesel::table! {
     rooms (id) {
         id -> Nullable<Integer>,
         name -> Text,
-        password -> Nullable<Text>,
+        password_hash -> Nullable<Text>,
         is_public -> Bool,
         created_at -> Timestamp,
+        room_id -> Text,
+        max_players -> Integer,
+        room_color -> Text,
+        host_player_id -> Text,
+        host_player_name -> Text,
+        is_persistent -> Bool,
+    }
+}
+
+diesel::table! {
+    sessions (token) {
+        token -> Text,
+        user_id -> Text,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    username_history (id) {
+        id -> Integer,
+        user_id -> Text,
+        old_username -> Text,
+        new_username -> Text,
+        changed_at -> Timestamp,
     }
 }
 
@@ -14,10 +105,25 @@ diesel::table! {
     users (id) {
         id -> Text,
         username -> Text,
+        password_hash -> Nullable<Text>,
+        is_guest -> Bool,
+        rating -> Integer,
     }
 }
 
+diesel::joinable!(game_players -> games (game_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(username_history -> users (user_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
+    chat_messages,
+    direct_messages,
+    friends,
+    game_players,
+    game_snapshots,
+    games,
     rooms,
+    sessions,
+    username_history,
     users,
 );
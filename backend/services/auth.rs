@@ -0,0 +1,154 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use argon2::password_hash::SaltString;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use crate::models::user::{User, Session, NewSession};
+use crate::services::user::DbPool;
+
+/// 会话有效期（天）
+const SESSION_TTL_DAYS: i64 = 30;
+/// 旧账户认领密码用的临时会话有效期（小时）
+pub const CLAIM_TTL_HOURS: i64 = 24;
+
+/// 密码长度限制
+pub const MIN_PASSWORD_LEN: usize = 6;
+pub const MAX_PASSWORD_LEN: usize = 64;
+
+/// 验证密码长度
+pub fn validate_password(password: &str) -> Result<(), String> {
+    let len = password.chars().count();
+    if len < MIN_PASSWORD_LEN || len > MAX_PASSWORD_LEN {
+        return Err(format!("密码长度必须在{}-{}个字符之间", MIN_PASSWORD_LEN, MAX_PASSWORD_LEN));
+    }
+    Ok(())
+}
+
+/// 使用 argon2 和随机盐对密码进行哈希
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt_bytes: [u8; 16] = rand::rng().random();
+    let salt = SaltString::encode_b64(&salt_bytes)?;
+    Ok(Argon2::default().hash_password(password.as_bytes(), &salt)?.to_string())
+}
+
+/// 校验密码是否与哈希匹配
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(parsed) => Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok(),
+        Err(_) => false,
+    }
+}
+
+// 用户不存在或没有密码时用于校验的哈希，使登录失败的响应时间一致
+static DUMMY_PASSWORD_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password("dummy password").expect("生成占位密码哈希失败"));
+
+/// 校验密码；没有哈希时仍执行一次校验并返回 false
+pub fn verify_password_or_dummy(password: &str, password_hash: Option<&str>) -> bool {
+    match password_hash {
+        Some(password_hash) => verify_password(password, password_hash),
+        None => {
+            verify_password(password, &DUMMY_PASSWORD_HASH);
+            false
+        }
+    }
+}
+
+/// 是否为管理员，管理员用户ID从环境变量 ADMIN_USER_IDS 读取（逗号分隔）
+pub fn is_admin(user_id: &str) -> bool {
+    std::env::var("ADMIN_USER_IDS")
+        .map(|ids| ids.split(',').any(|id| id.trim() == user_id))
+        .unwrap_or(false)
+}
+
+/// 生成随机 token（64位十六进制字符串），用于登录会话和房间邀请
+pub fn generate_token() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 为用户创建新的登录会话
+pub fn create_session(conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Session> {
+    create_session_with_ttl(conn, user_id, chrono::Duration::days(SESSION_TTL_DAYS))
+}
+
+/// 创建指定有效期的会话
+pub fn create_session_with_ttl(conn: &mut SqliteConnection, user_id: &str, ttl: chrono::Duration) -> QueryResult<Session> {
+    let token = generate_token();
+    let expires_at = chrono::Utc::now().naive_utc() + ttl;
+
+    diesel::insert_into(crate::schema::sessions::table)
+        .values(&NewSession {
+            token: &token,
+            user_id,
+            expires_at,
+        })
+        .get_result::<Session>(conn)
+}
+
+/// 删除登录会话
+pub fn delete_session(conn: &mut SqliteConnection, token: &str) -> QueryResult<usize> {
+    diesel::delete(crate::schema::sessions::table.filter(crate::schema::sessions::token.eq(token)))
+        .execute(conn)
+}
+
+/// 根据未过期的会话 token 查找用户
+pub fn find_user_by_token(conn: &mut SqliteConnection, token: &str) -> QueryResult<User> {
+    crate::schema::sessions::table
+        .inner_join(crate::schema::users::table)
+        .filter(crate::schema::sessions::token.eq(token))
+        .filter(crate::schema::sessions::expires_at.gt(chrono::Utc::now().naive_utc()))
+        .select(User::as_select())
+        .first::<User>(conn)
+}
+
+/// 从请求中读取会话 token：优先使用 Authorization: Bearer 头，
+/// WebSocket 握手无法设置请求头，因此也接受 ?token= 查询参数
+pub fn request_token(req: &HttpRequest) -> Option<String> {
+    if let Some(header) = req.headers().get("Authorization").and_then(|v| v.to_str().ok()) {
+        if let Some(token) = header.strip_prefix("Bearer ") {
+            if !token.is_empty() {
+                return Some(token.to_string());
+            }
+        }
+    }
+
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.get("token").cloned())
+        .filter(|token| !token.is_empty())
+}
+
+fn unauthorized(message: &str) -> HttpResponse {
+    HttpResponse::Unauthorized().json(serde_json::json!({
+        "error": "未授权",
+        "message": message
+    }))
+}
+
+/// 验证请求携带的会话 token，返回对应的用户；失败时返回可直接响应的错误
+pub async fn authenticate(pool: &web::Data<DbPool>, req: &HttpRequest) -> Result<User, HttpResponse> {
+    let token = match request_token(req) {
+        Some(token) => token,
+        None => return Err(unauthorized("缺少登录凭证，请先登录")),
+    };
+
+    let pool = pool.clone();
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+        find_user_by_token(&mut conn, &token)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(user)) => Ok(user),
+        Ok(Err(diesel::NotFound)) => Err(unauthorized("登录已失效，请重新登录")),
+        _ => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "服务器错误",
+            "message": "验证登录状态时发生内部错误"
+        }))),
+    }
+}
@@ -0,0 +1,170 @@
+use actix::prelude::*;
+use actix_web::{web, get, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::models::chat::{ChatMessage, DirectMessage};
+use crate::services::auth;
+use crate::services::user::DbPool;
+use crate::services::ws::{GameServer, GetChatHistory};
+
+/// 单页默认条数与上限
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize)]
+pub struct ChatHistoryQuery {
+    pub before: Option<i32>, // 只返回ID小于该值的消息，不传则返回最新的消息
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct WhisperHistoryResponse {
+    pub user_id: String, // 对方的用户ID
+    pub messages: Vec<DirectMessage>, // 按时间正序
+    pub has_more: bool,
+}
+
+#[derive(Serialize)]
+pub struct ChatHistoryResponse {
+    pub room_id: String,
+    pub messages: Vec<ChatMessage>, // 按时间正序
+    pub has_more: bool,
+}
+
+/// GET /api/chat/{room_id}?before=id&limit=n
+/// 分页获取聊天记录：全局聊天从数据库读取，房间聊天从内存中读取（仅房间内的玩家可查看）
+#[get("/chat/{room_id}")]
+pub async fn get_chat_history(
+    pool: web::Data<DbPool>,
+    game_server: web::Data<Addr<GameServer>>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ChatHistoryQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = match auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let room_id = path.into_inner();
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let before = query.before;
+
+    // 多取一条用于判断是否还有更早的消息
+    let result = if room_id == "global" {
+        let room_id = room_id.clone();
+        web::block(move || {
+            let mut conn = pool.get().expect("couldn't get db connection from pool");
+            let mut query = crate::schema::chat_messages::table
+                .filter(crate::schema::chat_messages::room_id.eq(room_id))
+                .into_boxed();
+            if let Some(before) = before {
+                query = query.filter(crate::schema::chat_messages::id.lt(before));
+            }
+            query
+                .order(crate::schema::chat_messages::id.desc())
+                .limit(limit as i64 + 1)
+                .select(ChatMessage::as_select())
+                .load::<ChatMessage>(&mut conn)
+                .map(|mut messages| {
+                    messages.reverse();
+                    messages
+                })
+                .map_err(|e| {
+                    println!("查询聊天记录失败: {}", e);
+                    "获取聊天记录失败".to_string()
+                })
+        })
+        .await?
+    } else {
+        match game_server.send(GetChatHistory {
+            room_id: room_id.clone(),
+            user_id: user.id,
+            before,
+            limit: limit + 1,
+        }).await {
+            Ok(result) => result,
+            Err(_) => Err("获取聊天记录失败".to_string()),
+        }
+    };
+
+    match result {
+        Ok(mut messages) => {
+            let has_more = messages.len() > limit;
+            if has_more {
+                messages.remove(0);
+            }
+            Ok(HttpResponse::Ok().json(ChatHistoryResponse {
+                room_id,
+                messages,
+                has_more,
+            }))
+        }
+        Err(message) => Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无法获取聊天记录",
+            "message": message
+        }))),
+    }
+}
+
+/// GET /api/chat/whispers/{user_id}?before=id&limit=n
+/// 分页获取当前用户与指定用户之间的私信记录
+#[get("/chat/whispers/{user_id}")]
+pub async fn get_whisper_history(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ChatHistoryQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = match auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let other_id = path.into_inner();
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let before = query.before;
+
+    let query_other_id = other_id.clone();
+    let result = web::block(move || {
+        use crate::schema::direct_messages::dsl::*;
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+        let mut query = direct_messages
+            .filter(
+                sender_id.eq(&user.id).and(recipient_id.eq(&query_other_id))
+                    .or(sender_id.eq(&query_other_id).and(recipient_id.eq(&user.id)))
+            )
+            .into_boxed();
+        if let Some(before) = before {
+            query = query.filter(id.lt(before));
+        }
+        // 多取一条用于判断是否还有更早的消息
+        query
+            .order(id.desc())
+            .limit(limit as i64 + 1)
+            .select(DirectMessage::as_select())
+            .load::<DirectMessage>(&mut conn)
+    })
+    .await?;
+
+    match result {
+        Ok(mut messages) => {
+            let has_more = messages.len() > limit;
+            messages.truncate(limit);
+            messages.reverse();
+            Ok(HttpResponse::Ok().json(WhisperHistoryResponse {
+                user_id: other_id,
+                messages,
+                has_more,
+            }))
+        }
+        Err(e) => {
+            println!("查询私信记录失败: {}", e);
+            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "服务器错误",
+                "message": "获取私信记录时发生内部错误"
+            })))
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+/// 默认配置文件路径，可通过 CONTENT_FILTER_CONFIG 环境变量覆盖
+const DEFAULT_CONFIG_PATH: &str = "content_filter.json";
+
+/// 内容过滤配置文件格式
+#[derive(Deserialize, Default)]
+pub struct FilterConfig {
+    #[serde(default)]
+    pub block: Vec<String>, // 命中后整条内容被拒绝
+    #[serde(default)]
+    pub mask: Vec<String>,  // 命中后在聊天中替换为 *，在名称中仍然拒绝
+}
+
+/// 共享的内容过滤器，用于用户名、房间名、房间ID和聊天消息
+pub struct ContentFilter {
+    block_words: Vec<String>, // 已归一化
+    mask_words: Vec<String>,  // 已归一化
+}
+
+static GLOBAL_FILTER: OnceLock<ContentFilter> = OnceLock::new();
+
+/// 获取全局过滤器，首次调用时从配置文件加载
+pub fn global() -> &'static ContentFilter {
+    GLOBAL_FILTER.get_or_init(|| {
+        let path = std::env::var("CONTENT_FILTER_CONFIG")
+            .unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+        ContentFilter::load(&path)
+    })
+}
+
+/// 检查名称（用户名、房间名、房间ID）是否包含屏蔽词
+pub fn check_name(text: &str) -> Result<(), String> {
+    match global().find_word(text) {
+        Some(_) => Err("包含禁用内容".to_string()),
+        None => Ok(()),
+    }
+}
+
+/// 过滤聊天消息：包含屏蔽词时拒绝，包含遮蔽词时替换为 *；聊天中只按完整的词匹配
+pub fn filter_chat(text: &str) -> Result<String, String> {
+    let filter = global();
+    if filter.find_blocked(text).is_some() {
+        return Err("消息包含禁用内容".to_string());
+    }
+    Ok(filter.mask(text))
+}
+
+// 零宽字符等不可见字符，判断词边界时跳过
+fn is_invisible(ch: char) -> bool {
+    matches!(ch, '\u{00AD}' | '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}')
+}
+
+// 中日韩文字没有空格分词，词内匹配不受边界限制
+fn is_cjk(ch: char) -> bool {
+    matches!(ch as u32, 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF)
+}
+
+// 词边界：匹配两端紧挨着的字符不能是拼音文字的字母或数字
+fn is_boundary(neighbor: Option<&char>) -> bool {
+    neighbor.map_or(true, |&ch| !ch.is_alphanumeric() || is_cjk(ch))
+}
+
+impl ContentFilter {
+    pub fn from_config(config: FilterConfig) -> Self {
+        let normalize_words = |words: Vec<String>| -> Vec<String> {
+            words.iter()
+                .map(|word| Self::normalize(word))
+                .filter(|word| !word.is_empty())
+                .collect()
+        };
+
+        ContentFilter {
+            block_words: normalize_words(config.block),
+            mask_words: normalize_words(config.mask),
+        }
+    }
+
+    /// 从配置文件加载，文件不存在或格式错误时退回到内置规则
+    pub fn load(path: &str) -> Self {
+        let config = match std::fs::read_to_string(path) {
+            Ok(content) => match serde_json::from_str::<FilterConfig>(&content) {
+                Ok(config) => {
+                    println!("已加载内容过滤配置: {} (屏蔽词 {} 个, 遮蔽词 {} 个)",
+                             path, config.block.len(), config.mask.len());
+                    config
+                }
+                Err(e) => {
+                    println!("内容过滤配置 {} 格式错误，使用内置规则: {}", path, e);
+                    Self::fallback_config()
+                }
+            },
+            Err(_) => {
+                println!("未找到内容过滤配置 {}，使用内置规则", path);
+                Self::fallback_config()
+            }
+        };
+
+        Self::from_config(config)
+    }
+
+    // 内置规则：与之前硬编码的检查保持一致
+    fn fallback_config() -> FilterConfig {
+        FilterConfig {
+            block: vec!["ek".to_string()],
+            mask: Vec::new(),
+        }
+    }
+
+    /// 归一化单个字符：全角转半角、转小写、常见形近字符替换；
+    /// 返回 None 表示该字符应被忽略（空白、标点、零宽字符等用于规避检测的分隔符）
+    fn normalize_char(ch: char) -> Option<char> {
+        // 全角字符转半角
+        let ch = match ch as u32 {
+            0xFF01..=0xFF5E => char::from_u32(ch as u32 - 0xFEE0).unwrap_or(ch),
+            0x3000 => ' ',
+            _ => ch,
+        };
+
+        if !ch.is_alphanumeric() {
+            return None;
+        }
+
+        let ch = ch.to_lowercase().next().unwrap_or(ch);
+
+        // 形近字符（数字替换、西里尔/希腊字母）
+        let ch = match ch {
+            '0' | 'о' | 'ο' => 'o',
+            '1' | 'ı' | 'і' => 'i',
+            '3' | 'е' | 'ε' => 'e',
+            '4' | 'а' | 'α' => 'a',
+            '5' | 'ѕ' => 's',
+            '7' => 't',
+            '8' => 'b',
+            'с' => 'c',
+            'р' | 'ρ' => 'p',
+            'к' | 'κ' => 'k',
+            'х' | 'χ' => 'x',
+            'у' | 'γ' => 'y',
+            'ν' => 'v',
+            'т' | 'τ' => 't',
+            'м' => 'm',
+            'н' | 'η' => 'h',
+            _ => ch,
+        };
+
+        Some(ch)
+    }
+
+    /// 将文本归一化为用于匹配的形式
+    pub fn normalize(text: &str) -> String {
+        text.chars().filter_map(Self::normalize_char).collect()
+    }
+
+    // 归一化后的字符及其在原文中的字符下标
+    fn normalize_with_positions(text: &str) -> (Vec<char>, Vec<usize>) {
+        let mut normalized = Vec::new();
+        let mut positions = Vec::new();
+        for (index, ch) in text.chars().enumerate() {
+            if let Some(normalized_ch) = Self::normalize_char(ch) {
+                normalized.push(normalized_ch);
+                positions.push(index);
+            }
+        }
+        (normalized, positions)
+    }
+
+    // 在去掉分隔符的整段文本中查找匹配，再按原文中匹配两侧的字符判断词边界，
+    // 这样 "f u c k" 这类拆开的写法仍能命中，而 "seek" 中的 "ek" 不会误伤
+    fn find_word_matches(words: &[String], text: &str) -> Vec<(usize, usize)> {
+        let original: Vec<char> = text.chars().collect();
+        let (normalized, positions) = Self::normalize_with_positions(text);
+        let mut matches = Vec::new();
+        for word in words {
+            let word: Vec<char> = word.chars().collect();
+            if word.len() > normalized.len() {
+                continue;
+            }
+            for start in 0..=(normalized.len() - word.len()) {
+                if normalized[start..start + word.len()] != word[..] {
+                    continue;
+                }
+                let first = positions[start];
+                let last = positions[start + word.len() - 1];
+                let before = original[..first].iter().rev().find(|ch| !is_invisible(**ch));
+                let after = original[last + 1..].iter().find(|ch| !is_invisible(**ch));
+                if is_boundary(before) && is_boundary(after) {
+                    matches.push((first, last));
+                }
+            }
+        }
+        matches
+    }
+
+    fn find_in<'a>(words: &'a [String], normalized: &str) -> Option<&'a str> {
+        words.iter()
+            .find(|word| normalized.contains(word.as_str()))
+            .map(|word| word.as_str())
+    }
+
+    /// 按完整的词查找聊天消息中的屏蔽词（忽略词内的分隔符）
+    pub fn find_blocked(&self, text: &str) -> Option<(usize, usize)> {
+        Self::find_word_matches(&self.block_words, text).into_iter().next()
+    }
+
+    /// 查找名称中的任意屏蔽词或遮蔽词，忽略分隔符按子串匹配
+    pub fn find_word(&self, text: &str) -> Option<&str> {
+        let normalized = Self::normalize(text);
+        Self::find_in(&self.block_words, &normalized)
+            .or_else(|| Self::find_in(&self.mask_words, &normalized))
+    }
+
+    /// 将文本中作为完整词出现的遮蔽词替换为 *（保留原文中的空白，其余被跨越的字符一并遮蔽）
+    pub fn mask(&self, text: &str) -> String {
+        let mut original: Vec<char> = text.chars().collect();
+        for (first, last) in Self::find_word_matches(&self.mask_words, text) {
+            for ch in &mut original[first..=last] {
+                if !ch.is_whitespace() {
+                    *ch = '*';
+                }
+            }
+        }
+        original.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_filter() -> ContentFilter {
+        ContentFilter::from_config(FilterConfig {
+            block: vec!["ek".to_string(), "傻逼".to_string()],
+            mask: vec!["fuck".to_string()],
+        })
+    }
+
+    #[test]
+    fn blocks_spaced_and_punctuated_words() {
+        let filter = test_filter();
+        assert!(filter.find_blocked("e k").is_some());
+        assert!(filter.find_blocked("e.k").is_some());
+        assert!(filter.find_blocked("e\u{200B}k").is_some());
+        assert!(filter.find_blocked("傻 逼").is_some());
+        assert!(filter.find_blocked("你是傻逼吧").is_some());
+    }
+
+    #[test]
+    fn ignores_words_inside_longer_words() {
+        let filter = test_filter();
+        assert!(filter.find_blocked("seek").is_none());
+        assert!(filter.find_blocked("the kid").is_none());
+        assert!(filter.find_blocked("e kid").is_none());
+        assert_eq!(filter.mask("unfucked"), "unfucked");
+    }
+
+    #[test]
+    fn masks_spaced_and_punctuated_words() {
+        let filter = test_filter();
+        assert_eq!(filter.mask("f u c k you"), "* * * * you");
+        assert_eq!(filter.mask("f.u.c.k"), "*******");
+        assert_eq!(filter.mask("ｆｕｃｋ!"), "****!");
+        assert_eq!(filter.mask("what the fuck"), "what the ****");
+    }
+}
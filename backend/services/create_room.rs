@@ -1,8 +1,11 @@
 use actix::prelude::*;
-use actix_web::{web, HttpResponse, Result, post};
+use actix_web::{web, HttpRequest, HttpResponse, Result, post};
 use serde::{Deserialize, Serialize};
 use rand::Rng;
 use crate::services::ws::{GameServer, CreateRoom};
+use crate::services::auth;
+use crate::services::content_filter;
+use crate::services::user::DbPool;
 
 /// 生成房间ID（不超过10位字符串）
 pub fn generate_room_id() -> String {
@@ -12,7 +15,7 @@ pub fn generate_room_id() -> String {
 }
 
 /// 验证自定义房间ID格式
-fn validate_room_id(room_id: &str) -> Result<(), String> {
+pub fn validate_room_id(room_id: &str) -> Result<(), String> {
     // 检查长度
     if room_id.is_empty() {
         return Err("房间ID不能为空".to_string());
@@ -29,6 +32,58 @@ fn validate_room_id(room_id: &str) -> Result<(), String> {
         }
     }
     
+    // 检查是否包含禁用内容
+    if content_filter::check_name(room_id).is_err() {
+        return Err("房间ID包含禁用内容".to_string());
+    }
+    
+    Ok(())
+}
+
+/// 验证房间设置（创建房间和修改房间设置共用），返回 (错误码, 错误信息)
+pub fn validate_room_settings(
+    name: Option<&str>,
+    max_players: Option<usize>,
+    room_color: Option<&str>,
+    password: Option<&str>,
+) -> Result<(), (&'static str, String)> {
+    // 验证房间名称
+    if let Some(name) = name {
+        if name.trim().is_empty() {
+            return Err(("invalid_name", "房间名称不能为空".to_string()));
+        }
+        
+        if name.len() > 50 {
+            return Err(("name_too_long", "房间名称不能超过50个字符".to_string()));
+        }
+        
+        // 验证是否包含禁用内容
+        if content_filter::check_name(name).is_err() {
+            return Err(("forbidden_content", "房间名称包含禁用内容".to_string()));
+        }
+    }
+    
+    // 验证最大玩家数
+    if let Some(max_players) = max_players {
+        if max_players < 2 || max_players > 16 {
+            return Err(("invalid_max_players", "最大玩家数必须在2-16之间".to_string()));
+        }
+    }
+    
+    // 验证颜色格式 (简单的十六进制颜色验证)
+    if let Some(room_color) = room_color {
+        if !room_color.starts_with('#') || room_color.len() != 7 {
+            return Err(("invalid_color", "颜色格式无效，请使用十六进制格式如 #FF5733".to_string()));
+        }
+    }
+    
+    // 验证密码（如果提供）
+    if let Some(password) = password {
+        if password.len() > 20 {
+            return Err(("password_too_long", "密码不能超过20个字符".to_string()));
+        }
+    }
+    
     Ok(())
 }
 
@@ -38,10 +93,10 @@ pub struct CreateRoomRequest {
     pub name: String,
     pub max_players: usize,
     pub room_color: String, // 房间专属颜色，例如 "#FF5733"
-    pub host_name: String, // 房主的用户名
-    pub host_id: String,   // 房主的用户ID
     pub password: Option<String>, // 新增：房间密码
     pub is_public: bool,         // 新增：是否为公开房间
+    #[serde(default)]
+    pub is_persistent: bool,     // 常驻房间：不会被自动清理，服务器重启后恢复
 }
 
 #[derive(Serialize)]
@@ -56,60 +111,39 @@ pub struct CreateRoomResponse {
 }
 
 /// POST /api/createRoom
-/// 创建新房间
+/// 创建新房间，房主为当前登录用户
 #[post("/createRoom")]
 pub async fn create_room(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
     game_server: web::Data<Addr<GameServer>>,
     request: web::Json<CreateRoomRequest>,
 ) -> Result<HttpResponse> {
-    // 验证房间名称
-    if request.name.trim().is_empty() {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "invalid_name",
-            "message": "房间名称不能为空"
-        })));
-    }
-    
-    if request.name.len() > 50 {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "name_too_long",
-            "message": "房间名称不能超过50个字符"
-        })));
-    }
-    
-    // 验证最大玩家数
-    if request.max_players < 2 || request.max_players > 16 {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "invalid_max_players",
-            "message": "最大玩家数必须在2-16之间"
-        })));
-    }
-    
-    // 验证颜色格式 (简单的十六进制颜色验证)
-    if !request.room_color.starts_with('#') || request.room_color.len() != 7 {
+    let host = match auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    // 验证房间设置
+    if let Err((error, message)) = validate_room_settings(
+        Some(&request.name),
+        Some(request.max_players),
+        Some(&request.room_color),
+        request.password.as_deref(),
+    ) {
         return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "invalid_color",
-            "message": "颜色格式无效，请使用十六进制格式如 #FF5733"
+            "error": error,
+            "message": message
         })));
     }
-    
-    // 验证是否包含禁用内容
-    if request.name.to_lowercase().contains("ek") {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "forbidden_content",
-            "message": "房间名称包含禁用内容"
+
+    // 常驻房间不会被自动清理，只允许管理员创建
+    if request.is_persistent && !auth::is_admin(&host.id) {
+        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "forbidden",
+            "message": "只有管理员可以创建常驻房间"
         })));
     }
-    
-    // 验证密码（如果提供）
-    if let Some(password) = &request.password {
-        if password.len() > 20 {
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "password_too_long",
-                "message": "密码不能超过20个字符"
-            })));
-        }
-    }
 
     // 确定房间ID
     let room_id_str = match &request.room_id {
@@ -125,16 +159,31 @@ pub async fn create_room(
         None => generate_room_id(),
     };
 
+    // 密码哈希计算量较大，放到阻塞线程池中执行，GameServer 只保存结果
+    let password_hash = match request.password.clone().filter(|p| !p.is_empty()) {
+        Some(password) => match web::block(move || auth::hash_password(&password)).await? {
+            Ok(hash) => Some(hash),
+            Err(_) => {
+                return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "server_error",
+                    "message": "设置房间密码失败"
+                })));
+            }
+        },
+        None => None,
+    };
+
     // 创建房间消息
     let create_room_msg = CreateRoom {
         room_id: Some(room_id_str),
         name: request.name.clone(),
         max_players: request.max_players,
         room_color: request.room_color.clone(),
-        host_name: request.host_name.clone(),
-        host_id: request.host_id.clone(),
-        password: request.password.clone(),
+        host_name: host.username.clone(),
+        host_id: host.id.clone(),
+        password_hash,
         is_public: request.is_public,
+        is_persistent: request.is_persistent,
     };
 
     // 发送消息到GameServer
@@ -146,15 +195,15 @@ pub async fn create_room(
                 name: request.name.clone(),
                 max_players: request.max_players,
                 room_color: request.room_color.clone(),
-                host_name: request.host_name.clone(),
+                host_name: host.username.clone(),
                 status: "waiting".to_string(),
                 message: "房间创建成功".to_string(),
             }))
         }
         Ok(Err(e)) => {
-            // GameServer返回错误（例如，房间ID已存在）
+            // GameServer返回错误（例如，房间ID已存在或常驻房间已达上限）
             Ok(HttpResponse::Conflict().json(serde_json::json!({
-                "error": "room_exists",
+                "error": "create_room_failed",
                 "message": e
             })))
         }
@@ -0,0 +1,348 @@
+use actix::prelude::*;
+use actix_web::{web, get, post, delete, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::models::friend::{Friendship, NewFriendship};
+use crate::models::user::User;
+use crate::services::auth;
+use crate::services::user::DbPool;
+use crate::services::ws::{GameServer, FriendshipChanged, GetPresence, Presence};
+
+#[derive(Deserialize)]
+pub struct FriendRequestInput {
+    pub username: String,
+}
+
+#[derive(Serialize)]
+pub struct FriendInfo {
+    pub user_id: String,
+    pub username: String,
+    pub presence: Option<Presence>, // 只有已确认的好友才返回在线状态
+    pub since: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct FriendListResponse {
+    pub friends: Vec<FriendInfo>,
+    pub incoming: Vec<FriendInfo>, // 收到的好友请求
+    pub outgoing: Vec<FriendInfo>, // 已发出、等待对方处理的请求
+}
+
+// 好友请求的处理结果
+enum RequestOutcome {
+    Sent(User),
+    Accepted(User), // 对方已向自己发出过请求，直接成为好友
+}
+
+fn error_response(status: actix_web::http::StatusCode, error: &str, message: &str) -> HttpResponse {
+    HttpResponse::build(status).json(serde_json::json!({
+        "error": error,
+        "message": message
+    }))
+}
+
+// 获取好友列表、收到的请求和发出的请求
+// GET /api/friends
+#[get("")]
+pub async fn list_friends(
+    pool: web::Data<DbPool>,
+    game_server: web::Data<Addr<GameServer>>,
+    req: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = match auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let user_id = user.id.clone();
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+        let friendships = Friendship::list_for_user(&mut conn, &user_id)?;
+        let other_ids: Vec<String> = friendships.iter()
+            .map(|friendship| friendship.other(&user_id).to_string())
+            .collect();
+        let usernames: HashMap<String, String> = crate::schema::users::table
+            .filter(crate::schema::users::id.eq_any(&other_ids))
+            .select((crate::schema::users::id, crate::schema::users::username))
+            .load::<(String, String)>(&mut conn)?
+            .into_iter()
+            .collect();
+        Ok::<_, diesel::result::Error>((friendships, usernames))
+    })
+    .await?;
+
+    let (friendships, usernames) = match result {
+        Ok(result) => result,
+        Err(_) => {
+            return Ok(error_response(
+                actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+                "服务器错误",
+                "获取好友列表时发生内部错误",
+            ));
+        }
+    };
+
+    let accepted_ids: Vec<String> = friendships.iter()
+        .filter(|friendship| friendship.status == "accepted")
+        .map(|friendship| friendship.other(&user.id).to_string())
+        .collect();
+    let mut presences = game_server.send(GetPresence { user_ids: accepted_ids }).await.unwrap_or_default();
+
+    let mut response = FriendListResponse {
+        friends: Vec::new(),
+        incoming: Vec::new(),
+        outgoing: Vec::new(),
+    };
+    for friendship in friendships {
+        let other_id = friendship.other(&user.id).to_string();
+        let info = FriendInfo {
+            username: usernames.get(&other_id).cloned().unwrap_or_else(|| "Unknown".to_string()),
+            presence: presences.remove(&other_id),
+            user_id: other_id,
+            since: friendship.created_at,
+        };
+        if friendship.status == "accepted" {
+            response.friends.push(info);
+        } else if friendship.user_id == user.id {
+            response.outgoing.push(info);
+        } else {
+            response.incoming.push(info);
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// 按用户名发送好友请求；如果对方已向自己发出请求，则直接成为好友
+// POST /api/friends/request
+#[post("/request")]
+pub async fn send_friend_request(
+    pool: web::Data<DbPool>,
+    game_server: web::Data<Addr<GameServer>>,
+    req: HttpRequest,
+    input: web::Json<FriendRequestInput>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = match auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let user_id = user.id.clone();
+    let target_username = input.username.trim().to_string();
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let target = crate::schema::users::table
+                .filter(crate::schema::users::username.eq(&target_username))
+                .first::<User>(conn)?;
+            if target.id == user_id {
+                return Err(diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::CheckViolation,
+                    Box::new("不能添加自己为好友".to_string())
+                ));
+            }
+
+            match Friendship::find_between(conn, &user_id, &target.id)? {
+                Some(friendship) if friendship.status == "accepted" => Err(diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::UniqueViolation,
+                    Box::new("你们已经是好友了".to_string())
+                )),
+                Some(friendship) if friendship.user_id == user_id => Err(diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::UniqueViolation,
+                    Box::new("已经发送过好友请求，请等待对方处理".to_string())
+                )),
+                Some(friendship) => {
+                    diesel::update(crate::schema::friends::table.find((&friendship.user_id, &friendship.friend_id)))
+                        .set(crate::schema::friends::status.eq("accepted"))
+                        .execute(conn)?;
+                    Ok(RequestOutcome::Accepted(target))
+                }
+                None => {
+                    let inserted = diesel::insert_into(crate::schema::friends::table)
+                        .values(&NewFriendship {
+                            user_id: &user_id,
+                            friend_id: &target.id,
+                            status: "pending",
+                        })
+                        .execute(conn);
+                    match inserted {
+                        Ok(_) => Ok(RequestOutcome::Sent(target)),
+                        // 对方同时发出了请求，违反唯一约束时直接接受对方的请求
+                        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+                            let accepted = diesel::update(crate::schema::friends::table.find((&target.id, &user_id)))
+                                .set(crate::schema::friends::status.eq("accepted"))
+                                .execute(conn)?;
+                            if accepted == 0 {
+                                return Err(diesel::result::Error::DatabaseError(
+                                    diesel::result::DatabaseErrorKind::UniqueViolation,
+                                    Box::new("已经发送过好友请求，请等待对方处理".to_string())
+                                ));
+                            }
+                            Ok(RequestOutcome::Accepted(target))
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+            }
+        })
+    })
+    .await?;
+
+    match result {
+        Ok(outcome) => {
+            let (target, event) = match outcome {
+                RequestOutcome::Sent(target) => (target, "request"),
+                RequestOutcome::Accepted(target) => (target, "accepted"),
+            };
+            println!("用户 {} 向 {} 发送好友请求 ({})", user.id, target.id, event);
+            game_server.do_send(FriendshipChanged {
+                user_id: user.id.clone(),
+                username: user.username.clone(),
+                friend_id: target.id.clone(),
+                event: event.to_string(),
+            });
+            Ok(HttpResponse::Ok().json(serde_json::json!({
+                "user_id": target.id,
+                "username": target.username,
+                "status": if event == "accepted" { "accepted" } else { "pending" },
+            })))
+        }
+        Err(diesel::NotFound) => Ok(error_response(
+            actix_web::http::StatusCode::NOT_FOUND,
+            "用户不存在",
+            "找不到该用户名对应的用户",
+        )),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, info)) => Ok(error_response(
+            actix_web::http::StatusCode::CONFLICT,
+            "好友请求失败",
+            info.message(),
+        )),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::CheckViolation, info)) => Ok(error_response(
+            actix_web::http::StatusCode::BAD_REQUEST,
+            "好友请求失败",
+            info.message(),
+        )),
+        Err(_) => Ok(error_response(
+            actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+            "服务器错误",
+            "发送好友请求时发生内部错误",
+        )),
+    }
+}
+
+// 接受对方发来的好友请求
+// POST /api/friends/{user_id}/accept
+#[post("/{user_id}/accept")]
+pub async fn accept_friend_request(
+    pool: web::Data<DbPool>,
+    game_server: web::Data<Addr<GameServer>>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = match auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let requester_id = path.into_inner();
+    let query_requester_id = requester_id.clone();
+    let current_user_id = user.id.clone();
+    let result = web::block(move || {
+        use crate::schema::friends::dsl::*;
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+        diesel::update(friends.find((&query_requester_id, &current_user_id)).filter(status.eq("pending")))
+            .set(status.eq("accepted"))
+            .execute(&mut conn)
+    })
+    .await?;
+
+    match result {
+        Ok(0) => Ok(error_response(
+            actix_web::http::StatusCode::NOT_FOUND,
+            "好友请求不存在",
+            "没有找到该用户发来的好友请求",
+        )),
+        Ok(_) => {
+            println!("用户 {} 接受了 {} 的好友请求", user.id, requester_id);
+            game_server.do_send(FriendshipChanged {
+                user_id: user.id.clone(),
+                username: user.username.clone(),
+                friend_id: requester_id.clone(),
+                event: "accepted".to_string(),
+            });
+            let presence = game_server.send(GetPresence { user_ids: vec![requester_id.clone()] }).await
+                .ok()
+                .and_then(|mut presences| presences.remove(&requester_id));
+            Ok(HttpResponse::Ok().json(serde_json::json!({
+                "user_id": requester_id,
+                "status": "accepted",
+                "presence": presence,
+            })))
+        }
+        Err(_) => Ok(error_response(
+            actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+            "服务器错误",
+            "接受好友请求时发生内部错误",
+        )),
+    }
+}
+
+// 删除好友，也用于拒绝收到的请求或撤回发出的请求
+// DELETE /api/friends/{user_id}
+#[delete("/{user_id}")]
+pub async fn remove_friend(
+    pool: web::Data<DbPool>,
+    game_server: web::Data<Addr<GameServer>>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = match auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let other_id = path.into_inner();
+    let query_other_id = other_id.clone();
+    let current_user_id = user.id.clone();
+    let result = web::block(move || {
+        use crate::schema::friends::dsl::*;
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+        diesel::delete(friends.filter(
+            user_id.eq(&current_user_id).and(friend_id.eq(&query_other_id))
+                .or(user_id.eq(&query_other_id).and(friend_id.eq(&current_user_id)))
+        ))
+        .execute(&mut conn)
+    })
+    .await?;
+
+    match result {
+        Ok(0) => Ok(error_response(
+            actix_web::http::StatusCode::NOT_FOUND,
+            "好友不存在",
+            "你们之间没有好友关系或好友请求",
+        )),
+        Ok(_) => {
+            println!("用户 {} 删除了与 {} 的好友关系", user.id, other_id);
+            game_server.do_send(FriendshipChanged {
+                user_id: user.id.clone(),
+                username: user.username.clone(),
+                friend_id: other_id,
+                event: "removed".to_string(),
+            });
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Err(_) => Ok(error_response(
+            actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+            "服务器错误",
+            "删除好友时发生内部错误",
+        )),
+    }
+}
+
+pub fn endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_friends)
+        .service(send_friend_request)
+        .service(accept_friend_request)
+        .service(remove_friend);
+}
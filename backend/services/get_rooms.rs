@@ -1,15 +1,53 @@
 use actix::prelude::*;
 use actix_web::{web, HttpResponse, Result, get};
 use serde::{Deserialize, Serialize};
-use crate::services::ws::{GameServer, GetRoomList};
+use crate::services::ws::{GameServer, GetRoomList, RoomListEntry};
 
 #[derive(Deserialize)]
 pub struct GetRoomsQuery {
     pub start: Option<usize>,
     pub end: Option<usize>,
+    pub status: Option<String>,      // 只返回指定状态的房间，例如 waiting / playing
+    pub has_password: Option<bool>,  // true 只返回有密码的房间，false 只返回无密码的房间
+    pub has_free_slots: Option<bool>, // true 只返回未满的房间
+    pub search: Option<String>,      // 按房间名称或房间ID搜索（不区分大小写）
+    pub sort: Option<String>,        // players（默认，人数多的在前）/ free_slots / name / activity（最近活跃的在前）
 }
 
-#[derive(Serialize)]
+impl GetRoomsQuery {
+    // 判断房间是否满足筛选条件（只包含公开房间）
+    fn matches(&self, room: &RoomListEntry) -> bool {
+        if !room.is_public {
+            return false;
+        }
+        if let Some(status) = &self.status {
+            if &room.status != status {
+                return false;
+            }
+        }
+        if let Some(has_password) = self.has_password {
+            if room.has_password != has_password {
+                return false;
+            }
+        }
+        if self.has_free_slots == Some(true) && room.player_count >= room.max_players {
+            return false;
+        }
+        if let Some(search) = self.search.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            let search = search.to_lowercase();
+            if !room.name.to_lowercase().contains(&search) && !room.room_id.to_lowercase().contains(&search) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 支持的排序方式
+const SORT_OPTIONS: [&str; 4] = ["players", "free_slots", "name", "activity"];
+
+#[tsync::tsync]
+#[derive(Clone, Debug, Serialize)]
 pub struct RoomInfo {
     pub room_id: String, // 保持为String类型以支持自定义房间ID
     pub name: String,
@@ -21,6 +59,27 @@ pub struct RoomInfo {
     pub required_to_start: usize,
     pub is_active: bool,
     pub has_password: bool, // 新增：是否有密码
+    pub game_turn: Option<u32>,       // 对局进行中时的当前回合
+    pub alive_players: Option<usize>, // 对局进行中时尚未被淘汰的玩家数，方便选择观战的对局
+}
+
+impl From<RoomListEntry> for RoomInfo {
+    fn from(room: RoomListEntry) -> Self {
+        RoomInfo {
+            room_id: room.room_id, // 直接使用String类型的room_id
+            name: room.name,
+            host_name: room.host_name,
+            status: room.status,
+            player_count: room.player_count,
+            max_players: room.max_players,
+            room_color: room.room_color,
+            required_to_start: room.required_to_start,
+            is_active: room.player_count > 0,
+            has_password: room.has_password,
+            game_turn: room.game_turn,
+            alive_players: room.alive_players,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -32,8 +91,8 @@ pub struct GetRoomsResponse {
     pub has_more: bool,
 }
 
-/// GET /api/getRooms?start=a&end=b
-/// 获取房间列表，支持分页
+/// GET /api/getRooms?start=a&end=b&status=&has_password=&has_free_slots=&search=&sort=
+/// 获取房间列表，支持筛选、搜索、排序和分页
 #[get("/getRooms")]
 pub async fn get_rooms(
     game_server: web::Data<Addr<GameServer>>,
@@ -56,32 +115,43 @@ pub async fn get_rooms(
             "message": "单次查询最多返回100个房间"
         })));
     }
+
+    let sort = query.sort.as_deref().unwrap_or("players");
+    if !SORT_OPTIONS.contains(&sort) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "invalid_sort",
+            "message": format!("不支持的排序方式，可选: {}", SORT_OPTIONS.join(", "))
+        })));
+    }
     
     // 向GameServer请求房间列表
     match game_server.send(GetRoomList).await {
         Ok(room_data) => {
-            let total_count = room_data.len();
+            // 先筛选再统计总数，total_count 只包含符合条件的公开房间
+            let mut rooms: Vec<RoomListEntry> = room_data
+                .into_iter()
+                .filter(|room| query.matches(room))
+                .collect();
+            let total_count = rooms.len();
+
+            // 排序，相同时按房间ID保证分页结果稳定
+            rooms.sort_by(|a, b| {
+                let order = match sort {
+                    "free_slots" => (b.max_players.saturating_sub(b.player_count))
+                        .cmp(&a.max_players.saturating_sub(a.player_count)),
+                    "name" => a.name.cmp(&b.name),
+                    "activity" => b.last_activity.cmp(&a.last_activity),
+                    _ => b.player_count.cmp(&a.player_count),
+                };
+                order.then_with(|| a.room_id.cmp(&b.room_id))
+            });
             
             // 应用分页
-            let paginated_rooms: Vec<RoomInfo> = room_data
+            let paginated_rooms: Vec<RoomInfo> = rooms
                 .into_iter()
-                .filter(|(_, _, _, _, _, _, _, _, _, is_public, _)| *is_public) // 只包含公开房间
                 .skip(start)
                 .take(end - start)
-                .map(|(room_id, name, host_name, status, player_count, max_players, room_color, _force_start_count, required_to_start, _, has_password)| {
-                    RoomInfo {
-                        room_id, // 直接使用String类型的room_id
-                        name,
-                        host_name,
-                        status,
-                        player_count,
-                        max_players,
-                        room_color,
-                        required_to_start,
-                        is_active: player_count > 0,
-                        has_password,
-                    }
-                })
+                .map(RoomInfo::from)
                 .collect();
             
             let actual_end = std::cmp::min(end, total_count);
@@ -0,0 +1,276 @@
+use actix::prelude::*;
+use actix_web::web;
+use diesel::prelude::*;
+use std::time::{Duration, Instant};
+use crate::services::user::DbPool;
+use crate::services::ws::{GameServer, CreateRoom, StartRankedMatch};
+
+/// Elo 计算中的 K 值，决定每局分数变化幅度
+const ELO_K_FACTOR: f64 = 32.0;
+/// 刚进入队列时允许的最大分差
+const BASE_RATING_WINDOW: i32 = 50;
+/// 每等待一秒放宽的分差
+const RATING_WINDOW_GROWTH_PER_SEC: i32 = 10;
+/// 分差上限，等待再久也不会匹配分差更大的对手
+const MAX_RATING_WINDOW: i32 = 500;
+/// 尝试配对的间隔
+const MATCH_INTERVAL: Duration = Duration::from_secs(1);
+/// 排位房间的颜色
+const RANKED_ROOM_COLOR: &str = "#DC2626";
+
+/// 按 Elo 规则计算胜者和败者的新分数
+pub fn elo_update(winner_rating: i32, loser_rating: i32) -> (i32, i32) {
+    let expected = 1.0 / (1.0 + 10f64.powf((loser_rating - winner_rating) as f64 / 400.0));
+    let delta = (ELO_K_FACTOR * (1.0 - expected)).round() as i32;
+    (winner_rating + delta, loser_rating - delta)
+}
+
+// 匹配队列中的玩家
+struct QueueEntry {
+    user_id: String,
+    username: String,
+    rating: i32,
+    joined_at: Instant,
+    recipient: Recipient<QueueStatus>,
+}
+
+impl QueueEntry {
+    // 当前可接受的分差，随等待时间放宽
+    fn rating_window(&self) -> i32 {
+        let waited = self.joined_at.elapsed().as_secs() as i32;
+        (BASE_RATING_WINDOW + waited * RATING_WINDOW_GROWTH_PER_SEC).min(MAX_RATING_WINDOW)
+    }
+
+    fn send_status(&self, status: &str) {
+        let _ = self.recipient.do_send(QueueStatus {
+            status: status.to_string(),
+            rating: Some(self.rating),
+        });
+    }
+}
+
+// 1v1 排位匹配：按排位分配对，配对成功后通过 CreateRoom 创建私人房间并自动开始对局
+pub struct Matchmaker {
+    game_server: Addr<GameServer>,
+    db_pool: DbPool,
+    queue: Vec<QueueEntry>,
+}
+
+impl Matchmaker {
+    pub fn new(game_server: Addr<GameServer>, db_pool: DbPool) -> Self {
+        Matchmaker {
+            game_server,
+            db_pool,
+            queue: Vec::new(),
+        }
+    }
+
+    fn is_queued(&self, user_id: &str) -> bool {
+        self.queue.iter().any(|entry| entry.user_id == user_id)
+    }
+
+    // 按进入队列的先后顺序寻找双方分差都在各自范围内的对手
+    fn find_pair(&self) -> Option<(usize, usize)> {
+        for (i, a) in self.queue.iter().enumerate() {
+            for (j, b) in self.queue.iter().enumerate().skip(i + 1) {
+                let diff = (a.rating - b.rating).abs();
+                if diff <= a.rating_window() && diff <= b.rating_window() {
+                    return Some((i, j));
+                }
+            }
+        }
+        None
+    }
+
+    // 为配对成功的两名玩家创建排位房间
+    fn start_match(&mut self, a: QueueEntry, b: QueueEntry, ctx: &mut Context<Self>) {
+        println!("排位匹配成功: {}({}) vs {}({})", a.username, a.rating, b.username, b.rating);
+        a.send_status("matched");
+        b.send_status("matched");
+        let players = vec![
+            (a.user_id.clone(), a.username.clone(), a.rating),
+            (b.user_id.clone(), b.username.clone(), b.rating),
+        ];
+
+        let create_room = CreateRoom {
+            room_id: None,
+            name: format!("排位赛 {} vs {}", a.username, b.username),
+            max_players: 2,
+            room_color: RANKED_ROOM_COLOR.to_string(),
+            host_name: a.username.clone(),
+            host_id: a.user_id.clone(),
+            password_hash: None,
+            is_public: false,
+            is_persistent: false,
+        };
+
+        self.game_server
+            .send(create_room)
+            .into_actor(self)
+            .then(move |res, act, ctx| {
+                match res {
+                    Ok(Ok(room_id)) => {
+                        act.start_ranked_room(room_id, players, a, b, ctx);
+                    }
+                    Ok(Err(e)) => {
+                        println!("创建排位房间失败: {}", e);
+                        a.send_status("failed");
+                        b.send_status("failed");
+                    }
+                    Err(_) => println!("创建排位房间失败: GameServer 无响应"),
+                }
+                fut::ready(())
+            })
+            .spawn(ctx);
+    }
+
+    // 房间创建后开始对局，有玩家已离线或进入其他对局时，将另一方按原来的排队时间放回队列
+    fn start_ranked_room(&mut self, room_id: String, players: Vec<(String, String, i32)>, a: QueueEntry, b: QueueEntry, ctx: &mut Context<Self>) {
+        self.game_server
+            .send(StartRankedMatch { room_id, players })
+            .into_actor(self)
+            .then(move |res, act, _ctx| {
+                if let Ok(Err(unavailable)) = res {
+                    for entry in [a, b] {
+                        if unavailable.contains(&entry.user_id) {
+                            entry.send_status("failed");
+                        } else if entry.recipient.connected() && !act.is_queued(&entry.user_id) {
+                            println!("对手无法开始对局，玩家 {} 重新加入排位匹配队列", entry.user_id);
+                            entry.send_status("requeued");
+                            act.queue.push(entry);
+                        }
+                    }
+                }
+                fut::ready(())
+            })
+            .spawn(ctx);
+    }
+}
+
+impl Actor for Matchmaker {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        println!("Matchmaker started");
+
+        // 定时尝试配对，等待越久分差范围越大
+        ctx.run_interval(MATCH_INTERVAL, |_act, ctx| {
+            ctx.address().do_send(MatchTick);
+        });
+    }
+}
+
+pub fn create_matchmaker(game_server: Addr<GameServer>, db_pool: DbPool) -> Addr<Matchmaker> {
+    Matchmaker::new(game_server, db_pool).start()
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct JoinQueue {
+    pub user_id: String,
+    pub username: String,
+    pub recipient: Recipient<QueueStatus>,
+}
+
+// 推送给玩家会话的匹配状态
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct QueueStatus {
+    pub status: String, // searching / matched / requeued / left / failed / guest
+    pub rating: Option<i32>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct LeaveQueue {
+    pub user_id: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+struct MatchTick;
+
+impl Handler<JoinQueue> for Matchmaker {
+    type Result = ();
+
+    fn handle(&mut self, msg: JoinQueue, ctx: &mut Context<Self>) {
+        if self.is_queued(&msg.user_id) {
+            return;
+        }
+
+        // 从数据库读取排位分后再加入队列，游客不能参加排位
+        let pool = self.db_pool.clone();
+        let user_id = msg.user_id.clone();
+        let load_rating = web::block(move || {
+            use crate::schema::users::dsl::*;
+            let mut conn = pool.get().expect("couldn't get db connection from pool");
+            users.find(&user_id).select((rating, is_guest)).first::<(i32, bool)>(&mut conn)
+        });
+
+        load_rating
+            .into_actor(self)
+            .then(move |res, act, _ctx| {
+                match res {
+                    Ok(Ok((_, true))) => {
+                        println!("游客 {} 尝试加入排位匹配队列，已拒绝", msg.user_id);
+                        let _ = msg.recipient.do_send(QueueStatus {
+                            status: "guest".to_string(),
+                            rating: None,
+                        });
+                    }
+                    Ok(Ok((player_rating, false))) => {
+                        // 读取期间可能已经重复加入
+                        if !act.is_queued(&msg.user_id) {
+                            let entry = QueueEntry {
+                                user_id: msg.user_id,
+                                username: msg.username,
+                                rating: player_rating,
+                                joined_at: Instant::now(),
+                                recipient: msg.recipient,
+                            };
+                            println!("玩家 {} 加入排位匹配队列，排位分: {}", entry.user_id, entry.rating);
+                            entry.send_status("searching");
+                            act.queue.push(entry);
+                        }
+                    }
+                    _ => {
+                        println!("读取玩家 {} 的排位分失败", msg.user_id);
+                        let _ = msg.recipient.do_send(QueueStatus {
+                            status: "failed".to_string(),
+                            rating: None,
+                        });
+                    }
+                }
+                fut::ready(())
+            })
+            .spawn(ctx);
+    }
+}
+
+impl Handler<LeaveQueue> for Matchmaker {
+    type Result = ();
+
+    fn handle(&mut self, msg: LeaveQueue, _: &mut Context<Self>) {
+        if let Some(index) = self.queue.iter().position(|entry| entry.user_id == msg.user_id) {
+            let entry = self.queue.remove(index);
+            println!("玩家 {} 离开排位匹配队列", entry.user_id);
+            entry.send_status("left");
+        }
+    }
+}
+
+impl Handler<MatchTick> for Matchmaker {
+    type Result = ();
+
+    fn handle(&mut self, _msg: MatchTick, ctx: &mut Context<Self>) {
+        // 移除会话已断开的玩家
+        self.queue.retain(|entry| entry.recipient.connected());
+
+        while let Some((i, j)) = self.find_pair() {
+            // 先移除靠后的元素，避免下标错位
+            let b = self.queue.remove(j);
+            let a = self.queue.remove(i);
+            self.start_match(a, b, ctx);
+        }
+    }
+}
@@ -1,4 +1,12 @@
 pub mod user;
+pub mod auth;
+pub mod rate_limit;
+pub mod content_filter;
+pub mod chat;
+pub mod friends;
 pub mod ws;
+pub mod protocol;
+pub mod matchmaking;
 pub mod get_rooms;
-pub mod create_room;
\ No newline at end of file
+pub mod create_room;
+pub mod quick_play;
\ No newline at end of file
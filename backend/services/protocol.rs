@@ -0,0 +1,500 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::models::chat::ChatMessage;
+use crate::services::get_rooms::RoomInfo;
+use crate::services::ws::Presence;
+
+// 全局 WebSocket 的消息协议，通过 `cargo tsync` 导出到 frontend/src/types/rust.d.ts，前后端共用同一份定义
+
+/// 当前服务器使用的协议版本，消息格式出现不兼容的修改时递增
+pub const PROTOCOL_VERSION: u32 = 2;
+/// 服务器仍然兼容的最低协议版本，不发送 hello 的旧客户端视为版本 1，会被拒绝
+pub const MIN_PROTOCOL_VERSION: u32 = 2;
+/// 连接建立后等待 hello 的时间（秒），超时未协商的客户端视为旧客户端
+pub const HELLO_TIMEOUT_SECS: u64 = 10;
+/// 服务器支持的可选功能，实际启用的是与客户端声明的功能的交集
+pub const SERVER_FEATURES: &[&str] = &["lobby", "invites", "ranked", "quick_play", MAP_DELTA_FEATURE, MSGPACK_FEATURE];
+/// 启用后地图只发送变化的格子，见 ServerMessage::MapDelta
+pub const MAP_DELTA_FEATURE: &str = "map_delta";
+/// 启用后地图和回合更新以 MessagePack 二进制帧发送，见 ServerMessage::is_game_traffic
+pub const MSGPACK_FEATURE: &str = "msgpack";
+/// 二进制帧中的格子类型代码即在此表中的下标，与前端 utils/msgpack.ts 中的 TILE_KINDS 保持一致
+pub const TILE_KINDS: &[&str] = &["unknown", "w", "t", "m", "g", "v", "c_settlement", "c_smallcity", "c_largecity"];
+
+/// 客户端发送给服务器的消息
+#[tsync::tsync]
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// 连接建立后声明客户端使用的协议版本和支持的功能
+    Hello {
+        protocol_version: u32,
+        features: Option<Vec<String>>,
+    },
+    JoinRoom {
+        room_id: String,
+        player_name: String,
+        password: Option<String>,
+        invite_token: Option<String>, // 邀请 token，有效时跳过密码检查
+    },
+    LeaveRoom {
+        room_id: String,
+    },
+    #[serde(alias = "chat_message")]
+    Chat {
+        room_id: String,
+        #[serde(alias = "message")]
+        content: String,
+        channel: Option<String>, // team / spectator，不填或 all 为全体
+    },
+    /// 在地图上放置标记
+    Ping {
+        room_id: String,
+        x: usize,
+        y: usize,
+        marker: String, // attack / defend / danger
+    },
+    Whisper {
+        target_player_name: String,
+        #[serde(alias = "message")]
+        content: String,
+    },
+    QuickPlay,
+    JoinQueue,
+    LeaveQueue,
+    SubscribeLobby,
+    UnsubscribeLobby,
+    Invite {
+        room_id: String,
+        target_player_name: String,
+    },
+    DeclineInvite {
+        token: String,
+    },
+    CreateInviteLink {
+        room_id: String,
+    },
+    GetRoomInfo {
+        room_id: String,
+    },
+    ForceStart {
+        room_id: String,
+    },
+    DeForceStart {
+        room_id: String,
+    },
+    ShouldStart {
+        room_id: String,
+    },
+    GameAction {
+        room_id: String,
+        action: String,
+    },
+    /// 地图增量的 seq 不连续时请求完整快照，在下一次地图更新时发送
+    RequestMapSnapshot {
+        room_id: String,
+    },
+    GameMove {
+        room_id: String,
+        from_x: usize,
+        from_y: usize,
+        to_x: usize,
+        to_y: usize,
+        move_id: usize,
+        is_half_move: Option<bool>, // 是否为分半移动，默认否
+    },
+    SetAdmin {
+        room_id: String,
+        target_player_name: String,
+    },
+    RemoveAdmin {
+        room_id: String,
+    },
+    KickPlayer {
+        room_id: String,
+        target_player_name: String,
+    },
+    MutePlayer {
+        room_id: String,
+        target_player_name: String,
+        duration_minutes: Option<u64>,
+    },
+    UnmutePlayer {
+        room_id: String,
+        target_player_name: String,
+    },
+    ChangeGroup {
+        room_id: String,
+        target_group_id: usize,
+    },
+    /// 只修改提供了的字段
+    UpdateRoomSettings {
+        room_id: String,
+        name: Option<String>,
+        max_players: Option<usize>,
+        room_color: Option<String>,
+        #[serde(default, deserialize_with = "present")]
+        password: Option<Option<String>>, // 提供了该字段时：null 或空字符串表示移除密码
+        is_public: Option<bool>,
+        is_persistent: Option<bool>,
+    },
+}
+
+// 区分字段缺失（None）和字段为 null（Some(None)）
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// 无法解析的客户端消息
+#[derive(Debug)]
+pub struct DecodeError {
+    pub error: &'static str, // invalid_json / missing_type / invalid_message
+    pub message: String,
+    pub request_type: Option<String>,
+}
+
+impl ClientMessage {
+    pub fn parse(text: &str) -> Result<ClientMessage, DecodeError> {
+        let value: serde_json::Value = serde_json::from_str(text).map_err(|e| DecodeError {
+            error: "invalid_json",
+            message: format!("JSON解析失败: {}", e),
+            request_type: None,
+        })?;
+
+        let request_type = match value.get("type").and_then(|v| v.as_str()) {
+            Some(request_type) => request_type.to_string(),
+            None => {
+                return Err(DecodeError {
+                    error: "missing_type",
+                    message: "消息缺少type字段".to_string(),
+                    request_type: None,
+                })
+            }
+        };
+
+        ClientMessage::deserialize(value).map_err(|e| DecodeError {
+            error: "invalid_message",
+            message: format!("消息格式错误: {}", e),
+            request_type: Some(request_type),
+        })
+    }
+}
+
+/// 房间中一个分组的玩家
+#[tsync::tsync]
+#[derive(Clone, Debug, Serialize)]
+pub struct GroupPlayers {
+    pub id: usize,
+    pub players: Vec<String>, // 只包含用户名，颜色和名称由前端处理
+}
+
+/// 地图标记，只发送给队友和观战者
+#[tsync::tsync]
+#[derive(Clone, Debug, Serialize)]
+pub struct PingMarker {
+    pub x: usize,
+    pub y: usize,
+    pub marker: String, // attack / defend / danger
+    pub username: String,
+    pub expires_turn: u32, // 到达该回合时标记消失
+}
+
+/// 服务器发送给客户端的消息
+#[tsync::tsync]
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// 认证成功，会话已建立
+    Connected {
+        user_id: String,
+        username: String,
+        protocol_version: u32,
+        min_protocol_version: u32,
+        features: Vec<String>,
+    },
+    /// 对 hello 的回应，包含协商后的协议版本和双方都支持的功能
+    Handshake {
+        protocol_version: u32,
+        features: Vec<String>,
+    },
+    /// 客户端协议版本过旧，发送后服务器关闭连接
+    ProtocolMismatch {
+        message: String,
+        client_version: u32,
+        min_protocol_version: u32,
+        protocol_version: u32,
+    },
+    Ok,
+    Error {
+        message: String,
+    },
+    /// 客户端发送的消息无法解析
+    ProtocolError {
+        error: String, // invalid_json / missing_type / invalid_message
+        message: String,
+        request_type: Option<String>,
+    },
+    JoinRoomSuccess {
+        room_id: String,
+        message: String,
+    },
+    JoinRoomError {
+        room_id: String,
+        error: String,
+    },
+    JoinRoom {
+        room_id: String,
+        player_id: String,
+        player_name: String,
+    },
+    LeaveRoom {
+        room_id: String,
+        player_id: String,
+    },
+    RoomInfo {
+        room_id: String,
+        name: String,
+        host_player_name: String,
+        admin_player_name: Option<String>,
+        status: String,
+        players: Vec<String>,
+        player_count: usize,
+        force_start_players: Vec<String>,
+        required_to_start: usize,
+        groups: Vec<GroupPlayers>,
+        max_players: usize,
+        muted_players: Vec<String>, // 正在禁言中的玩家用户名
+    },
+    ChatMessage {
+        room_id: String,
+        user_id: String,
+        username: String,
+        message: String,
+        content: String, // 与 message 相同，兼容旧前端
+        channel: Option<String>, // team / spectator，全体消息为空
+    },
+    /// 加入房间或连接时的聊天记录
+    ChatHistory {
+        room_id: String,
+        messages: Vec<ChatMessage>,
+    },
+    Whisper {
+        sender_id: String,
+        username: String,
+        recipient_id: String,
+        recipient_name: String,
+        content: String,
+    },
+    /// 用户名被修改
+    UsernameChanged {
+        user_id: String,
+        username: String,
+    },
+    /// 好友请求、好友关系或在线状态变化
+    FriendUpdate {
+        user_id: String,
+        username: String,
+        event: String, // presence / request / accepted / removed
+        presence: Option<Presence>,
+    },
+    RoomInvite {
+        room_id: String,
+        room_name: String,
+        inviter_name: String,
+        token: String,
+        expires_at: u64, // 秒级时间戳
+    },
+    InviteLink {
+        room_id: String,
+        token: String,
+        expires_at: u64,
+    },
+    /// 排位匹配队列状态
+    QueueStatus {
+        status: String, // searching / matched / requeued / left / failed / guest
+        rating: Option<i32>,
+    },
+    MatchFound {
+        room_id: String,
+        opponent_name: String,
+        rating: i32,
+        opponent_rating: i32,
+    },
+    /// 快速开始选中的房间
+    QuickPlay {
+        room_id: String,
+        name: String,
+        created: bool,
+    },
+    /// 订阅大厅时的完整公开房间列表
+    LobbyRooms {
+        rooms: Vec<RoomInfo>,
+    },
+    RoomCreated {
+        room: RoomInfo,
+    },
+    RoomUpdated {
+        room: RoomInfo,
+    },
+    /// 房间被删除或不再公开
+    RoomRemoved {
+        room_id: String,
+    },
+    RedirectToHome {
+        reason: String,
+    },
+    RedirectToGame {
+        room_id: String,
+    },
+    StartGame {
+        room_id: String,
+    },
+    EndGame {
+        room_id: String,
+    },
+    GameTurnUpdate {
+        room_id: String,
+        turn: u32,
+        turn_half: bool,
+        actions: Vec<(String, String)>, // (player_name, action)
+    },
+    /// 完整的地图快照
+    MapUpdate {
+        room_id: String,
+        seq: u64, // 地图更新序号，每个对局内从 1 开始连续递增
+        visible_tiles: Vec<(usize, usize, String, usize, Option<String>, bool)>, // (x, y, tile_type, count, user_id, has_vision)
+        successful_move_sends: Vec<usize>, // 成功执行的 move_id
+        player_powers: Vec<(String, usize, u32, String)>, // (username, group_id, total_power, status)
+        pings: Vec<PingMarker>,
+    },
+    /// 与上一次地图更新相比变化的格子，只在协商了 map_delta 时发送
+    MapDelta {
+        room_id: String,
+        seq: u64, // 必须等于客户端持有的 seq + 1，否则客户端应请求快照
+        changed_tiles: Vec<(usize, usize, String, usize, Option<String>, bool)>, // 新出现或内容变化的格子，格式同 visible_tiles
+        removed_tiles: Vec<(usize, usize)>, // 不再出现在可见列表中的格子
+        successful_move_sends: Vec<usize>,
+        player_powers: Vec<(String, usize, u32, String)>,
+        pings: Vec<PingMarker>,
+    },
+    MoveOk,
+    GameWin {
+        room_id: String,
+        winner: String,
+    },
+    PlayerEliminated {
+        room_id: String,
+        eliminated_player: String,
+        eliminated_by: String,
+    },
+}
+
+/// 二进制帧中的格子 (x, y, 格子类型代码, 兵力, 队伍序号, 是否有视野)，队伍序号 0 表示无归属，n 表示 teams 中的第 n 项
+type PackedTile = (usize, usize, u8, usize, usize, bool);
+
+// 二进制帧中的地图消息，格子压缩为整数，其余字段与 JSON 消息相同
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum PackedMapMessage<'a> {
+    MapUpdate {
+        room_id: &'a str,
+        seq: u64,
+        teams: &'a [String], // 快照总是携带完整的队伍表
+        visible_tiles: Vec<PackedTile>,
+        successful_move_sends: &'a [usize],
+        player_powers: &'a [(String, usize, u32, String)],
+        pings: &'a [PingMarker],
+    },
+    MapDelta {
+        room_id: &'a str,
+        seq: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        teams: Option<&'a [String]>, // 只在队伍表有新增时携带
+        changed_tiles: Vec<PackedTile>,
+        removed_tiles: &'a [(usize, usize)],
+        successful_move_sends: &'a [usize],
+        player_powers: &'a [(String, usize, u32, String)],
+        pings: &'a [PingMarker],
+    },
+}
+
+// 按队伍表压缩格子，新出现的队伍追加到表的末尾
+fn pack_tiles(tiles: &[(usize, usize, String, usize, Option<String>, bool)], teams: &mut Vec<String>) -> Vec<PackedTile> {
+    tiles.iter()
+        .map(|(x, y, tile_type, count, user_id, has_vision)| {
+            let kind = TILE_KINDS.iter().position(|kind| kind == tile_type).unwrap_or(0) as u8;
+            let team = match user_id {
+                Some(user_id) => match teams.iter().position(|team| team == user_id) {
+                    Some(index) => index + 1,
+                    None => {
+                        teams.push(user_id.clone());
+                        teams.len()
+                    }
+                },
+                None => 0,
+            };
+            (*x, *y, kind, *count, team, *has_vision)
+        })
+        .collect()
+}
+
+impl ServerMessage {
+    pub fn to_text(&self) -> String {
+        serde_json::to_string(self).expect("ServerMessage 序列化失败")
+    }
+
+    // 地图消息的格子按该对局的队伍表 teams 压缩，其余消息保留字段名，解码后的结构与 JSON 相同
+    pub fn to_msgpack(&self, teams: &mut Vec<String>) -> Vec<u8> {
+        let result = match self {
+            ServerMessage::MapUpdate { room_id, seq, visible_tiles, successful_move_sends, player_powers, pings } => {
+                let visible_tiles = pack_tiles(visible_tiles, teams);
+                rmp_serde::to_vec_named(&PackedMapMessage::MapUpdate {
+                    room_id,
+                    seq: *seq,
+                    teams,
+                    visible_tiles,
+                    successful_move_sends,
+                    player_powers,
+                    pings,
+                })
+            }
+            ServerMessage::MapDelta { room_id, seq, changed_tiles, removed_tiles, successful_move_sends, player_powers, pings } => {
+                let known_teams = teams.len();
+                let changed_tiles = pack_tiles(changed_tiles, teams);
+                rmp_serde::to_vec_named(&PackedMapMessage::MapDelta {
+                    room_id,
+                    seq: *seq,
+                    teams: (teams.len() > known_teams).then_some(teams.as_slice()),
+                    changed_tiles,
+                    removed_tiles,
+                    successful_move_sends,
+                    player_powers,
+                    pings,
+                })
+            }
+            _ => rmp_serde::to_vec_named(self),
+        };
+        result.expect("ServerMessage 序列化失败")
+    }
+
+    /// 对局中每半回合发送的高频消息，协商了 msgpack 时改用二进制帧
+    pub fn is_game_traffic(&self) -> bool {
+        matches!(
+            self,
+            ServerMessage::MapUpdate { .. } | ServerMessage::MapDelta { .. } | ServerMessage::GameTurnUpdate { .. }
+        )
+    }
+}
+
+impl From<DecodeError> for ServerMessage {
+    fn from(error: DecodeError) -> Self {
+        ServerMessage::ProtocolError {
+            error: error.error.to_string(),
+            message: error.message,
+            request_type: error.request_type,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use actix::prelude::*;
+use actix_web::{web, HttpRequest, HttpResponse, Result, post};
+use serde::Serialize;
+use crate::services::ws::{GameServer, GetRoomList, CreateRoom};
+use crate::services::auth;
+use crate::services::user::DbPool;
+
+/// 快速开始创建的房间使用的默认设置
+const DEFAULT_MAX_PLAYERS: usize = 8;
+const DEFAULT_ROOM_COLOR: &str = "#4F46E5";
+
+#[derive(Serialize)]
+pub struct QuickPlayResponse {
+    pub room_id: String,
+    pub name: String,
+    pub created: bool, // 没有合适的房间时新建了房间
+}
+
+/// 选择人数最多的公开、无密码、等待中且未满的房间，没有则以默认设置创建新房间
+pub async fn find_or_create_room(
+    game_server: &Addr<GameServer>,
+    host_id: &str,
+    host_name: &str,
+) -> Result<QuickPlayResponse, String> {
+    let room_list = game_server.send(GetRoomList).await
+        .map_err(|_| "获取房间列表失败".to_string())?;
+
+    let best_room = room_list
+        .into_iter()
+        .filter(|room| {
+            room.is_public && !room.has_password && room.status == "waiting" && room.player_count < room.max_players
+        })
+        .max_by_key(|room| room.player_count);
+
+    if let Some(room) = best_room {
+        return Ok(QuickPlayResponse {
+            room_id: room.room_id,
+            name: room.name,
+            created: false,
+        });
+    }
+
+    let name = format!("{} 的房间", host_name);
+    let room_id = game_server
+        .send(CreateRoom {
+            room_id: None,
+            name: name.clone(),
+            max_players: DEFAULT_MAX_PLAYERS,
+            room_color: DEFAULT_ROOM_COLOR.to_string(),
+            host_name: host_name.to_string(),
+            host_id: host_id.to_string(),
+            password_hash: None,
+            is_public: true,
+            is_persistent: false,
+        })
+        .await
+        .map_err(|_| "创建房间失败".to_string())??;
+
+    println!("快速开始: 没有合适的房间，为 {} 创建房间 {}", host_id, room_id);
+    Ok(QuickPlayResponse {
+        room_id,
+        name,
+        created: true,
+    })
+}
+
+/// POST /api/quickPlay
+/// 快速开始：返回要加入的房间，客户端随后正常加入该房间
+#[post("/quickPlay")]
+pub async fn quick_play(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    game_server: web::Data<Addr<GameServer>>,
+) -> Result<HttpResponse> {
+    let user = match auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    match find_or_create_room(game_server.get_ref(), &user.id, &user.username).await {
+        Ok(response) => Ok(HttpResponse::Ok().json(response)),
+        Err(message) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "quick_play_failed",
+            "message": message
+        }))),
+    }
+}
@@ -0,0 +1,51 @@
+use actix_web::HttpRequest;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 超过该数量的键时清理已过期的计数，避免无限增长
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// 固定窗口计数器：同一个键（IP、用户ID等）在窗口内最多记录 limit 次
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    entries: Mutex<HashMap<String, (u32, Instant)>>, // key -> (窗口内次数, 窗口开始时间)
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        RateLimiter {
+            limit,
+            window,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 记录一次，返回记录后是否超过上限
+    pub fn record(&self, key: &str) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() > PRUNE_THRESHOLD {
+            let window = self.window;
+            entries.retain(|_, (_, started)| started.elapsed() < window);
+        }
+
+        let entry = entries.entry(key.to_string()).or_insert((0, Instant::now()));
+        if entry.1.elapsed() >= self.window {
+            *entry = (0, Instant::now());
+        }
+        entry.0 += 1;
+        entry.0 > self.limit
+    }
+
+    pub fn reset(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+    }
+}
+
+/// 请求的对端 IP；不信任 X-Forwarded-For，避免伪造绕过限流
+pub fn client_ip(req: &HttpRequest) -> String {
+    req.peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
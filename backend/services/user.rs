@@ -1,9 +1,20 @@
-use actix_web::{web, get, post, HttpResponse};
+use actix_web::{web, get, post, HttpRequest, HttpResponse};
 use diesel::prelude::*;
-use crate::models::user::{User, NewUser,UserInput};
+use crate::models::user::{User, NewUser, UserInput, LoginInput, UpgradeGuestInput, RenameInput, UsernameChange, NewUsernameChange, ClaimPasswordInput};
+use crate::services::auth;
+use crate::services::rate_limit::{self, RateLimiter};
+use crate::services::content_filter;
+use crate::services::ws::{GameServer, UpdateUsername};
+use actix::Addr;
+use crate::models::game::{Game, GamePlayer};
+use crate::models::rooms::PaginationResult;
 use diesel::r2d2::{self, ConnectionManager};
 use diesel::sqlite::SqliteConnection;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::Duration;
 
 // 定义数据库连接池类型别名
 pub type DbPool = r2d2::Pool<ConnectionManager<SqliteConnection>>;
@@ -13,11 +24,139 @@ pub struct UserIdQuery {
     user_id: String,  // 改为字符串
 }
 
+#[derive(Deserialize)]
+pub struct MatchHistoryQuery {
+    pub page: Option<i64>,      // 从0开始
+    pub page_size: Option<i64>,
+}
+
+/// 登录失败次数限制：同一账户、同一IP 在窗口内超过上限后暂时拒绝登录；
+/// 每次尝试在校验密码前先计数，避免并发请求在记录失败前全部通过检查，登录成功后清零账户计数
+const MAX_LOGIN_FAILURES_PER_ACCOUNT: u32 = 5;
+const MAX_LOGIN_FAILURES_PER_IP: u32 = 20;
+const LOGIN_FAILURE_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+static LOGIN_FAILURES_BY_ACCOUNT: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(MAX_LOGIN_FAILURES_PER_ACCOUNT, LOGIN_FAILURE_WINDOW));
+static LOGIN_FAILURES_BY_IP: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(MAX_LOGIN_FAILURES_PER_IP, LOGIN_FAILURE_WINDOW));
+
+/// 同一IP每小时最多创建的游客账户数
+const MAX_GUESTS_PER_IP: u32 = 5;
+/// 从未参加对局的游客账户在最后一次登录后保留的时间（小时），超过后被清理
+const GUEST_IDLE_HOURS: i64 = 24;
+
+static GUESTS_BY_IP: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(MAX_GUESTS_PER_IP, Duration::from_secs(60 * 60)));
+
+/// 用户名长度限制（按字符计）
+pub const MIN_USERNAME_LEN: usize = 2;
+pub const MAX_USERNAME_LEN: usize = 20;
+
+/// 用户名中禁止出现的字符
+pub const FORBIDDEN_USERNAME_CHARS: &[char] = &['<', '>', '"', '\'', '&'];
+
+/// 系统保留的用户名，归一化后比较，防止冒充系统消息
+pub const RESERVED_USERNAMES: &[&str] = &["system", "系统", "系统广播", "Unknown", "admin", "管理员"];
+
+/// 校验用户名格式（注册、改名、游客升级共用），与前端 usernameValidator 规则一致
+pub fn validate_username(username: &str) -> Result<(), String> {
+    if username != username.trim() {
+        return Err("用户名首尾不能包含空格".to_string());
+    }
+    let len = username.chars().count();
+    if len < MIN_USERNAME_LEN || len > MAX_USERNAME_LEN {
+        return Err(format!("用户名长度必须在{}-{}个字符之间", MIN_USERNAME_LEN, MAX_USERNAME_LEN));
+    }
+    if username.chars().any(|ch| FORBIDDEN_USERNAME_CHARS.contains(&ch)) {
+        return Err("用户名不能包含特殊字符 < > \" ' &".to_string());
+    }
+    let normalized = content_filter::ContentFilter::normalize(username);
+    if RESERVED_USERNAMES.iter().any(|reserved| content_filter::ContentFilter::normalize(reserved) == normalized) {
+        return Err("该用户名为系统保留，请选择其他用户名".to_string());
+    }
+    if content_filter::check_name(username).is_err() {
+        return Err("用户名包含禁用内容，请选择其他用户名".to_string());
+    }
+    Ok(())
+}
+
+fn invalid_username_response(message: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": "用户名不符合规范",
+        "message": message
+    }))
+}
+
+// 检查用户名是否已被占用（注册、改名、游客升级共用）
+fn check_username(conn: &mut SqliteConnection, new_username: &str) -> QueryResult<()> {
+    match crate::schema::users::table.filter(crate::schema::users::username.eq(new_username)).first::<User>(conn) {
+        Ok(_) => Err(diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UniqueViolation,
+            Box::new("用户名已存在".to_string())
+        )),
+        Err(diesel::NotFound) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+// 将 check_username 的错误转换为响应
+fn username_error_response(e: &diesel::result::Error) -> Option<HttpResponse> {
+    match e {
+        diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
+            Some(HttpResponse::Conflict().json(serde_json::json!({
+                "error": "用户名已存在",
+                "message": "该用户名已被使用，请选择其他用户名"
+            })))
+        }
+        _ => None,
+    }
+}
+
+// 注册或登录成功后返回的用户信息和会话 token
+#[derive(Serialize)]
+pub struct AuthResponse {
+    #[serde(flatten)]
+    pub user: User,
+    pub token: String,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
 #[post("")]
 pub async fn create_user(
     pool: web::Data<DbPool>,
     user_input: web::Json<UserInput>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    // "system" 是服务器发送系统消息时使用的ID，不允许注册
+    if user_input.user_id.eq_ignore_ascii_case("system") {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "用户ID不可用",
+            "message": "该用户ID为系统保留，请选择其他ID"
+        })));
+    }
+
+    if let Err(message) = validate_username(&user_input.username) {
+        return Ok(invalid_username_response(message));
+    }
+
+    if let Err(message) = auth::validate_password(&user_input.password) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "密码不符合规范",
+            "message": message
+        })));
+    }
+
+    // 密码哈希计算量较大，放到阻塞线程池中执行
+    let password = user_input.password.clone();
+    let password_hash = match web::block(move || auth::hash_password(&password)).await? {
+        Ok(hash) => hash,
+        Err(_) => {
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "服务器错误",
+                "message": "创建用户时发生内部错误"
+            })));
+        }
+    };
     
     let user = web::block(move || {
         let mut conn = pool.get().expect("couldn't get db connection from pool");
@@ -36,42 +175,34 @@ pub async fn create_user(
             Err(e) => return Err(e),
         }
         
-        // 检查用户名是否包含禁用内容
-        if user_input.username.to_lowercase().contains("ek") {
-            return Err(diesel::result::Error::DatabaseError(
-                diesel::result::DatabaseErrorKind::CheckViolation,
-                Box::new("用户名包含禁用内容".to_string())
-            ));
-        }
-        
-        // 检查username是否已存在
-        match crate::schema::users::table.filter(crate::schema::users::username.eq(&user_input.username)).first::<User>(&mut conn) {
-            Ok(_) => {
-                return Err(diesel::result::Error::DatabaseError(
-                    diesel::result::DatabaseErrorKind::UniqueViolation,
-                    Box::new("用户名已存在".to_string())
-                ));
-            }
-            Err(diesel::NotFound) => {
-                // 用户名不存在，可以创建
-            }
-            Err(e) => return Err(e),
-        }
+        // 检查用户名是否已存在
+        check_username(&mut conn, &user_input.username)?;
         
         // 将 UserInput 转换为 NewUser
         let new_user = NewUser {
             id: &user_input.user_id,  // 前端的user_id字段映射到数据库的id字段
             username: &user_input.username,
+            password_hash: Some(&password_hash),
+            is_guest: false,
         };
 
-        diesel::insert_into(crate::schema::users::table)
-            .values(&new_user)
-            .get_result::<User>(&mut conn)
+        // 注册成功后直接创建登录会话
+        conn.transaction(|conn| {
+            let user = diesel::insert_into(crate::schema::users::table)
+                .values(&new_user)
+                .get_result::<User>(conn)?;
+            let session = auth::create_session(conn, &user.id)?;
+            Ok(AuthResponse {
+                user,
+                token: session.token,
+                expires_at: session.expires_at,
+            })
+        })
     })
     .await?;
 
     match user {
-        Ok(user) => Ok(HttpResponse::Ok().json(user)),
+        Ok(response) => Ok(HttpResponse::Ok().json(response)),
         Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, info)) => {
             let error_message = info.message();
             if error_message.contains("用户ID已存在") {
@@ -91,20 +222,6 @@ pub async fn create_user(
                 })))
             }
         }
-        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::CheckViolation, info)) => {
-            let error_message = info.message();
-            if error_message.contains("用户名包含禁用内容") {
-                Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                    "error": "用户名不符合规范",
-                    "message": "用户名包含禁用内容，请选择其他用户名"
-                })))
-            } else {
-                Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                    "error": "数据验证失败",
-                    "message": "输入数据不符合要求"
-                })))
-            }
-        }
         Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
             "error": "服务器错误",
             "message": "创建用户时发生内部错误"
@@ -136,7 +253,810 @@ pub async fn get_user(
     }
 }
 
+// 登录失败的原因
+enum LoginError {
+    InvalidCredentials, // 用户不存在、游客账户、未设置密码或密码错误，统一返回以防枚举账户
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for LoginError {
+    fn from(e: diesel::result::Error) -> Self {
+        LoginError::Database(e)
+    }
+}
+
+fn too_many_login_attempts() -> HttpResponse {
+    HttpResponse::TooManyRequests().json(serde_json::json!({
+        "error": "尝试次数过多",
+        "message": format!("登录失败次数过多，请 {} 分钟后再试", LOGIN_FAILURE_WINDOW.as_secs() / 60)
+    }))
+}
+
+// 登录的 Handler，成功后返回新的会话 token
+// POST /api/users/login
+#[post("/login")]
+pub async fn login(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    login_input: web::Json<LoginInput>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let ip = rate_limit::client_ip(&req);
+    let account = login_input.user_id.clone();
+    let ip_limited = LOGIN_FAILURES_BY_IP.record(&ip);
+    let account_limited = LOGIN_FAILURES_BY_ACCOUNT.record(&account);
+    if ip_limited || account_limited {
+        return Ok(too_many_login_attempts());
+    }
+
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+        let user = crate::schema::users::table
+            .filter(crate::schema::users::id.eq(&login_input.user_id))
+            .first::<User>(&mut conn)
+            .optional()?;
+
+        // 游客账户只能通过会话token登录；旧账户未设置密码时需通过认领流程设置，不能在登录时直接设置
+        let password_hash = user.as_ref()
+            .filter(|user| !user.is_guest)
+            .and_then(|user| user.password_hash.as_deref());
+        if !auth::verify_password_or_dummy(&login_input.password, password_hash) {
+            return Err(LoginError::InvalidCredentials);
+        }
+        let user = user.ok_or(LoginError::InvalidCredentials)?;
+
+        let session = auth::create_session(&mut conn, &user.id)?;
+        Ok(AuthResponse {
+            user,
+            token: session.token,
+            expires_at: session.expires_at,
+        })
+    })
+    .await?;
+
+    match result {
+        Ok(response) => {
+            LOGIN_FAILURES_BY_ACCOUNT.reset(&account);
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(LoginError::InvalidCredentials) => {
+            Ok(HttpResponse::Unauthorized().json(serde_json::json!({
+                "error": "登录失败",
+                "message": "User ID 或密码错误"
+            })))
+        }
+        Err(LoginError::Database(e)) => {
+            println!("登录时数据库错误: {}", e);
+            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "服务器错误",
+                "message": "登录时发生内部错误"
+            })))
+        }
+    }
+}
+
+// 为未设置密码的旧账户签发认领 token，仅管理员可用；管理员需先在线下确认申请人是账户所有者
+// POST /api/users/{id}/claim_token
+#[post("/{id}/claim_token")]
+pub async fn create_claim_token(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let admin = match auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    if !auth::is_admin(&admin.id) {
+        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "权限不足",
+            "message": "只有管理员可以签发认领 token"
+        })));
+    }
+
+    let target_id = path.into_inner();
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+        let user = crate::schema::users::table
+            .filter(crate::schema::users::id.eq(&target_id))
+            .first::<User>(&mut conn)
+            .optional()?;
+        match user {
+            Some(user) if !user.is_guest && user.password_hash.is_none() => {
+                let session = auth::create_session_with_ttl(&mut conn, &user.id, chrono::Duration::hours(auth::CLAIM_TTL_HOURS))?;
+                Ok(Some(session))
+            }
+            _ => Ok::<_, diesel::result::Error>(None),
+        }
+    })
+    .await?;
+
+    match result {
+        Ok(Some(session)) => {
+            println!("管理员 {} 为旧账户 {} 签发了认领 token", admin.id, session.user_id);
+            Ok(HttpResponse::Ok().json(serde_json::json!({
+                "user_id": session.user_id,
+                "token": session.token,
+                "expires_at": session.expires_at,
+            })))
+        }
+        Ok(None) => Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无法认领",
+            "message": "该账户不存在、是游客账户或已设置密码"
+        }))),
+        Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "服务器错误",
+            "message": "签发认领 token 时发生内部错误"
+        }))),
+    }
+}
+
+// 旧账户使用认领 token 设置密码，成功后认领 token 失效并返回新的会话 token
+// POST /api/users/claim_password
+#[post("/claim_password")]
+pub async fn claim_password(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    input: web::Json<ClaimPasswordInput>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = match auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    if user.is_guest || user.password_hash.is_some() {
+        return Ok(HttpResponse::Conflict().json(serde_json::json!({
+            "error": "无需认领",
+            "message": "该账户已设置密码或是游客账户"
+        })));
+    }
+
+    if let Err(message) = auth::validate_password(&input.password) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "密码不符合规范",
+            "message": message
+        })));
+    }
+
+    let password = input.password.clone();
+    let password_hash = match web::block(move || auth::hash_password(&password)).await? {
+        Ok(hash) => hash,
+        Err(_) => {
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "服务器错误",
+                "message": "设置密码时发生内部错误"
+            })));
+        }
+    };
+
+    // authenticate 已确认 token 存在
+    let claim_token = auth::request_token(&req).unwrap_or_default();
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+        conn.transaction(|conn| {
+            // 只在仍未设置密码时更新，防止并发认领
+            let updated = diesel::update(crate::schema::users::table
+                    .filter(crate::schema::users::id.eq(&user.id))
+                    .filter(crate::schema::users::password_hash.is_null()))
+                .set(crate::schema::users::password_hash.eq(Some(&password_hash)))
+                .execute(conn)?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            auth::delete_session(conn, &claim_token)?;
+            let session = auth::create_session(conn, &user.id)?;
+            println!("旧账户 {} 已认领密码", user.id);
+            Ok::<_, diesel::result::Error>(Some(AuthResponse {
+                user: User { password_hash: Some(password_hash.clone()), ..user },
+                token: session.token,
+                expires_at: session.expires_at,
+            }))
+        })
+    })
+    .await?;
+
+    match result {
+        Ok(Some(response)) => Ok(HttpResponse::Ok().json(response)),
+        Ok(None) => Ok(HttpResponse::Conflict().json(serde_json::json!({
+            "error": "无需认领",
+            "message": "该账户已设置密码"
+        }))),
+        Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "服务器错误",
+            "message": "设置密码时发生内部错误"
+        }))),
+    }
+}
+
+// 登出的 Handler，删除当前会话
+// POST /api/users/logout
+#[post("/logout")]
+pub async fn logout(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = match auth::request_token(&req) {
+        Some(token) => token,
+        None => {
+            return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
+                "error": "未授权",
+                "message": "缺少登录凭证"
+            })));
+        }
+    };
+
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+        auth::delete_session(&mut conn, &token)
+    })
+    .await?;
+
+    match result {
+        Ok(_) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "已登出"
+        }))),
+        Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "服务器错误",
+            "message": "登出时发生内部错误"
+        }))),
+    }
+}
+
+// 获取用户名校验规则，前端据此与服务端保持一致
+// GET /api/users/username_rules
+#[get("/username_rules")]
+pub async fn get_username_rules() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({
+        "min_length": MIN_USERNAME_LEN,
+        "max_length": MAX_USERNAME_LEN,
+        "forbidden_chars": FORBIDDEN_USERNAME_CHARS.iter().collect::<String>(),
+        "reserved_names": RESERVED_USERNAMES,
+    }))
+}
+
+// 获取当前登录用户的 Handler
+// GET /api/users/me
+#[get("/me")]
+pub async fn get_current_user(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    match auth::authenticate(&pool, &req).await {
+        Ok(user) => Ok(HttpResponse::Ok().json(user)),
+        Err(response) => Ok(response),
+    }
+}
+
+// 生成游客账户的ID和用户名
+fn generate_guest_identity() -> (String, String) {
+    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    let mut rng = rand::rng();
+    let suffix: String = (0..7)
+        .map(|_| CHARSET[rng.random_range(0..CHARSET.len())] as char)
+        .collect();
+    (format!("g{}", suffix), format!("游客{}", rng.random_range(100000..=999999)))
+}
+
+// 创建游客账户的 Handler，无需填写任何信息即可开始游戏
+// POST /api/users/guest
+#[post("/guest")]
+pub async fn create_guest(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let ip = rate_limit::client_ip(&req);
+    if GUESTS_BY_IP.record(&ip) {
+        println!("IP {} 创建游客账户过于频繁", ip);
+        return Ok(HttpResponse::TooManyRequests().json(serde_json::json!({
+            "error": "请求过于频繁",
+            "message": "创建游客账户过于频繁，请稍后再试"
+        })));
+    }
+
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+        // 生成的ID或用户名已被占用时重新生成
+        for _ in 0..5 {
+            let (guest_id, guest_name) = generate_guest_identity();
+            let taken = crate::schema::users::table
+                .filter(crate::schema::users::id.eq(&guest_id)
+                    .or(crate::schema::users::username.eq(&guest_name)))
+                .count()
+                .get_result::<i64>(&mut conn)?;
+            if taken > 0 {
+                continue;
+            }
+
+            let new_user = NewUser {
+                id: &guest_id,
+                username: &guest_name,
+                password_hash: None,
+                is_guest: true,
+            };
+            let response = conn.transaction(|conn| {
+                let user = diesel::insert_into(crate::schema::users::table)
+                    .values(&new_user)
+                    .get_result::<User>(conn)?;
+                let session = auth::create_session(conn, &user.id)?;
+                Ok::<_, diesel::result::Error>(AuthResponse {
+                    user,
+                    token: session.token,
+                    expires_at: session.expires_at,
+                })
+            })?;
+            return Ok(Some(response));
+        }
+
+        Ok::<_, diesel::result::Error>(None)
+    })
+    .await?;
+
+    match result {
+        Ok(Some(response)) => Ok(HttpResponse::Ok().json(response)),
+        Ok(None) | Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "服务器错误",
+            "message": "创建游客账户失败，请稍后再试"
+        }))),
+    }
+}
+
+/// 清理从未参加对局、且最近一次登录已超过 GUEST_IDLE_HOURS 的游客账户及其会话，online 为当前在线的用户ID
+pub fn purge_stale_guests(conn: &mut SqliteConnection, online: &[String]) -> QueryResult<usize> {
+    use crate::schema::{direct_messages, friends, game_players, sessions, username_history, users};
+
+    let cutoff = chrono::Utc::now().naive_utc() - chrono::Duration::hours(GUEST_IDLE_HOURS);
+    let stale_ids: Vec<String> = users::table
+        .filter(users::is_guest.eq(true))
+        .filter(diesel::dsl::not(users::id.eq_any(online)))
+        .filter(diesel::dsl::not(users::id.eq_any(game_players::table.select(game_players::user_id))))
+        .filter(diesel::dsl::not(users::id.eq_any(
+            sessions::table.filter(sessions::created_at.gt(cutoff)).select(sessions::user_id)
+        )))
+        .select(users::id)
+        .load(conn)?;
+    if stale_ids.is_empty() {
+        return Ok(0);
+    }
+
+    // SQLite 未开启外键约束，关联数据需要手动删除
+    conn.transaction(|conn| {
+        diesel::delete(sessions::table.filter(sessions::user_id.eq_any(&stale_ids))).execute(conn)?;
+        diesel::delete(friends::table.filter(friends::user_id.eq_any(&stale_ids).or(friends::friend_id.eq_any(&stale_ids)))).execute(conn)?;
+        diesel::delete(direct_messages::table.filter(direct_messages::sender_id.eq_any(&stale_ids).or(direct_messages::recipient_id.eq_any(&stale_ids)))).execute(conn)?;
+        diesel::delete(username_history::table.filter(username_history::user_id.eq_any(&stale_ids))).execute(conn)?;
+        diesel::delete(users::table.filter(users::id.eq_any(&stale_ids))).execute(conn)
+    })
+}
+
+// 游客升级为正式账户的 Handler，保留原有ID，对局记录和统计随之保留
+// POST /api/users/upgrade
+#[post("/upgrade")]
+pub async fn upgrade_guest(
+    pool: web::Data<DbPool>,
+    game_server: web::Data<Addr<GameServer>>,
+    req: HttpRequest,
+    input: web::Json<UpgradeGuestInput>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = match auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    if !user.is_guest {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "不是游客账户",
+            "message": "该账户已是正式账户"
+        })));
+    }
+
+    if let Err(message) = auth::validate_password(&input.password) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "密码不符合规范",
+            "message": message
+        })));
+    }
+
+    let new_username = input.username.as_ref()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty() && name != &user.username);
+    if let Some(ref name) = new_username {
+        if let Err(message) = validate_username(name) {
+            return Ok(invalid_username_response(message));
+        }
+    }
+
+    let password = input.password.clone();
+    let password_hash = match web::block(move || auth::hash_password(&password)).await? {
+        Ok(hash) => hash,
+        Err(_) => {
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "服务器错误",
+                "message": "升级账户时发生内部错误"
+            })));
+        }
+    };
+
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+        conn.transaction(|conn| {
+            if let Some(ref name) = new_username {
+                check_username(conn, name)?;
+                diesel::insert_into(crate::schema::username_history::table)
+                    .values(&NewUsernameChange {
+                        user_id: &user.id,
+                        old_username: &user.username,
+                        new_username: name,
+                    })
+                    .execute(conn)?;
+            }
+
+            diesel::update(crate::schema::users::table.filter(crate::schema::users::id.eq(&user.id)))
+                .set((
+                    crate::schema::users::username.eq(new_username.as_deref().unwrap_or(&user.username)),
+                    crate::schema::users::password_hash.eq(Some(&password_hash)),
+                    crate::schema::users::is_guest.eq(false),
+                ))
+                .get_result::<User>(conn)
+        })
+    })
+    .await?;
+
+    match result {
+        Ok(user) => {
+            println!("游客 {} 已升级为正式账户，用户名: {}", user.id, user.username);
+            // 同步在线会话和房间中的用户名
+            game_server.do_send(UpdateUsername {
+                user_id: user.id.clone(),
+                username: user.username.clone(),
+            });
+            Ok(HttpResponse::Ok().json(user))
+        }
+        Err(e) => match username_error_response(&e) {
+            Some(response) => Ok(response),
+            None => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "服务器错误",
+                "message": "升级账户时发生内部错误"
+            }))),
+        },
+    }
+}
+
+// 修改当前用户用户名的 Handler，记录改名历史并同步到在线会话
+// POST /api/users/rename
+#[post("/rename")]
+pub async fn rename_user(
+    pool: web::Data<DbPool>,
+    game_server: web::Data<Addr<GameServer>>,
+    req: HttpRequest,
+    input: web::Json<RenameInput>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = match auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let new_username = input.username.trim().to_string();
+    if let Err(message) = validate_username(&new_username) {
+        return Ok(invalid_username_response(message));
+    }
+    if new_username == user.username {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "用户名未改变",
+            "message": "新用户名与当前用户名相同"
+        })));
+    }
+
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+        conn.transaction(|conn| {
+            check_username(conn, &new_username)?;
+
+            diesel::insert_into(crate::schema::username_history::table)
+                .values(&NewUsernameChange {
+                    user_id: &user.id,
+                    old_username: &user.username,
+                    new_username: &new_username,
+                })
+                .execute(conn)?;
+
+            diesel::update(crate::schema::users::table.filter(crate::schema::users::id.eq(&user.id)))
+                .set(crate::schema::users::username.eq(&new_username))
+                .get_result::<User>(conn)
+        })
+    })
+    .await?;
+
+    match result {
+        Ok(user) => {
+            println!("用户 {} 改名为 {}", user.id, user.username);
+            game_server.do_send(UpdateUsername {
+                user_id: user.id.clone(),
+                username: user.username.clone(),
+            });
+            Ok(HttpResponse::Ok().json(user))
+        }
+        Err(e) => match username_error_response(&e) {
+            Some(response) => Ok(response),
+            None => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "服务器错误",
+                "message": "修改用户名时发生内部错误"
+            }))),
+        },
+    }
+}
+
+// 获取用户改名历史的 Handler，按时间从新到旧排序
+// GET /api/users/{id}/username_history
+#[get("/{id}/username_history")]
+pub async fn get_username_history(
+    pool: web::Data<DbPool>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = path.into_inner();
+
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+        crate::schema::username_history::table
+            .filter(crate::schema::username_history::user_id.eq(&user_id))
+            .order(crate::schema::username_history::id.desc())
+            .select(UsernameChange::as_select())
+            .load::<UsernameChange>(&mut conn)
+    })
+    .await?;
+
+    match result {
+        Ok(history) => Ok(HttpResponse::Ok().json(history)),
+        Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "服务器错误",
+            "message": "获取改名记录时发生内部错误"
+        }))),
+    }
+}
+
+#[derive(Serialize)]
+pub struct FavouriteGroup {
+    pub group_id: i32,
+    pub name: String,
+    pub color: String,
+    pub games_played: i64, // 使用该组进行的对局数
+}
+
+#[derive(Serialize)]
+pub struct UserStats {
+    pub user_id: String,
+    pub username: String,
+    pub games_played: i64,
+    pub wins: i64,
+    pub win_rate: f64, // 0.0 - 1.0
+    pub average_placement: Option<f64>, // 没有对局时为 null
+    pub cities_captured: i64,
+    pub generals_captured: i64,
+    pub favourite_group: Option<FavouriteGroup>,
+}
+
+impl UserStats {
+    // 根据已保存的对局成绩计算统计数据
+    fn from_records(user: User, records: &[GamePlayer]) -> Self {
+        let games_played = records.len() as i64;
+        let wins = records.iter().filter(|r| r.is_winner).count() as i64;
+        let win_rate = if games_played > 0 { wins as f64 / games_played as f64 } else { 0.0 };
+        let average_placement = if games_played > 0 {
+            Some(records.iter().map(|r| r.placement as f64).sum::<f64>() / games_played as f64)
+        } else {
+            None
+        };
+
+        // 统计每个组的使用次数，次数相同时取组ID较小的
+        let mut group_counts: HashMap<i32, i64> = HashMap::new();
+        for record in records {
+            *group_counts.entry(record.group_id).or_insert(0) += 1;
+        }
+        let favourite_group = group_counts
+            .into_iter()
+            .max_by(|(a_id, a_count), (b_id, b_count)| a_count.cmp(b_count).then(b_id.cmp(a_id)))
+            .map(|(group_id, games_played)| {
+                let (name, color) = crate::services::ws::group_name_and_color(group_id as usize);
+                FavouriteGroup {
+                    group_id,
+                    name: name.to_string(),
+                    color: color.to_string(),
+                    games_played,
+                }
+            });
+
+        UserStats {
+            user_id: user.id,
+            username: user.username,
+            games_played,
+            wins,
+            win_rate,
+            average_placement,
+            cities_captured: records.iter().map(|r| r.cities_captured as i64).sum(),
+            generals_captured: records.iter().map(|r| r.generals_captured as i64).sum(),
+            favourite_group,
+        }
+    }
+}
+
+// 获取用户对局统计的 Handler
+// GET /api/users/{id}/stats
+#[get("/{id}/stats")]
+pub async fn get_user_stats(
+    pool: web::Data<DbPool>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = path.into_inner();
+    let user_id_for_error = user_id.clone();
+
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+        let user = crate::schema::users::table
+            .filter(crate::schema::users::id.eq(&user_id))
+            .first::<User>(&mut conn)?;
+
+        let records = crate::schema::game_players::table
+            .filter(crate::schema::game_players::user_id.eq(&user_id))
+            .select(GamePlayer::as_select())
+            .load::<GamePlayer>(&mut conn)?;
+
+        Ok::<_, diesel::result::Error>(UserStats::from_records(user, &records))
+    })
+    .await?;
+
+    match result {
+        Ok(stats) => Ok(HttpResponse::Ok().json(stats)),
+        Err(diesel::NotFound) => Ok(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "用户不存在",
+            "message": format!("No user found with ID: {}", user_id_for_error)
+        }))),
+        Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "服务器错误",
+            "message": "获取用户统计时发生内部错误"
+        }))),
+    }
+}
+
+#[derive(Serialize)]
+pub struct MatchParticipant {
+    pub user_id: String,
+    pub username: String,
+    pub group_id: i32,
+    pub placement: i32,
+}
+
+#[derive(Serialize)]
+pub struct MatchHistoryEntry {
+    pub game_id: i32,
+    pub room_id: String,
+    pub result: String, // "win" 或 "loss"
+    pub placement: i32,
+    pub group_id: i32,
+    pub cities_captured: i32,
+    pub generals_captured: i32,
+    pub teammates: Vec<MatchParticipant>,
+    pub opponents: Vec<MatchParticipant>,
+    pub player_count: i32,
+    pub started_at: chrono::NaiveDateTime,
+    pub ended_at: chrono::NaiveDateTime,
+    pub duration_seconds: i64,
+    pub map_seed: Option<i64>,
+}
+
+// 获取用户对局历史的 Handler
+// GET /api/users/{id}/games?page=&page_size=
+#[get("/{id}/games")]
+pub async fn get_user_games(
+    pool: web::Data<DbPool>,
+    path: web::Path<String>,
+    query: web::Query<MatchHistoryQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = path.into_inner();
+    let page = query.page.unwrap_or(0);
+    let page_size = query.page_size.unwrap_or(10);
+
+    if page < 0 || page_size < 1 {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "invalid_page",
+            "message": "page不能小于0，page_size不能小于1"
+        })));
+    }
+
+    if page_size > 50 {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "page_size_too_large",
+            "message": "单次查询最多返回50场对局"
+        })));
+    }
+
+    let result = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+        let records = GamePlayer::paginate_by_user(&mut conn, &user_id, page, page_size)?;
+
+        // 一次性加载当前页涉及的对局和这些对局的所有玩家
+        let game_ids: Vec<i32> = records.items.iter().map(|r| r.game_id).collect();
+        let games: HashMap<i32, Game> = crate::schema::games::table
+            .filter(crate::schema::games::id.eq_any(&game_ids))
+            .select(Game::as_select())
+            .load::<Game>(&mut conn)?
+            .into_iter()
+            .map(|game| (game.id, game))
+            .collect();
+        let participants = crate::schema::game_players::table
+            .filter(crate::schema::game_players::game_id.eq_any(&game_ids))
+            .select(GamePlayer::as_select())
+            .load::<GamePlayer>(&mut conn)?;
+
+        let items = records.items
+            .into_iter()
+            .filter_map(|record| {
+                let game = games.get(&record.game_id)?;
+                let (teammates, opponents): (Vec<&GamePlayer>, Vec<&GamePlayer>) = participants
+                    .iter()
+                    .filter(|p| p.game_id == record.game_id && p.user_id != record.user_id)
+                    .partition(|p| p.team_id == record.team_id);
+                let to_participant = |p: &GamePlayer| MatchParticipant {
+                    user_id: p.user_id.clone(),
+                    username: p.username.clone(),
+                    group_id: p.group_id,
+                    placement: p.placement,
+                };
+
+                Some(MatchHistoryEntry {
+                    game_id: game.id,
+                    room_id: game.room_id.clone(),
+                    result: if record.is_winner { "win" } else { "loss" }.to_string(),
+                    placement: record.placement,
+                    group_id: record.group_id,
+                    cities_captured: record.cities_captured,
+                    generals_captured: record.generals_captured,
+                    teammates: teammates.into_iter().map(to_participant).collect(),
+                    opponents: opponents.into_iter().map(to_participant).collect(),
+                    player_count: game.player_count,
+                    started_at: game.started_at,
+                    ended_at: game.ended_at,
+                    duration_seconds: (game.ended_at - game.started_at).num_seconds(),
+                    map_seed: game.map_seed,
+                })
+            })
+            .collect();
+
+        Ok::<_, diesel::result::Error>(PaginationResult {
+            items,
+            total_items: records.total_items,
+            page: records.page,
+            page_size: records.page_size,
+            num_pages: records.num_pages,
+        })
+    })
+    .await?;
+
+    match result {
+        Ok(history) => Ok(HttpResponse::Ok().json(history)),
+        Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "服务器错误",
+            "message": "获取对局历史时发生内部错误"
+        }))),
+    }
+}
+
 // 将用户服务的路由组织起来
 pub fn endpoints(cfg: &mut web::ServiceConfig) {
-    cfg.service(create_user).service(get_user);
+    cfg.service(create_user)
+        .service(login)
+        .service(claim_password)
+        .service(create_claim_token)
+        .service(logout)
+        .service(create_guest)
+        .service(upgrade_guest)
+        .service(rename_user)
+        .service(get_username_rules)
+        .service(get_current_user)
+        .service(get_user)
+        .service(get_user_stats)
+        .service(get_user_games)
+        .service(get_username_history);
 }
\ No newline at end of file
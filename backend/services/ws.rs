@@ -1,15 +1,28 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::LazyLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 use actix::prelude::*;
 use actix::fut;
 use actix_web::{ web, HttpRequest, HttpResponse };
 use actix_web_actors::ws;
+use serde::{Serialize, Deserialize};
 use serde_json;
 use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use crate::models::game::{Game, NewGame, NewGamePlayer, GameSnapshot, NewGameSnapshot};
+use crate::models::chat::{ChatMessage, NewDirectMessage};
+use crate::models::friend::Friendship;
+use crate::models::rooms::{Rooms, CreateRooms, UpdateRooms, RoomsFilter};
+use crate::services::user::DbPool;
+use crate::services::rate_limit::RateLimiter;
+use crate::services::matchmaking::{Matchmaker, JoinQueue, LeaveQueue, QueueStatus};
+use crate::services::get_rooms::RoomInfo as LobbyRoom;
+use crate::services::protocol::{ClientMessage, ServerMessage, GroupPlayers, PingMarker, PROTOCOL_VERSION, MIN_PROTOCOL_VERSION, HELLO_TIMEOUT_SECS, SERVER_FEATURES, MAP_DELTA_FEATURE, MSGPACK_FEATURE};
 type Coordinate = (i32, i32);
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 enum Tile {
     Wilderness,                           // w: 无主之地
     Territory { count: usize, user_id: String }, // t: 玩家领地，兵力count，玩家user_id
@@ -19,7 +32,7 @@ enum Tile {
     City { count: usize, user_id: Option<String>, city_type: CityType }, // c: 城市，兵力count，拥有者user_id（可为空），城市类型
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 enum CityType {
     Settlement,  // 定居点
     SmallCity,   // 小型城市  
@@ -55,11 +68,12 @@ impl Tile {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct GameMap {
     tiles: Vec<Vec<Tile>>,
     width: usize,
     height: usize,
+    seed: u64, // 生成地图使用的seed，用于复现地图
 }
 
 impl GameMap {
@@ -67,7 +81,7 @@ impl GameMap {
         // 这个方法现在已被new_random替代，仅保留用于测试
         // 创建基础地图，所有位置初始为荒野
         let tiles = vec![vec![Tile::Wilderness; width]; height];
-        Self { tiles, width, height }
+        Self { tiles, width, height, seed: 0 }
     }
 
     // 生成随机地图，根据玩家数量调整地图大小和内容
@@ -177,11 +191,11 @@ impl GameMap {
             }
             
             // 验证王城连通性
-            let temp_map = Self { tiles: tiles.clone(), width, height };
+            let temp_map = Self { tiles: tiles.clone(), width, height, seed };
             if temp_map.validate_general_connectivity() {
                 println!("成功生成{}x{}地图，{}个王城，{}座山，{}座城市", 
                         width, height, general_positions.len(), mountain_count, city_count);
-                return Self { tiles, width, height };
+                return Self { tiles, width, height, seed };
             }
             
             println!("地图连通性验证失败，重新生成... (尝试 {}/{})", attempts, max_attempts);
@@ -316,7 +330,7 @@ impl GameMap {
             }
         }
         
-        Self { tiles, width, height }
+        Self { tiles, width, height, seed }
     }
     
     // 验证王城连通性
@@ -813,45 +827,95 @@ struct GroupInfo {
     players: Vec<String>, // 组内玩家ID列表
 }
 
+// 组ID对应的名称和颜色
+pub fn group_name_and_color(id: usize) -> (&'static str, &'static str) {
+    match id {
+        0 => ("红队", "#FF4444"),
+        1 => ("蓝队", "#4444FF"),
+        2 => ("绿队", "#44FF44"),
+        3 => ("黄队", "#FFFF44"),
+        4 => ("紫队", "#FF44FF"),
+        5 => ("青队", "#44FFFF"),
+        6 => ("橙队", "#FF8844"),
+        7 => ("粉队", "#FF8888"),
+        8 => ("观众", "#888888"),
+        _ => ("未知", "#000000"),
+    }
+}
+
 impl GroupInfo {
     fn new(id: usize) -> Self {
-        let (name, color) = match id {
-            0 => ("红队".to_string(), "#FF4444".to_string()),
-            1 => ("蓝队".to_string(), "#4444FF".to_string()),
-            2 => ("绿队".to_string(), "#44FF44".to_string()),
-            3 => ("黄队".to_string(), "#FFFF44".to_string()),
-            4 => ("紫队".to_string(), "#FF44FF".to_string()),
-            5 => ("青队".to_string(), "#44FFFF".to_string()),
-            6 => ("橙队".to_string(), "#FF8844".to_string()),
-            7 => ("粉队".to_string(), "#FF8888".to_string()),
-            8 => ("观众".to_string(), "#888888".to_string()),
-            _ => ("未知".to_string(), "#000000".to_string()),
-        };
+        let (name, color) = group_name_and_color(id);
         
         GroupInfo {
             id,
-            name,
-            color,
+            name: name.to_string(),
+            color: color.to_string(),
             players: Vec::new(),
         }
     }
 }
 
+// 写入数据库前收集的玩家对局成绩
+struct PlayerGameRecord {
+    user_id: String,
+    username: String,
+    group_id: i32,
+    team_id: String,
+    placement: i32,
+    is_winner: bool,
+    cities_captured: i32,
+    generals_captured: i32,
+}
+
+// 队伍在一局对局中的统计数据
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct TeamStats {
+    cities_captured: u32,   // 占领的城市数
+    generals_captured: u32, // 攻下的王城数
+}
+
+// 进行中对局的快照，定期写入数据库，服务器重启后据此恢复对局
+#[derive(Serialize, Deserialize)]
+struct RoomSnapshot {
+    name: String,
+    host_player_id: String,
+    host_player_name: String,
+    max_players: usize,
+    room_color: String,
+    #[serde(alias = "password")] // 旧快照中保存的是明文密码
+    password_hash: Option<String>,
+    is_public: bool,
+    is_persistent: bool,
+    player_groups: HashMap<String, usize>, // 玩家ID -> 组ID映射
+    player_teams: HashMap<String, String>, // 玩家ID -> 队伍ID映射
+    usernames: HashMap<String, String>,    // 玩家ID -> 用户名，用于恢复后记录对局结果
+    game_map: GameMap,
+    game_turn: u32,
+    turn_half: bool,
+    game_started_at: u64,
+    team_stats: HashMap<String, TeamStats>,
+    eliminated_teams: Vec<String>,
+    #[serde(default)] // 旧快照中没有排位标记
+    is_ranked: bool,
+}
+
 struct RoomInfo {
     name: String,
     host_player_id: String, // 存储player_id (userid)
     host_player_name: String,
     admin_player_id: Option<String>, // 新增：管理员player_id
     admin_player_name: Option<String>, // 新增：管理员用户名
-    status: String, // "waiting", "playing", "game_turn"
+    status: String, // "waiting", "playing", "paused"（重启后恢复的对局，等待玩家重连）, "game_turn"
     max_players: usize, // 最大玩家数
     room_color: String, // 房间专属颜色
     players: Vec<String>, // 存储player_id (userid)的列表
     player_count: usize,
     force_start_players: Vec<String>, // 存储player_id (userid)的列表
     last_activity: u64, // 最后活动时间戳（秒）
-    password: Option<String>, // 新增：房间密码
+    password_hash: Option<String>, // 房间密码的 argon2 哈希，不保存明文
     is_public: bool,         // 新增：是否为公开房间
+    is_persistent: bool,     // 常驻房间：不会被自动清理，服务器重启后恢复
     groups: Vec<GroupInfo>,  // 新增：分组信息
     player_groups: HashMap<String, usize>, // 新增：玩家ID -> 组ID映射
     // 游戏回合制相关字段
@@ -862,6 +926,10 @@ struct RoomInfo {
     // 游戏地图相关字段
     game_map: Option<GameMap>, // 游戏地图
     player_teams: HashMap<String, String>, // 玩家ID -> 队伍ID映射
+    // 对局统计相关字段
+    game_started_at: u64, // 对局开始时间戳（秒）
+    team_stats: HashMap<String, TeamStats>, // 队伍ID -> 对局统计
+    eliminated_teams: Vec<String>, // 按被击败顺序记录的队伍ID
 }
 
 #[derive(Message, Clone)]
@@ -878,6 +946,7 @@ struct ReturnedRoomInfo {
     groups: Vec<ReturnedGroupInfo>, // 新增：分组信息
     room_id: String, // 新增：房间ID
     max_players: usize, // 新增：最大玩家数
+    muted_players: Vec<String>, // 正在禁言中的玩家用户名
 }
 
 #[derive(Clone, Debug)]
@@ -886,6 +955,144 @@ struct ReturnedGroupInfo {
     players: Vec<String>, // 只返回用户名列表，颜色和名称前端处理
 }
 
+impl From<ReturnedRoomInfo> for ServerMessage {
+    fn from(room_info: ReturnedRoomInfo) -> Self {
+        ServerMessage::RoomInfo {
+            room_id: room_info.room_id,
+            name: room_info.name,
+            host_player_name: room_info.host_player_name,
+            admin_player_name: room_info.admin_player_name,
+            status: room_info.status,
+            players: room_info.players,
+            player_count: room_info.player_count,
+            force_start_players: room_info.force_start_players,
+            required_to_start: room_info.required_to_start,
+            groups: room_info.groups
+                .into_iter()
+                .map(|group| GroupPlayers { id: group.id, players: group.players })
+                .collect(),
+            max_players: room_info.max_players,
+            muted_players: room_info.muted_players,
+        }
+    }
+}
+
+/// 单条聊天消息的最大长度（按字符计）
+const MAX_CHAT_LENGTH: usize = 200;
+/// 聊天令牌桶容量，即允许连续发送的消息条数
+const CHAT_BURST: f64 = 5.0;
+/// 聊天令牌每秒恢复数量
+const CHAT_REFILL_PER_SEC: f64 = 1.0;
+/// 未指定时长时的默认禁言时间与上限（分钟）
+const DEFAULT_MUTE_MINUTES: u64 = 5;
+const MAX_MUTE_MINUTES: u64 = 24 * 60;
+/// 地图标记持续的回合数
+const PING_DURATION_TURNS: u32 = 5;
+/// 每个玩家同时存在的地图标记上限，超出时移除最早的标记
+const MAX_PINGS_PER_PLAYER: usize = 3;
+/// 每个房间在内存中保留的聊天记录条数
+const CHAT_HISTORY_LIMIT: usize = 200;
+/// 加入房间或连接全局聊天时发送的历史消息条数
+const CHAT_BACKLOG_SIZE: usize = 50;
+/// 直接邀请的有效期（秒），邀请只能由被邀请者使用一次
+const INVITE_TTL_SECS: u64 = 10 * 60;
+/// 邀请链接的有效期（秒），有效期内可被任何人多次使用
+const INVITE_LINK_TTL_SECS: u64 = 24 * 60 * 60;
+/// 连续输错房间密码达到该次数后进入冷却
+const MAX_FAILED_JOINS: u32 = 5;
+/// 输错密码次数过多后的冷却时间（秒）
+const FAILED_JOIN_COOLDOWN_SECS: u64 = 5 * 60;
+/// 同一IP每分钟最多发起的带密码加入请求
+const MAX_PASSWORD_JOINS_PER_IP: u32 = 10;
+/// 常驻房间数量上限，常驻房间不会被自动清理
+const MAX_PERSISTENT_ROOMS: usize = 20;
+
+static PASSWORD_JOINS_BY_IP: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(MAX_PASSWORD_JOINS_PER_IP, std::time::Duration::from_secs(60)));
+
+// 每个用户的聊天令牌桶，所有房间（包括全局房间）共用
+#[derive(Clone, Debug)]
+struct ChatBucket {
+    tokens: f64,
+    last_refill: std::time::Instant,
+}
+
+impl ChatBucket {
+    fn new() -> Self {
+        ChatBucket {
+            tokens: CHAT_BURST,
+            last_refill: std::time::Instant::now(),
+        }
+    }
+
+    // 按经过的时间恢复令牌，有剩余令牌时消耗一个并返回 true
+    fn try_take(&mut self) -> bool {
+        let now = std::time::Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * CHAT_REFILL_PER_SEC).min(CHAT_BURST);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    // 令牌已恢复满，说明用户近期没有发言，可以回收
+    fn is_idle(&self) -> bool {
+        self.tokens + self.last_refill.elapsed().as_secs_f64() * CHAT_REFILL_PER_SEC >= CHAT_BURST
+    }
+}
+
+// 玩家在地图上放置的标记，只有队友和观战者可见
+#[derive(Clone, Debug, Serialize)]
+struct MapPing {
+    x: usize,
+    y: usize,
+    marker: String, // attack / defend / danger
+    username: String,
+    #[serde(skip)]
+    player_id: String,
+    #[serde(skip)]
+    team_id: String,
+    #[serde(skip)]
+    game_started_at: u64, // 所属对局，用于丢弃上一局遗留的标记
+    expires_turn: u32, // 到达该回合时标记消失
+}
+
+impl From<&MapPing> for PingMarker {
+    fn from(ping: &MapPing) -> Self {
+        PingMarker {
+            x: ping.x,
+            y: ping.y,
+            marker: ping.marker.clone(),
+            username: ping.username.clone(),
+            expires_turn: ping.expires_turn,
+        }
+    }
+}
+
+// 房间邀请 token，持有者加入房间时无需密码
+#[derive(Clone, Debug)]
+struct InviteToken {
+    room_id: String,
+    inviter_id: String,
+    invitee_id: Option<String>, // None 表示邀请链接，任何人都可以使用
+    expires_at: u64,
+}
+
+// 房间设置修改请求，未提供的字段保持不变
+#[derive(Clone, Debug, Default)]
+struct RoomSettingsUpdate {
+    name: Option<String>,
+    max_players: Option<usize>,
+    room_color: Option<String>,
+    password_hash: Option<Option<String>>, // Some(None) 表示移除密码，新密码由会话在阻塞线程池中哈希
+    is_public: Option<bool>,
+    is_persistent: Option<bool>,
+}
+
 #[derive(Message, Clone)]
 #[rtype(result = "()")]
 enum UserMessage {
@@ -916,6 +1123,7 @@ enum UserMessage {
         player_id: String,
         player_name: String,
         password: Option<String>, // 新增：加入房间时提供的密码
+        invite_token: Option<String>, // 邀请 token，有效时跳过密码检查
     },
     LeaveRoom {
         room_id: String,
@@ -946,15 +1154,98 @@ enum UserMessage {
         kicker_id: String,
         target_player_name: String,
     },
+    MutePlayer {
+        room_id: String,
+        muter_id: String,
+        target_player_name: String,
+        duration_minutes: Option<u64>,
+    },
+    UnmutePlayer {
+        room_id: String,
+        muter_id: String,
+        target_player_name: String,
+    },
     ChangeGroup {
         room_id: String,
         player_id: String,
         target_group_id: usize,
     },
+    UpdateRoomSettings {
+        room_id: String,
+        player_id: String,
+        settings: RoomSettingsUpdate,
+    },
     SetUserInfo {
         user_id: String,
         username: String,
     },
+    ChatHistory {
+        room_id: String,
+        messages: Vec<ChatMessage>,
+    },
+    Whisper {
+        sender_id: String,
+        target_player_name: String,
+        content: String,
+    },
+    ChannelChat {
+        room_id: String,
+        sender_id: String,
+        username: String,
+        content: String,
+        channel: String, // "team" 队伍频道，"spectator" 观战频道
+    },
+    FriendUpdate {
+        user_id: String,
+        username: String,
+        event: String, // presence / request / accepted / removed
+        presence: Option<Presence>,
+    },
+    WhisperMessage {
+        sender_id: String,
+        sender_name: String,
+        recipient_id: String,
+        recipient_name: String,
+        content: String,
+    },
+    InvitePlayer {
+        room_id: String,
+        inviter_id: String,
+        target_player_name: String,
+    },
+    DeclineInvite {
+        player_id: String,
+        token: String,
+    },
+    CreateInviteLink {
+        room_id: String,
+        player_id: String,
+    },
+    RoomInvite {
+        room_id: String,
+        room_name: String,
+        inviter_name: String,
+        token: String,
+        expires_at: u64,
+    },
+    InviteLink {
+        room_id: String,
+        token: String,
+        expires_at: u64,
+    },
+    MatchFound {
+        room_id: String,
+        opponent_name: String,
+        rating: i32,
+        opponent_rating: i32,
+    },
+    SubscribeLobby {
+        player_id: String,
+    },
+    UnsubscribeLobby {
+        player_id: String,
+    },
+    Lobby(ServerMessage), // 大厅的完整房间列表或房间变化
     RoomInfoUpdate(ReturnedRoomInfo),
     RedirectToHome {
         reason: String,
@@ -984,6 +1275,14 @@ enum UserMessage {
         visible_tiles: Vec<(usize, usize, String, usize, Option<String>, bool)>, // (x, y, tile_type, count, user_id, has_vision)
         successful_move_sends: Vec<usize>, // 成功发送的move_id列表
         player_powers: Vec<(String, usize, u32, String)>, // (username, group_id, total_power, status) - 所有玩家的总兵力和状态
+        pings: Vec<MapPing>, // 该玩家可见的地图标记
+    },
+    Ping {
+        room_id: String,
+        player_id: String,
+        x: usize,
+        y: usize,
+        marker: String,
     },
     GameWin {
         room_id: String,
@@ -1030,9 +1329,106 @@ pub struct CleanupInactiveRooms;
 pub struct CleanupDisconnectedPlayers;
 
 #[derive(Message)]
-#[rtype(result = "Vec<(String, String, String, String, usize, usize, String, usize, usize, bool, bool)>")]
+#[rtype(result = "()")]
+pub struct SnapshotGames;
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct PurgeStaleGuests;
+
+// 排位匹配成功后，将双方放入新建的房间并自动开始对局；有玩家无法开始时返回这些玩家的ID
+#[derive(Message)]
+#[rtype(result = "Result<(), Vec<String>>")]
+pub struct StartRankedMatch {
+    pub room_id: String,
+    pub players: Vec<(String, String, i32)>, // (user_id, username, rating)，依次放入组0和组1
+}
+
+// 玩家的在线状态，变化时推送给在线的好友
+#[tsync::tsync]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Presence {
+    pub status: String, // offline / lobby / waiting / playing / spectating
+    pub room_id: Option<String>,
+}
+
+// 玩家连接后从数据库载入的好友列表
+#[derive(Message)]
+#[rtype(result = "()")]
+struct SetFriends {
+    user_id: String,
+    friend_ids: Vec<String>,
+}
+
+// 好友关系在 REST 接口中发生变化，同步到在线会话
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct FriendshipChanged {
+    pub user_id: String,   // 操作者
+    pub username: String,
+    pub friend_id: String, // 对方
+    pub event: String,     // request / accepted / removed
+}
+
+// 查询一批用户的在线状态
+#[derive(Message)]
+#[rtype(result = "HashMap<String, Presence>")]
+pub struct GetPresence {
+    pub user_ids: Vec<String>,
+}
+
+// 用户名在 REST 接口中被修改后，同步到在线会话和房间
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct UpdateUsername {
+    pub user_id: String,
+    pub username: String,
+}
+
+// 获取房间中 id 小于 before 的聊天记录（按时间正序），只有房间内的玩家可以查看
+#[derive(Message)]
+#[rtype(result = "Result<Vec<ChatMessage>, String>")]
+pub struct GetChatHistory {
+    pub room_id: String,
+    pub user_id: String,
+    pub before: Option<i32>,
+    pub limit: usize,
+}
+
+#[derive(Message)]
+#[rtype(result = "Vec<RoomListEntry>")]
 pub struct GetRoomList;
 
+// 房间列表中的一项，供房间列表接口、快速开始和大厅推送使用
+#[derive(Clone, PartialEq)]
+pub struct RoomListEntry {
+    pub room_id: String,
+    pub name: String,
+    pub host_name: String,
+    pub status: String,
+    pub player_count: usize,
+    pub max_players: usize,
+    pub room_color: String,
+    pub required_to_start: usize,
+    pub is_public: bool,
+    pub has_password: bool,
+    pub last_activity: u64,
+    pub game_turn: Option<u32>,       // 对局进行中时的当前回合
+    pub alive_players: Option<usize>, // 对局进行中时尚未被淘汰的玩家数
+}
+
+impl RoomListEntry {
+    // 大厅推送时忽略每回合都会变化的回合数和活动时间，避免进行中的房间每回合都推送一次
+    fn same_lobby_state(&self, other: &RoomListEntry) -> bool {
+        let normalize = |entry: &RoomListEntry| RoomListEntry {
+            game_turn: None,
+            last_activity: 0,
+            ..entry.clone()
+        };
+        normalize(self) == normalize(other)
+    }
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<String, String>")] // 返回创建的房间ID或错误信息
 pub struct CreateRoom {
@@ -1042,8 +1438,9 @@ pub struct CreateRoom {
     pub room_color: String,
     pub host_name: String,
     pub host_id: String,
-    pub password: Option<String>,
+    pub password_hash: Option<String>, // 房间密码的哈希，由调用方在阻塞线程池中计算
     pub is_public: bool,
+    pub is_persistent: bool,
 }
 
 #[derive(Message)]
@@ -1075,7 +1472,92 @@ pub struct GameServer {
     rooms: HashMap<String, RoomInfo>, // room_id -> room_info
     user_name_table: HashMap<String, String>, // userid -> username
     kicked_players: HashMap<String, HashMap<String, u64>>, // room_id -> (userid -> kick_time)
+    failed_joins: HashMap<String, HashMap<String, (u32, u64)>>, // room_id -> (userid -> (连续输错密码次数, 最后一次输错时间))
+    muted_players: HashMap<String, HashMap<String, u64>>, // room_id -> (userid -> 禁言结束时间)
+    invite_tokens: HashMap<String, InviteToken>, // token -> 邀请信息
+    chat_buckets: HashMap<String, ChatBucket>, // userid -> 聊天令牌桶
+    chat_history: HashMap<String, VecDeque<ChatMessage>>, // room_id -> 最近的聊天记录
+    map_pings: HashMap<String, Vec<MapPing>>, // room_id -> 对局中的地图标记
+    friends: HashMap<String, HashSet<String>>, // 在线用户 -> 已确认的好友ID
+    last_presence: HashMap<String, Presence>, // 上次推送给好友的在线状态
+    presence_dirty: HashSet<String>, // 在线状态可能已变化、待推送给好友的用户ID
+    next_chat_id: i32, // 下一条聊天记录的ID，启动时从数据库中的最大ID继续
     disconnected_players: HashMap<String, u64>, // userid -> disconnect_time - 新增：断线玩家时间跟踪
+    paused_rooms: HashMap<String, u64>, // room_id -> 恢复时间，重启后恢复的对局在玩家重连前保持暂停
+    ranked_rooms: HashSet<String>, // 排位匹配创建的房间，对局结束后结算排位分
+    lobby_subscribers: HashSet<String>, // 订阅大厅房间变化的用户ID
+    lobby_rooms: HashMap<String, RoomListEntry>, // 上次推送给大厅的公开房间
+    lobby_dirty: bool, // 对局中有玩家被淘汰，处理完当前消息或回合后同步大厅
+    db_pool: Option<DbPool>, // 数据库连接池，用于持久化对局记录
+    db_writer: Option<std::sync::mpsc::Sender<DbWrite>>, // 房间和对局快照的写入队列，按发送顺序依次执行
+}
+
+// 房间和对局快照的写操作，由同一个写入线程按顺序执行，避免较晚的写入被较早的覆盖
+enum DbWrite {
+    SaveSnapshots(Vec<NewGameSnapshot>),
+    DeleteSnapshot(String),
+    SaveRoom(CreateRooms, UpdateRooms),
+    DeleteRoom(String),
+}
+
+// 启动数据库写入线程，返回写入队列的发送端
+fn spawn_db_writer(db_pool: DbPool) -> std::sync::mpsc::Sender<DbWrite> {
+    let (sender, receiver) = std::sync::mpsc::channel::<DbWrite>();
+    std::thread::spawn(move || {
+        for write in receiver {
+            let mut conn = match db_pool.get() {
+                Ok(conn) => conn,
+                Err(e) => {
+                    println!("获取数据库连接失败: {}", e);
+                    continue;
+                }
+            };
+            match write {
+                DbWrite::SaveSnapshots(snapshots) => {
+                    let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                        for snapshot in &snapshots {
+                            diesel::insert_into(crate::schema::game_snapshots::table)
+                                .values(snapshot)
+                                .on_conflict(crate::schema::game_snapshots::room_id)
+                                .do_update()
+                                .set(snapshot)
+                                .execute(conn)?;
+                        }
+                        Ok(())
+                    });
+                    if let Err(e) = result {
+                        println!("保存对局快照失败: {}", e);
+                    }
+                }
+                DbWrite::DeleteSnapshot(room_id) => {
+                    let query = crate::schema::game_snapshots::table
+                        .filter(crate::schema::game_snapshots::room_id.eq(&room_id));
+                    if let Err(e) = diesel::delete(query).execute(&mut conn) {
+                        println!("删除房间 {} 的对局快照失败: {}", room_id, e);
+                    }
+                }
+                DbWrite::SaveRoom(row, changes) => {
+                    let result = diesel::insert_into(crate::schema::rooms::table)
+                        .values(&row)
+                        .on_conflict(crate::schema::rooms::room_id)
+                        .do_update()
+                        .set(&changes)
+                        .execute(&mut conn);
+                    if let Err(e) = result {
+                        println!("保存房间 {} 失败: {}", row.room_id, e);
+                    }
+                }
+                DbWrite::DeleteRoom(room_id) => {
+                    let query = crate::schema::rooms::table
+                        .filter(crate::schema::rooms::room_id.eq(&room_id));
+                    if let Err(e) = diesel::delete(query).execute(&mut conn) {
+                        println!("删除房间 {} 的数据库记录失败: {}", room_id, e);
+                    }
+                }
+            }
+        }
+    });
+    sender
 }
 
 impl Default for GameServer {
@@ -1099,8 +1581,9 @@ impl Default for GameServer {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_secs(),
-            password: None, // 全局房间无密码
+            password_hash: None, // 全局房间无密码
             is_public: false, // 全局房间不公开在列表
+            is_persistent: false, // 全局房间总是存在，无需持久化
             groups: (0..9).map(|i| GroupInfo::new(i)).collect(), // 初始化9个组
             player_groups: HashMap::new(), // 初始化玩家组映射
             // 游戏回合制字段
@@ -1111,6 +1594,10 @@ impl Default for GameServer {
             // 游戏地图字段
             game_map: None,
             player_teams: HashMap::new(),
+            // 对局统计字段
+            game_started_at: 0,
+            team_stats: HashMap::new(),
+            eliminated_teams: Vec::new(),
         });
         
         Self {
@@ -1118,7 +1605,24 @@ impl Default for GameServer {
             rooms,
             user_name_table: HashMap::new(),
             kicked_players: HashMap::new(),
+            failed_joins: HashMap::new(),
+            muted_players: HashMap::new(),
+            invite_tokens: HashMap::new(),
+            chat_buckets: HashMap::new(),
+            chat_history: HashMap::new(),
+            map_pings: HashMap::new(),
+            friends: HashMap::new(),
+            last_presence: HashMap::new(),
+            presence_dirty: HashSet::new(),
+            next_chat_id: 1,
             disconnected_players: HashMap::new(), // 新增：初始化断线玩家跟踪
+            paused_rooms: HashMap::new(),
+            ranked_rooms: HashSet::new(),
+            lobby_subscribers: HashSet::new(),
+            lobby_rooms: HashMap::new(),
+            lobby_dirty: false,
+            db_pool: None,
+            db_writer: None,
         }
     }
 }
@@ -1141,8 +1645,9 @@ impl GameServer {
             player_count: 0,
             force_start_players: Vec::new(),
             last_activity: 0,
-            password: None, // 全局房间无密码
+            password_hash: None, // 全局房间无密码
             is_public: false, // 全局房间不公开在列表
+            is_persistent: false, // 全局房间总是存在，无需持久化
             groups: (0..9).map(|i| GroupInfo::new(i)).collect(), // 初始化9个组
             player_groups: HashMap::new(), // 初始化玩家组映射
             // 游戏回合制字段
@@ -1153,6 +1658,10 @@ impl GameServer {
             // 游戏地图字段
             game_map: None,
             player_teams: HashMap::new(),
+            // 对局统计字段
+            game_started_at: 0,
+            team_stats: HashMap::new(),
+            eliminated_teams: Vec::new(),
         });
 
         GameServer {
@@ -1160,7 +1669,24 @@ impl GameServer {
             rooms,
             user_name_table: HashMap::new(),
             kicked_players: HashMap::new(),
+            failed_joins: HashMap::new(),
+            muted_players: HashMap::new(),
+            invite_tokens: HashMap::new(),
+            chat_buckets: HashMap::new(),
+            chat_history: HashMap::new(),
+            map_pings: HashMap::new(),
+            friends: HashMap::new(),
+            last_presence: HashMap::new(),
+            presence_dirty: HashSet::new(),
+            next_chat_id: 1,
             disconnected_players: HashMap::new(), // 新增：初始化断线玩家跟踪
+            paused_rooms: HashMap::new(),
+            ranked_rooms: HashSet::new(),
+            lobby_subscribers: HashSet::new(),
+            lobby_rooms: HashMap::new(),
+            lobby_dirty: false,
+            db_pool: None,
+            db_writer: None,
         }
     }
 
@@ -1172,6 +1698,30 @@ impl GameServer {
             .as_secs()
     }
 
+    // 对房间密码加盐哈希，空密码视为不设置密码
+    fn hash_room_password(password: Option<&str>) -> Result<Option<String>, String> {
+        match password.filter(|p| !p.is_empty()) {
+            Some(password) => crate::services::auth::hash_password(password)
+                .map(Some)
+                .map_err(|e| {
+                    println!("房间密码哈希失败: {}", e);
+                    "设置房间密码失败".to_string()
+                }),
+            None => Ok(None),
+        }
+    }
+
+    // 旧数据中保存的明文房间密码升级为哈希
+    fn upgrade_legacy_room_password(stored: Option<String>) -> Option<String> {
+        match stored {
+            Some(password) if !password.starts_with("$argon2") => {
+                // 哈希失败时保留原值，避免房间变成无密码
+                Self::hash_room_password(Some(&password)).ok().flatten().or(Some(password))
+            }
+            other => other,
+        }
+    }
+
     // 更新房间活动时间
     fn update_room_activity(&mut self, room_id: &str) {
         if let Some(room) = self.rooms.get_mut(room_id) {
@@ -1179,101 +1729,417 @@ impl GameServer {
         }
     }
 
-    // 检查玩家是否被踢出指定房间且仍在冷却时间内
-    fn is_player_kicked_from_room(&mut self, player_id: &str, room_id: &str) -> bool {
+    // 返回玩家在指定房间的剩余禁言秒数，禁言已过期时移除记录
+    fn remaining_mute_secs(&mut self, player_id: &str, room_id: &str) -> Option<u64> {
         let current_time = Self::current_timestamp();
-        let five_minutes = 5 * 60; // 5分钟 = 300秒
-        
-        if let Some(room_kicks) = self.kicked_players.get_mut(room_id) {
-            if let Some(&kick_time) = room_kicks.get(player_id) {
-                if current_time - kick_time < five_minutes {
-                    return true;
-                } else {
-                    // 冷却时间已过，移除记录
-                    room_kicks.remove(player_id);
-                    // 如果该房间没有踢出记录了，移除整个房间记录
-                    if room_kicks.is_empty() {
-                        self.kicked_players.remove(room_id);
-                    }
-                }
-            }
+        let room_mutes = self.muted_players.get_mut(room_id)?;
+        let until = *room_mutes.get(player_id)?;
+        if until > current_time {
+            return Some(until - current_time);
         }
-        false
-    }
-
-    // 广播消息给所有在线玩家
-    fn broadcast_to_all_players(&self, message: &str) {
-        for (player_id, recipient) in &self.player_sessions {
-            let _ = recipient.do_send(UserMessage::Chat {
-                room_id: "global".to_string(),
-                sender_id: "system".to_string(),
-                username: "系统广播".to_string(),
-                content: message.to_string(),
-            });
+        room_mutes.remove(player_id);
+        if room_mutes.is_empty() {
+            self.muted_players.remove(room_id);
         }
+        None
     }
-    
-    // 处理玩家被击败
-    fn handle_player_elimination(&mut self, room_id: &str, defeated_team: &str, winner_team: &str) {
-        if let Some(room) = self.rooms.get_mut(room_id) {
-            // 找到被击败的玩家并将其转为观众组（组8）
-            let mut defeated_players = Vec::new();
-            for (player_id, team) in &room.player_teams {
-                if team == defeated_team {
-                    defeated_players.push(player_id.clone());
-                }
-            }
-            
-            // 将被击败的玩家移动到观众组
-            for player_id in defeated_players {
-                room.player_groups.insert(player_id.clone(), 8);
-                
-                // 向被击败的玩家发送系统消息
-                if let Some(recipient) = self.player_sessions.get(&player_id) {
+
+    // 向房间内所有玩家发送系统消息
+    fn send_room_system_message(&self, room_id: &str, content: &str) {
+        if let Some(room) = self.rooms.get(room_id) {
+            for player_id in &room.players {
+                if let Some(recipient) = self.player_sessions.get(player_id) {
                     let _ = recipient.do_send(UserMessage::Chat {
                         room_id: room_id.to_string(),
                         sender_id: "system".to_string(),
                         username: "系统".to_string(),
-                        content: format!("您已被 {} 击败，现在转为观众身份，拥有全局视野", winner_team),
+                        content: content.to_string(),
                     });
                 }
             }
         }
     }
-    
-    // 向房间内所有玩家发送地图更新
-    fn send_map_update_to_all_players(&mut self, room_id: &str, successful_move_sends: Vec<usize>) {
-        if let Some(room) = self.rooms.get(room_id) {
-            if let Some(ref game_map) = room.game_map {
-                for p_id in &room.players {
-                    if let Some(&group_id) = room.player_groups.get(p_id) {
-                        let player_powers = self.calculate_player_powers(room_id);
-                        
-                        let formatted_tiles: Vec<(usize, usize, String, usize, Option<String>, bool)>;
-                        
-                        if group_id == 8 {
-                            // 观众可以看到全图
-                            formatted_tiles = game_map.get_all_tiles()
-                                .into_iter().map(|(x, y, tile, has_vision)| {
-                                    let (tile_type, count, user_id) = match tile {
-                                        Tile::Wilderness => ("w".to_string(), 0, None),
-                                        Tile::Territory { count, user_id } => ("t".to_string(), count, Some(user_id)),
-                                        Tile::Mountain => ("m".to_string(), 0, None),
-                                        Tile::General { count, user_id } => ("g".to_string(), count, Some(user_id)),
-                                        Tile::Void => ("v".to_string(), 0, None),
-                                        Tile::City { count, user_id, city_type } => {
-                                            let type_str = match city_type {
-                                                CityType::Settlement => "c_settlement",
-                                                CityType::SmallCity => "c_smallcity",
-                                                CityType::LargeCity => "c_largecity",
-                                            };
-                                            (type_str.to_string(), count, user_id)
-                                        },
-                                    };
-                                    (x, y, tile_type, count, user_id, has_vision)
-                                }).collect();
-                        } else {
-                            // 其他玩家根据视野规则看到地图
+
+    // 检查聊天消息的长度、禁言状态（仅房间聊天）和发言频率，并进行内容过滤；
+    // 返回过滤后的内容，系统消息不受长度、禁言和频率限制
+    fn check_chat_message(&mut self, sender_id: &str, room_id: Option<&str>, content: &str) -> Result<String, String> {
+        if sender_id != "system" {
+            if content.trim().is_empty() {
+                return Err("消息不能为空".to_string());
+            }
+            if content.chars().count() > MAX_CHAT_LENGTH {
+                return Err(format!("消息不能超过{}个字符", MAX_CHAT_LENGTH));
+            }
+            if let Some(room_id) = room_id {
+                if let Some(remaining) = self.remaining_mute_secs(sender_id, room_id) {
+                    return Err(format!("您已被禁言，{}秒后可以发言", remaining));
+                }
+            }
+            if !self.chat_buckets.entry(sender_id.to_string()).or_insert_with(ChatBucket::new).try_take() {
+                return Err("发言过于频繁，请稍后再试".to_string());
+            }
+        }
+
+        // 内容过滤：包含屏蔽词的消息被拒绝，遮蔽词替换为 *
+        crate::services::content_filter::filter_chat(content)
+    }
+
+    // 玩家在房间中所属的聊天队伍；观众（第8组）以及对局中已被击败的玩家返回 None，视为观战者
+    fn chat_team(room: &RoomInfo, player_id: &str) -> Option<String> {
+        let group_id = *room.player_groups.get(player_id)?;
+        if group_id >= 8 {
+            return None;
+        }
+        let team_id = room.player_teams.get(player_id)
+            .cloned()
+            .unwrap_or_else(|| format!("team_{}", group_id));
+        let in_game = room.status == "playing" || room.status == "paused";
+        if in_game && room.eliminated_teams.contains(&team_id) {
+            return None;
+        }
+        Some(team_id)
+    }
+
+    // 玩家在下一次地图更新中可见的标记：队友的标记，观战者（含已被击败的玩家）可见全部标记
+    fn visible_pings(&self, room_id: &str, player_id: &str) -> Vec<MapPing> {
+        match self.rooms.get(room_id) {
+            Some(room) => Self::room_visible_pings(room, self.map_pings.get(room_id), player_id),
+            None => Vec::new(),
+        }
+    }
+
+    fn room_visible_pings(room: &RoomInfo, pings: Option<&Vec<MapPing>>, player_id: &str) -> Vec<MapPing> {
+        let pings = match pings {
+            Some(pings) => pings,
+            None => return Vec::new(),
+        };
+        let viewer_team = Self::chat_team(room, player_id);
+        pings.iter()
+            .filter(|ping| ping.game_started_at == room.game_started_at && ping.expires_turn > room.game_turn)
+            .filter(|ping| viewer_team.as_ref().map_or(true, |team_id| &ping.team_id == team_id))
+            .cloned()
+            .collect()
+    }
+
+    // 根据会话、断线记录和房间成员关系推导玩家的在线状态
+    fn presence_of(&self, user_id: &str) -> Presence {
+        if !self.player_sessions.contains_key(user_id) || self.disconnected_players.contains_key(user_id) {
+            return Presence { status: "offline".to_string(), room_id: None };
+        }
+
+        for (room_id, room) in &self.rooms {
+            if room_id == "global" || !room.players.iter().any(|id| id == user_id) {
+                continue;
+            }
+            let status = match room.status.as_str() {
+                "playing" | "paused" if Self::chat_team(room, user_id).is_some() => "playing",
+                "playing" | "paused" => "spectating",
+                _ => "waiting",
+            };
+            return Presence { status: status.to_string(), room_id: Some(room_id.clone()) };
+        }
+
+        Presence { status: "lobby".to_string(), room_id: None }
+    }
+
+    // 向用户的在线好友推送好友相关事件
+    fn notify_friends(&self, user_id: &str, event: &str, presence: Option<Presence>) {
+        let friend_ids = match self.friends.get(user_id) {
+            Some(friend_ids) => friend_ids,
+            None => return,
+        };
+        let username = self.user_name_table.get(user_id).cloned().unwrap_or_else(|| "Unknown".to_string());
+        for friend_id in friend_ids {
+            if let Some(recipient) = self.player_sessions.get(friend_id) {
+                let _ = recipient.do_send(UserMessage::FriendUpdate {
+                    user_id: user_id.to_string(),
+                    username: username.clone(),
+                    event: event.to_string(),
+                    presence: presence.clone(),
+                });
+            }
+        }
+    }
+
+    // 将在线状态发生变化的用户推送给其好友，在连接、断开、进出房间和对局状态变化后调用
+    fn push_presence(&mut self) {
+        for user_id in std::mem::take(&mut self.presence_dirty) {
+            if !self.friends.contains_key(&user_id) {
+                continue; // 好友列表尚未载入或已离线，无需推送
+            }
+            let presence = self.presence_of(&user_id);
+            if self.last_presence.get(&user_id) == Some(&presence) {
+                continue;
+            }
+            self.notify_friends(&user_id, "presence", Some(presence.clone()));
+
+            // 已离线的用户不再跟踪，重新连接时会再次载入好友列表
+            if presence.status == "offline" {
+                self.friends.remove(&user_id);
+                self.last_presence.remove(&user_id);
+            } else {
+                self.last_presence.insert(user_id, presence);
+            }
+        }
+    }
+
+    // 记录一条聊天消息，全局聊天同时写入数据库
+    fn record_chat(&mut self, room_id: &str, sender_id: &str, username: &str, content: &str) {
+        let message = ChatMessage {
+            id: self.next_chat_id,
+            room_id: room_id.to_string(),
+            sender_id: sender_id.to_string(),
+            username: username.to_string(),
+            content: content.to_string(),
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+        self.next_chat_id += 1;
+
+        let history = self.chat_history.entry(room_id.to_string()).or_insert_with(VecDeque::new);
+        history.push_back(message.clone());
+        if history.len() > CHAT_HISTORY_LIMIT {
+            history.pop_front();
+        }
+
+        if room_id == "global" {
+            self.spawn_db_task(move |conn| {
+                if let Err(e) = diesel::insert_into(crate::schema::chat_messages::table)
+                    .values(&message)
+                    .execute(conn)
+                {
+                    println!("保存全局聊天记录失败: {}", e);
+                }
+            });
+        }
+    }
+
+    // 向玩家发送房间最近的聊天记录
+    fn send_chat_backlog(&self, room_id: &str, player_id: &str) {
+        let recipient = match self.player_sessions.get(player_id) {
+            Some(recipient) => recipient,
+            None => return,
+        };
+        let messages: Vec<ChatMessage> = match self.chat_history.get(room_id) {
+            Some(history) => history.iter()
+                .skip(history.len().saturating_sub(CHAT_BACKLOG_SIZE))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        let _ = recipient.do_send(UserMessage::ChatHistory {
+            room_id: room_id.to_string(),
+            messages,
+        });
+    }
+
+    // 生成房间列表中的一项
+    fn room_list_entry(room_id: &str, room: &RoomInfo) -> RoomListEntry {
+        let in_game = room.status == "playing" || room.status == "paused";
+        RoomListEntry {
+            room_id: room_id.to_string(),
+            name: room.name.clone(),
+            host_name: room.host_player_name.clone(),
+            status: room.status.clone(),
+            player_count: room.player_count,
+            max_players: room.max_players,
+            room_color: room.room_color.clone(),
+            required_to_start: (room.max_players as f32 * 0.6).ceil() as usize, // 假设需要60%玩家准备
+            is_public: room.is_public,
+            has_password: room.password_hash.is_some(),
+            last_activity: room.last_activity,
+            game_turn: in_game.then_some(room.game_turn),
+            alive_players: in_game.then(|| {
+                room.player_teams.values()
+                    .filter(|team_id| !room.eliminated_teams.contains(team_id))
+                    .count()
+            }),
+        }
+    }
+
+    // 大厅中显示的公开房间
+    fn lobby_room_entries(&self) -> HashMap<String, RoomListEntry> {
+        self.rooms.iter()
+            .filter(|(id, room)| *id != "global" && room.is_public)
+            .map(|(id, room)| (id.clone(), Self::room_list_entry(id, room)))
+            .collect()
+    }
+
+    // 与上次推送的公开房间对比，向订阅大厅的用户推送新增、变化和移除的房间
+    fn sync_lobby(&mut self) {
+        self.lobby_dirty = false;
+        if self.lobby_subscribers.is_empty() {
+            return; // 没有订阅者时不比较，下次订阅时重新发送完整列表
+        }
+
+        let current = self.lobby_room_entries();
+        let mut updates = Vec::new();
+        for (room_id, entry) in &current {
+            match self.lobby_rooms.get(room_id) {
+                None => updates.push(ServerMessage::RoomCreated { room: LobbyRoom::from(entry.clone()) }),
+                Some(previous) if !previous.same_lobby_state(entry) => {
+                    updates.push(ServerMessage::RoomUpdated { room: LobbyRoom::from(entry.clone()) });
+                }
+                _ => {}
+            }
+        }
+        for room_id in self.lobby_rooms.keys() {
+            if !current.contains_key(room_id) {
+                updates.push(ServerMessage::RoomRemoved { room_id: room_id.clone() });
+            }
+        }
+        self.lobby_rooms = current;
+
+        if updates.is_empty() {
+            return;
+        }
+        for subscriber_id in &self.lobby_subscribers {
+            if let Some(recipient) = self.player_sessions.get(subscriber_id) {
+                for update in &updates {
+                    let _ = recipient.do_send(UserMessage::Lobby(update.clone()));
+                }
+            }
+        }
+    }
+
+    // 检查邀请 token 是否可用于该玩家加入指定房间
+    fn is_invite_valid(&self, token: &Option<String>, room_id: &str, player_id: &str) -> bool {
+        let Some(token) = token else {
+            return false;
+        };
+        match self.invite_tokens.get(token) {
+            Some(invite) => {
+                invite.room_id == room_id
+                    && invite.expires_at > Self::current_timestamp()
+                    && invite.invitee_id.as_deref().map_or(true, |id| id == player_id)
+            }
+            None => false,
+        }
+    }
+
+    // 输错密码次数过多时返回剩余冷却秒数
+    fn failed_join_cooldown(&self, player_id: &str, room_id: &str) -> Option<u64> {
+        let &(failures, last_failure) = self.failed_joins.get(room_id)?.get(player_id)?;
+        let elapsed = Self::current_timestamp().saturating_sub(last_failure);
+        if failures >= MAX_FAILED_JOINS && elapsed < FAILED_JOIN_COOLDOWN_SECS {
+            Some(FAILED_JOIN_COOLDOWN_SECS - elapsed)
+        } else {
+            None
+        }
+    }
+
+    // 记录一次输错密码，冷却时间过后重新计数
+    fn record_failed_join(&mut self, player_id: &str, room_id: &str) {
+        let now = Self::current_timestamp();
+        let entry = self.failed_joins
+            .entry(room_id.to_string())
+            .or_insert_with(HashMap::new)
+            .entry(player_id.to_string())
+            .or_insert((0, now));
+        if now.saturating_sub(entry.1) >= FAILED_JOIN_COOLDOWN_SECS {
+            entry.0 = 0;
+        }
+        entry.0 += 1;
+        entry.1 = now;
+        if entry.0 >= MAX_FAILED_JOINS {
+            println!("玩家 {} 在房间 {} 连续输错密码 {} 次，进入冷却", player_id, room_id, entry.0);
+        }
+    }
+
+    // 检查玩家是否被踢出指定房间且仍在冷却时间内
+    fn is_player_kicked_from_room(&mut self, player_id: &str, room_id: &str) -> bool {
+        let current_time = Self::current_timestamp();
+        let five_minutes = 5 * 60; // 5分钟 = 300秒
+        
+        if let Some(room_kicks) = self.kicked_players.get_mut(room_id) {
+            if let Some(&kick_time) = room_kicks.get(player_id) {
+                if current_time - kick_time < five_minutes {
+                    return true;
+                } else {
+                    // 冷却时间已过，移除记录
+                    room_kicks.remove(player_id);
+                    // 如果该房间没有踢出记录了，移除整个房间记录
+                    if room_kicks.is_empty() {
+                        self.kicked_players.remove(room_id);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    // 广播消息给所有在线玩家
+    fn broadcast_to_all_players(&self, message: &str) {
+        for (player_id, recipient) in &self.player_sessions {
+            let _ = recipient.do_send(UserMessage::Chat {
+                room_id: "global".to_string(),
+                sender_id: "system".to_string(),
+                username: "系统广播".to_string(),
+                content: message.to_string(),
+            });
+        }
+    }
+    
+    // 处理玩家被击败
+    fn handle_player_elimination(&mut self, room_id: &str, defeated_team: &str, winner_team: &str) {
+        if let Some(room) = self.rooms.get_mut(room_id) {
+            // 找到被击败的玩家并将其转为观众组（组8）
+            let mut defeated_players = Vec::new();
+            for (player_id, team) in &room.player_teams {
+                if team == defeated_team {
+                    defeated_players.push(player_id.clone());
+                }
+            }
+            
+            // 将被击败的玩家移动到观众组
+            for player_id in defeated_players {
+                room.player_groups.insert(player_id.clone(), 8);
+                
+                // 向被击败的玩家发送系统消息
+                if let Some(recipient) = self.player_sessions.get(&player_id) {
+                    let _ = recipient.do_send(UserMessage::Chat {
+                        room_id: room_id.to_string(),
+                        sender_id: "system".to_string(),
+                        username: "系统".to_string(),
+                        content: format!("您已被 {} 击败，现在转为观众身份，拥有全局视野", winner_team),
+                    });
+                }
+            }
+        }
+    }
+    
+    // 向房间内所有玩家发送地图更新
+    fn send_map_update_to_all_players(&mut self, room_id: &str, successful_move_sends: Vec<usize>) {
+        if let Some(room) = self.rooms.get(room_id) {
+            if let Some(ref game_map) = room.game_map {
+                for p_id in &room.players {
+                    if let Some(&group_id) = room.player_groups.get(p_id) {
+                        let player_powers = self.calculate_player_powers(room_id);
+                        
+                        let formatted_tiles: Vec<(usize, usize, String, usize, Option<String>, bool)>;
+                        
+                        if group_id == 8 {
+                            // 观众可以看到全图
+                            formatted_tiles = game_map.get_all_tiles()
+                                .into_iter().map(|(x, y, tile, has_vision)| {
+                                    let (tile_type, count, user_id) = match tile {
+                                        Tile::Wilderness => ("w".to_string(), 0, None),
+                                        Tile::Territory { count, user_id } => ("t".to_string(), count, Some(user_id)),
+                                        Tile::Mountain => ("m".to_string(), 0, None),
+                                        Tile::General { count, user_id } => ("g".to_string(), count, Some(user_id)),
+                                        Tile::Void => ("v".to_string(), 0, None),
+                                        Tile::City { count, user_id, city_type } => {
+                                            let type_str = match city_type {
+                                                CityType::Settlement => "c_settlement",
+                                                CityType::SmallCity => "c_smallcity",
+                                                CityType::LargeCity => "c_largecity",
+                                            };
+                                            (type_str.to_string(), count, user_id)
+                                        },
+                                    };
+                                    (x, y, tile_type, count, user_id, has_vision)
+                                }).collect();
+                        } else {
+                            // 其他玩家根据视野规则看到地图
                             if let Some(team_id) = room.player_teams.get(p_id) {
                                 formatted_tiles = game_map.get_visible_tiles(team_id)
                                     .into_iter().map(|(x, y, tile, has_vision)| {
@@ -1309,6 +2175,7 @@ impl GameServer {
                                 visible_tiles: formatted_tiles,
                                 successful_move_sends: successful_move_sends.clone(),
                                 player_powers: player_powers.clone(),
+                                pings: self.visible_pings(room_id, p_id),
                             });
                         }
                     }
@@ -1495,6 +2362,14 @@ impl GameServer {
                 }
             };
 
+            let current_time = Self::current_timestamp();
+            let muted_players = self.muted_players.get(room_id)
+                .map(|mutes| mutes.iter()
+                    .filter(|(_, until)| **until > current_time)
+                    .map(|(id, _)| self.user_name_table.get(id).cloned().unwrap_or_else(|| "Unknown".to_string()))
+                    .collect())
+                .unwrap_or_default();
+
             Some(ReturnedRoomInfo {
                 name: room.name.clone(),
                 host_player_name: room.host_player_name.clone(),
@@ -1507,6 +2382,7 @@ impl GameServer {
                 groups,
                 room_id: room_id.to_string(),
                 max_players: room.max_players,
+                muted_players,
             })
         } else {
             None
@@ -1522,80 +2398,687 @@ impl GameServer {
             }
         }
     }
-}
-impl Actor for GameServer {
-    type Context = Context<Self>;
-    fn started(&mut self, ctx: &mut Self::Context) {
-        println!("GameServer started");
-        
-        // 启动定时清理任务：每分钟检查一次
-        ctx.run_interval(std::time::Duration::from_secs(60), |_act, ctx| {
-            ctx.address().do_send(CleanupInactiveRooms);
-        });
-        
-        // 启动断线用户清理任务：每10秒检查一次
-        ctx.run_interval(std::time::Duration::from_secs(10), |_act, ctx| {
-            ctx.address().do_send(CleanupDisconnectedPlayers);
-        });
-    }
-    fn stopped(&mut self, _ctx: &mut Self::Context) {
-        println!("GameServer stopped");
-    }
-}
 
-impl Handler<Connect> for GameServer {
-    type Result = Result<(), String>;
-
-    fn handle(&mut self, msg: Connect, _: &mut Context<Self>) -> Self::Result {
-        // 检查是否是重连（用户在断线列表中）
-        let is_reconnecting = self.disconnected_players.contains_key(&msg.user_id);
-        
-        if is_reconnecting {
-            // 重连情况：移除断线记录，恢复会话
-            self.disconnected_players.remove(&msg.user_id);
-            println!("用户 {} 重连成功，恢复会话", msg.user_id);
-        } else if self.player_sessions.contains_key(&msg.user_id) {
-            // 用户已在线且不是重连情况
-            return Err(format!("用户ID {} 已经在线", msg.user_id));
+    // 按出局先后记录队伍（被击败、对局中离开或断线超时），同一队伍只记录一次
+    fn record_elimination(room: &mut RoomInfo, team_id: &str) -> bool {
+        if room.eliminated_teams.iter().any(|team| team == team_id) {
+            return false;
         }
-        
-        println!("{}连接注册会话: 用户ID={}, 用户名={}", 
-                if is_reconnecting { "重" } else { "新" }, msg.user_id, msg.username);
-        
-        // 注册会话和用户名
-        self.player_sessions.insert(msg.user_id.clone(), msg.recipient);
-        self.user_name_table.insert(msg.user_id.clone(), msg.username.clone());
-        
-        // 自动加入全局聊天房间（如果还不在）
-        if let Some(global_room) = self.rooms.get_mut("global") {
-            if !global_room.players.contains(&msg.user_id) {
-                println!("将用户 {} 自动添加到全局房间", msg.user_id);
-                global_room.players.push(msg.user_id.clone());
-                global_room.player_count += 1;
-                
-                // 广播全局房间信息更新
-                if let Some(room_info) = self.get_room_info("global") {
-                    self.broadcast_room_info("global", room_info);
-                }
-            }
+        room.eliminated_teams.push(team_id.to_string());
+        true
+    }
+
+    // 玩家在对局中离开房间后，队伍中已没有成员留在房间时按出局记录
+    fn eliminate_absent_team(room: &mut RoomInfo, player_id: &str) -> bool {
+        if room.status != "playing" && room.status != "paused" {
+            return false;
         }
-        
-        Ok(())
+        let team_id = match room.player_teams.get(player_id) {
+            Some(team_id) => team_id.clone(),
+            None => return false,
+        };
+        let teammate_present = room.player_teams.iter()
+            .any(|(id, team)| team == &team_id && room.players.contains(id));
+        !teammate_present && Self::record_elimination(room, &team_id)
     }
-}
 
-impl Handler<Disconnect> for GameServer {
-    type Result = ();
+    // 对局开始时重置对局统计
+    fn reset_game_stats(room: &mut RoomInfo) {
+        room.game_started_at = Self::current_timestamp();
+        room.team_stats.clear();
+        room.eliminated_teams.clear();
+    }
 
-    fn handle(&mut self, msg: Disconnect, _: &mut Context<Self>) {
-        println!("用户 {} 断开连接，开始30秒会话保留期", msg.player_id);
-        
-        // 移除会话连接，但保留其他信息
-        self.player_sessions.remove(&msg.player_id);
-        
+    // 对局结束时将对局结果写入数据库（在阻塞线程池中执行，不阻塞GameServer）
+    fn record_game_result(&mut self, room_id: &str, winner_team: &str) {
+        // 对局已结束，不再需要快照
+        self.delete_game_snapshot(room_id);
+        // 排位房间只结算一局
+        let is_ranked = self.ranked_rooms.remove(room_id);
+
+        let room = match self.rooms.get(room_id) {
+            Some(room) => room,
+            None => return,
+        };
+
+        // 参与对局的队伍数量
+        let team_count = room.player_teams.values()
+            .collect::<std::collections::HashSet<_>>()
+            .len();
+
+        let players: Vec<PlayerGameRecord> = room.player_teams.iter()
+            .map(|(player_id, team_id)| {
+                let is_winner = team_id == winner_team;
+                // 获胜队伍为第一名，其余队伍越晚被击败名次越靠前
+                let placement = if is_winner {
+                    1
+                } else {
+                    room.eliminated_teams.iter()
+                        .position(|t| t == team_id)
+                        .map(|index| team_count - index)
+                        .unwrap_or(team_count)
+                };
+                let stats = room.team_stats.get(team_id).cloned().unwrap_or_default();
+
+                PlayerGameRecord {
+                    user_id: player_id.clone(),
+                    username: self.user_name_table.get(player_id)
+                        .cloned()
+                        .unwrap_or_else(|| "Unknown".to_string()),
+                    group_id: team_id.trim_start_matches("team_").parse().unwrap_or(0),
+                    team_id: team_id.clone(),
+                    placement: placement as i32,
+                    is_winner,
+                    cities_captured: stats.cities_captured as i32,
+                    generals_captured: stats.generals_captured as i32,
+                }
+            })
+            .collect();
+
+        let room_id = room_id.to_string();
+        let winner_team = winner_team.to_string();
+        let map_seed = room.game_map.as_ref().map(|game_map| game_map.seed as i64);
+        let started_at = Self::to_naive_datetime(room.game_started_at);
+        let ended_at = Self::to_naive_datetime(Self::current_timestamp());
+
+        self.spawn_db_task(move |conn| {
+            let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let game = diesel::insert_into(crate::schema::games::table)
+                    .values(&NewGame {
+                        room_id: &room_id,
+                        winner_team: Some(&winner_team),
+                        player_count: players.len() as i32,
+                        started_at,
+                        ended_at,
+                        map_seed,
+                    })
+                    .get_result::<Game>(conn)?;
+
+                let new_players: Vec<NewGamePlayer> = players.iter()
+                    .map(|p| NewGamePlayer {
+                        game_id: game.id,
+                        user_id: &p.user_id,
+                        username: &p.username,
+                        group_id: p.group_id,
+                        team_id: &p.team_id,
+                        placement: p.placement,
+                        is_winner: p.is_winner,
+                        cities_captured: p.cities_captured,
+                        generals_captured: p.generals_captured,
+                    })
+                    .collect();
+
+                diesel::insert_into(crate::schema::game_players::table)
+                    .values(&new_players)
+                    .execute(conn)?;
+
+                // 排位对局按 Elo 结算双方的排位分
+                if is_ranked {
+                    if let (Some(winner), Some(loser)) = (
+                        players.iter().find(|p| p.is_winner),
+                        players.iter().find(|p| !p.is_winner),
+                    ) {
+                        use crate::schema::users::dsl::{users, rating};
+                        let winner_rating = users.find(&winner.user_id).select(rating).first::<i32>(conn)?;
+                        let loser_rating = users.find(&loser.user_id).select(rating).first::<i32>(conn)?;
+                        let (new_winner_rating, new_loser_rating) =
+                            crate::services::matchmaking::elo_update(winner_rating, loser_rating);
+                        diesel::update(users.find(&winner.user_id)).set(rating.eq(new_winner_rating)).execute(conn)?;
+                        diesel::update(users.find(&loser.user_id)).set(rating.eq(new_loser_rating)).execute(conn)?;
+                        println!("排位结算: {} {} -> {}, {} {} -> {}",
+                                 winner.username, winner_rating, new_winner_rating,
+                                 loser.username, loser_rating, new_loser_rating);
+                    }
+                }
+
+                Ok(game.id)
+            });
+
+            match result {
+                Ok(game_id) => println!("房间 {} 的对局记录已保存，对局ID: {}", room_id, game_id),
+                Err(e) => println!("保存房间 {} 的对局记录失败: {}", room_id, e),
+            }
+        });
+    }
+
+    // 在阻塞线程池中执行数据库操作，不阻塞GameServer（未配置数据库时直接跳过）
+    fn spawn_db_task<F>(&self, task: F)
+    where
+        F: FnOnce(&mut SqliteConnection) + Send + 'static,
+    {
+        let db_pool = match &self.db_pool {
+            Some(pool) => pool.clone(),
+            None => return,
+        };
+
+        tokio::task::spawn_blocking(move || {
+            match db_pool.get() {
+                Ok(mut conn) => task(&mut conn),
+                Err(e) => println!("获取数据库连接失败: {}", e),
+            }
+        });
+    }
+
+    fn persistent_room_count(&self) -> usize {
+        self.rooms.values().filter(|room| room.is_persistent).count()
+    }
+
+    // 将房间当前设置同步到 rooms 表（全局房间除外）
+    fn persist_room(&self, room_id: &str) {
+        if room_id == "global" {
+            return;
+        }
+        let room = match self.rooms.get(room_id) {
+            Some(room) => room,
+            None => return,
+        };
+
+        let row = CreateRooms {
+            name: room.name.clone(),
+            password_hash: room.password_hash.clone(),
+            is_public: room.is_public,
+            room_id: room_id.to_string(),
+            max_players: room.max_players as i32,
+            room_color: room.room_color.clone(),
+            host_player_id: room.host_player_id.clone(),
+            host_player_name: room.host_player_name.clone(),
+            is_persistent: room.is_persistent,
+        };
+        let changes = UpdateRooms {
+            name: Some(row.name.clone()),
+            password_hash: Some(row.password_hash.clone()),
+            is_public: Some(row.is_public),
+            max_players: Some(row.max_players),
+            room_color: Some(row.room_color.clone()),
+            host_player_id: Some(row.host_player_id.clone()),
+            host_player_name: Some(row.host_player_name.clone()),
+            is_persistent: Some(row.is_persistent),
+            ..Default::default()
+        };
+
+        self.send_db_write(DbWrite::SaveRoom(row, changes));
+    }
+
+    // 从 rooms 表中删除房间
+    fn delete_persisted_room(&self, room_id: &str) {
+        self.send_db_write(DbWrite::DeleteRoom(room_id.to_string()));
+    }
+
+    // 删除房间及其附带的状态，之后以相同ID创建的房间不会继承聊天记录等数据
+    // （房间聊天只保存在内存中，数据库中只有全局聊天）
+    fn remove_room(&mut self, room_id: &str) {
+        self.rooms.remove(room_id);
+        self.paused_rooms.remove(room_id);
+        self.ranked_rooms.remove(room_id);
+        self.chat_history.remove(room_id);
+        self.map_pings.remove(room_id);
+        self.delete_persisted_room(room_id);
+        self.delete_game_snapshot(room_id);
+    }
+
+    // 服务器启动时载入最近的全局聊天记录，并从最大ID继续分配聊天记录ID
+    fn restore_chat_history(&mut self) {
+        let db_pool = match &self.db_pool {
+            Some(pool) => pool.clone(),
+            None => return,
+        };
+        let mut conn = match db_pool.get() {
+            Ok(conn) => conn,
+            Err(e) => {
+                println!("载入聊天记录失败，无法获取数据库连接: {}", e);
+                return;
+            }
+        };
+
+        let recent = crate::schema::chat_messages::table
+            .filter(crate::schema::chat_messages::room_id.eq("global"))
+            .order(crate::schema::chat_messages::id.desc())
+            .limit(CHAT_HISTORY_LIMIT as i64)
+            .select(ChatMessage::as_select())
+            .load::<ChatMessage>(&mut conn);
+        let max_id = crate::schema::chat_messages::table
+            .select(diesel::dsl::max(crate::schema::chat_messages::id))
+            .first::<Option<i32>>(&mut conn);
+
+        match (recent, max_id) {
+            (Ok(recent), Ok(max_id)) => {
+                println!("已载入 {} 条全局聊天记录", recent.len());
+                self.next_chat_id = max_id.unwrap_or(0) + 1;
+                self.chat_history.insert("global".to_string(), recent.into_iter().rev().collect());
+            }
+            (Err(e), _) | (_, Err(e)) => println!("载入聊天记录失败: {}", e),
+        }
+    }
+
+    // 服务器启动时恢复常驻房间，并清除上次运行遗留的非常驻房间记录
+    fn restore_persistent_rooms(&mut self) {
+        let db_pool = match &self.db_pool {
+            Some(pool) => pool.clone(),
+            None => return,
+        };
+        let mut conn = match db_pool.get() {
+            Ok(conn) => conn,
+            Err(e) => {
+                println!("恢复常驻房间失败，无法获取数据库连接: {}", e);
+                return;
+            }
+        };
+
+        let stale_rooms = crate::schema::rooms::table
+            .filter(crate::schema::rooms::is_persistent.eq(false));
+        if let Err(e) = diesel::delete(stale_rooms).execute(&mut conn) {
+            println!("清除遗留房间记录失败: {}", e);
+        }
+
+        let persistent_rooms = match Rooms::filter(RoomsFilter {
+            is_persistent: Some(true),
+            ..Default::default()
+        }).load::<Rooms>(&mut conn) {
+            Ok(rooms) => rooms,
+            Err(e) => {
+                println!("恢复常驻房间失败: {}", e);
+                return;
+            }
+        };
+
+        for row in persistent_rooms {
+            if row.room_id.is_empty() || row.room_id == "global" {
+                continue;
+            }
+            println!("恢复常驻房间: {} ({})", row.room_id, row.name);
+            let password_hash = Self::upgrade_legacy_room_password(row.password_hash.clone());
+            if password_hash != row.password_hash {
+                // 迁移前保存的明文密码，哈希后写回数据库
+                let query = crate::schema::rooms::table
+                    .filter(crate::schema::rooms::room_id.eq(&row.room_id));
+                if let Err(e) = diesel::update(query)
+                    .set(crate::schema::rooms::password_hash.eq(&password_hash))
+                    .execute(&mut conn)
+                {
+                    println!("更新房间 {} 的密码哈希失败: {}", row.room_id, e);
+                }
+            }
+            self.rooms.insert(row.room_id, RoomInfo {
+                name: row.name,
+                host_player_id: row.host_player_id,
+                host_player_name: row.host_player_name,
+                admin_player_id: None,
+                admin_player_name: None,
+                status: "waiting".to_string(),
+                max_players: row.max_players as usize,
+                room_color: row.room_color,
+                players: Vec::new(),
+                player_count: 0,
+                force_start_players: Vec::new(),
+                last_activity: Self::current_timestamp(),
+                password_hash,
+                is_public: row.is_public,
+                is_persistent: true,
+                groups: (0..9).map(|i| GroupInfo::new(i)).collect(), // 初始化9个组
+                player_groups: HashMap::new(),
+                // 游戏回合制字段
+                game_turn: 0,
+                turn_half: true,
+                player_actions: HashMap::new(),
+                turn_start_time: None,
+                // 游戏地图字段
+                game_map: None,
+                player_teams: HashMap::new(),
+                // 对局统计字段
+                game_started_at: 0,
+                team_stats: HashMap::new(),
+                eliminated_teams: Vec::new(),
+            });
+        }
+    }
+
+    // 将所有进行中的对局写入快照表，服务器重启后可据此恢复
+    fn save_game_snapshots(&self) {
+        let updated_at = Self::to_naive_datetime(Self::current_timestamp());
+        let snapshots: Vec<NewGameSnapshot> = self.rooms.iter()
+            .filter(|(_, room)| room.status == "playing")
+            .filter_map(|(room_id, room)| {
+                let game_map = room.game_map.as_ref()?;
+                let snapshot = RoomSnapshot {
+                    name: room.name.clone(),
+                    host_player_id: room.host_player_id.clone(),
+                    host_player_name: room.host_player_name.clone(),
+                    max_players: room.max_players,
+                    room_color: room.room_color.clone(),
+                    password_hash: room.password_hash.clone(),
+                    is_public: room.is_public,
+                    is_persistent: room.is_persistent,
+                    player_groups: room.player_groups.clone(),
+                    player_teams: room.player_teams.clone(),
+                    usernames: room.player_teams.keys()
+                        .filter_map(|player_id| {
+                            self.user_name_table.get(player_id)
+                                .map(|username| (player_id.clone(), username.clone()))
+                        })
+                        .collect(),
+                    game_map: game_map.clone(),
+                    game_turn: room.game_turn,
+                    turn_half: room.turn_half,
+                    game_started_at: room.game_started_at,
+                    team_stats: room.team_stats.clone(),
+                    eliminated_teams: room.eliminated_teams.clone(),
+                    is_ranked: self.ranked_rooms.contains(room_id),
+                };
+                match serde_json::to_string(&snapshot) {
+                    Ok(snapshot) => Some(NewGameSnapshot {
+                        room_id: room_id.clone(),
+                        game_turn: room.game_turn as i32,
+                        snapshot,
+                        updated_at,
+                    }),
+                    Err(e) => {
+                        println!("序列化房间 {} 的对局快照失败: {}", room_id, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        if snapshots.is_empty() {
+            return;
+        }
+
+        self.send_db_write(DbWrite::SaveSnapshots(snapshots));
+    }
+
+    // 删除房间的对局快照
+    fn delete_game_snapshot(&self, room_id: &str) {
+        self.send_db_write(DbWrite::DeleteSnapshot(room_id.to_string()));
+    }
+
+    // 房间和快照的写入统一交给写入线程，保证保存和删除按发生顺序落盘（未配置数据库时直接跳过）
+    fn send_db_write(&self, write: DbWrite) {
+        if let Some(writer) = &self.db_writer {
+            if writer.send(write).is_err() {
+                println!("数据库写入线程已退出");
+            }
+        }
+    }
+
+    // 服务器启动时从快照恢复进行中的对局，恢复后的对局处于暂停状态，等待玩家重连
+    fn restore_game_snapshots(&mut self) {
+        let db_pool = match &self.db_pool {
+            Some(pool) => pool.clone(),
+            None => return,
+        };
+        let mut conn = match db_pool.get() {
+            Ok(conn) => conn,
+            Err(e) => {
+                println!("恢复对局快照失败，无法获取数据库连接: {}", e);
+                return;
+            }
+        };
+
+        let records = match crate::schema::game_snapshots::table
+            .select(GameSnapshot::as_select())
+            .load::<GameSnapshot>(&mut conn)
+        {
+            Ok(records) => records,
+            Err(e) => {
+                println!("恢复对局快照失败: {}", e);
+                return;
+            }
+        };
+
+        let now = Self::current_timestamp();
+        for record in records {
+            let snapshot: RoomSnapshot = match serde_json::from_str(&record.snapshot) {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    println!("解析房间 {} 的对局快照失败: {}", record.room_id, e);
+                    continue;
+                }
+            };
+
+            // 已分出胜负的对局（结束前刚好写入了快照）不再恢复
+            if snapshot.game_map.get_active_teams().len() <= 1 {
+                let query = crate::schema::game_snapshots::table
+                    .filter(crate::schema::game_snapshots::room_id.eq(&record.room_id));
+                let _ = diesel::delete(query).execute(&mut conn);
+                continue;
+            }
+
+            let mut groups: Vec<GroupInfo> = (0..9).map(|i| GroupInfo::new(i)).collect();
+            for (player_id, &group_id) in &snapshot.player_groups {
+                if let Some(group) = groups.get_mut(group_id) {
+                    group.players.push(player_id.clone());
+                }
+            }
+            for (player_id, username) in snapshot.usernames {
+                self.user_name_table.entry(player_id).or_insert(username);
+            }
+
+            // 常驻房间的房主以 rooms 表为准，快照中的房主可能已过期
+            let (host_player_id, host_player_name) = match self.rooms.get(&record.room_id) {
+                Some(room) if room.is_persistent => (room.host_player_id.clone(), room.host_player_name.clone()),
+                _ => (snapshot.host_player_id, snapshot.host_player_name),
+            };
+
+            println!("恢复进行中的对局: {} (第{}回合)，等待玩家重连", record.room_id, snapshot.game_turn);
+            self.rooms.insert(record.room_id.clone(), RoomInfo {
+                name: snapshot.name,
+                host_player_id,
+                host_player_name,
+                admin_player_id: None,
+                admin_player_name: None,
+                status: "paused".to_string(),
+                max_players: snapshot.max_players,
+                room_color: snapshot.room_color,
+                players: Vec::new(),
+                player_count: 0,
+                force_start_players: Vec::new(),
+                last_activity: now,
+                password_hash: Self::upgrade_legacy_room_password(snapshot.password_hash),
+                is_public: snapshot.is_public,
+                is_persistent: snapshot.is_persistent,
+                groups,
+                player_groups: snapshot.player_groups,
+                // 游戏回合制字段
+                game_turn: snapshot.game_turn,
+                turn_half: snapshot.turn_half,
+                player_actions: HashMap::new(),
+                turn_start_time: None,
+                // 游戏地图字段
+                game_map: Some(snapshot.game_map),
+                player_teams: snapshot.player_teams,
+                // 对局统计字段
+                game_started_at: snapshot.game_started_at,
+                team_stats: snapshot.team_stats,
+                eliminated_teams: snapshot.eliminated_teams,
+            });
+            // 排位对局恢复后仍需在结束时结算排位分
+            if snapshot.is_ranked {
+                self.ranked_rooms.insert(record.room_id.clone());
+            }
+            self.paused_rooms.insert(record.room_id, now);
+        }
+    }
+
+    // 检查暂停的对局能否继续：所有未被淘汰的玩家都已重连时继续；
+    // 等待超时后只要有玩家回来也继续，未回来的玩家按断线处理
+    fn try_resume_paused_game(&mut self, room_id: &str, timed_out: bool, ctx: &mut Context<Self>) {
+        if !self.paused_rooms.contains_key(room_id) {
+            return;
+        }
+        let room = match self.rooms.get_mut(room_id) {
+            Some(room) => room,
+            None => {
+                self.paused_rooms.remove(room_id);
+                return;
+            }
+        };
+
+        let active_teams = room.game_map.as_ref()
+            .map(|game_map| game_map.get_active_teams())
+            .unwrap_or_default();
+        let waiting_count = room.player_teams.iter()
+            .filter(|(player_id, team_id)| active_teams.contains(team_id) && !room.players.contains(player_id))
+            .count();
+        let any_returned = room.players.iter().any(|player_id| room.player_teams.contains_key(player_id));
+
+        if waiting_count > 0 && !(timed_out && any_returned) {
+            if !timed_out {
+                for p_id in &room.players {
+                    if let Some(recipient) = self.player_sessions.get(p_id) {
+                        let _ = recipient.do_send(UserMessage::Chat {
+                            room_id: room_id.to_string(),
+                            sender_id: "system".to_string(),
+                            username: "系统".to_string(),
+                            content: format!("游戏已暂停，等待 {} 名玩家重连", waiting_count),
+                        });
+                    }
+                }
+            }
+            return;
+        }
+
+        room.status = "playing".to_string();
+        room.player_actions.clear();
+        room.turn_start_time = Some(std::time::Instant::now());
+
+        let content = if waiting_count == 0 {
+            "所有玩家已重连，游戏继续！".to_string()
+        } else {
+            format!("等待超时，仍有 {} 名玩家未重连，游戏继续！", waiting_count)
+        };
+        for p_id in &room.players {
+            if let Some(recipient) = self.player_sessions.get(p_id) {
+                let _ = recipient.do_send(UserMessage::Chat {
+                    room_id: room_id.to_string(),
+                    sender_id: "system".to_string(),
+                    username: "系统".to_string(),
+                    content: content.clone(),
+                });
+            }
+        }
+
+        self.paused_rooms.remove(room_id);
+        println!("房间 {} 的对局恢复进行", room_id);
+
+        // 重新启动回合制系统
+        let room_id_clone = room_id.to_string();
+        ctx.run_later(std::time::Duration::from_millis(500), move |_act, ctx| {
+            ctx.address().do_send(GameTurnMessage {
+                room_id: room_id_clone,
+            });
+        });
+
+        if let Some(room_info) = self.get_room_info(room_id) {
+            self.broadcast_room_info(room_id, room_info);
+        }
+    }
+
+    // 时间戳（秒）转换为数据库时间类型
+    fn to_naive_datetime(timestamp: u64) -> chrono::NaiveDateTime {
+        chrono::DateTime::from_timestamp(timestamp as i64, 0)
+            .unwrap_or_default()
+            .naive_utc()
+    }
+}
+impl Actor for GameServer {
+    type Context = Context<Self>;
+    fn started(&mut self, ctx: &mut Self::Context) {
+        println!("GameServer started");
+        
+        // 启动定时清理任务：每分钟检查一次
+        ctx.run_interval(std::time::Duration::from_secs(60), |_act, ctx| {
+            ctx.address().do_send(CleanupInactiveRooms);
+        });
+        
+        // 启动断线用户清理任务：每10秒检查一次
+        ctx.run_interval(std::time::Duration::from_secs(10), |_act, ctx| {
+            ctx.address().do_send(CleanupDisconnectedPlayers);
+        });
+        
+        // 启动对局快照任务：每5秒保存一次进行中的对局
+        ctx.run_interval(std::time::Duration::from_secs(5), |_act, ctx| {
+            ctx.address().do_send(SnapshotGames);
+        });
+        
+        // 启动游客账户清理任务：每小时清理一次从未使用的游客账户
+        ctx.run_interval(std::time::Duration::from_secs(60 * 60), |_act, ctx| {
+            ctx.address().do_send(PurgeStaleGuests);
+        });
+
+    }
+    fn stopped(&mut self, _ctx: &mut Self::Context) {
+        println!("GameServer stopped");
+    }
+}
+
+impl Handler<Connect> for GameServer {
+    type Result = Result<(), String>;
+
+    fn handle(&mut self, msg: Connect, ctx: &mut Context<Self>) -> Self::Result {
+        // 检查是否是重连（用户在断线列表中）
+        let is_reconnecting = self.disconnected_players.contains_key(&msg.user_id);
+        
+        if is_reconnecting {
+            // 重连情况：移除断线记录，恢复会话
+            self.disconnected_players.remove(&msg.user_id);
+            println!("用户 {} 重连成功，恢复会话", msg.user_id);
+        } else if self.player_sessions.contains_key(&msg.user_id) {
+            // 用户已在线且不是重连情况
+            return Err(format!("用户ID {} 已经在线", msg.user_id));
+        }
+        
+        println!("{}连接注册会话: 用户ID={}, 用户名={}", 
+                if is_reconnecting { "重" } else { "新" }, msg.user_id, msg.username);
+        
+        // 注册会话和用户名
+        self.player_sessions.insert(msg.user_id.clone(), msg.recipient);
+        self.user_name_table.insert(msg.user_id.clone(), msg.username.clone());
+        
+        // 自动加入全局聊天房间（如果还不在）
+        if let Some(global_room) = self.rooms.get_mut("global") {
+            if !global_room.players.contains(&msg.user_id) {
+                println!("将用户 {} 自动添加到全局房间", msg.user_id);
+                global_room.players.push(msg.user_id.clone());
+                global_room.player_count += 1;
+                
+                // 广播全局房间信息更新
+                if let Some(room_info) = self.get_room_info("global") {
+                    self.broadcast_room_info("global", room_info);
+                }
+            }
+        }
+        
+        self.send_chat_backlog("global", &msg.user_id);
+        
+        // 载入好友列表，之后的状态变化由 push_presence 推送给好友
+        let addr = ctx.address();
+        let user_id = msg.user_id.clone();
+        self.spawn_db_task(move |conn| {
+            match Friendship::accepted_friend_ids(conn, &user_id) {
+                Ok(friend_ids) => addr.do_send(SetFriends { user_id, friend_ids }),
+                Err(e) => println!("载入用户 {} 的好友列表失败: {}", user_id, e),
+            }
+        });
+        
+        Ok(())
+    }
+}
+
+impl Handler<Disconnect> for GameServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: Disconnect, _: &mut Context<Self>) {
+        println!("用户 {} 断开连接，开始30秒会话保留期", msg.player_id);
+        
+        // 移除会话连接，但保留其他信息
+        self.player_sessions.remove(&msg.player_id);
+        self.lobby_subscribers.remove(&msg.player_id);
+        
         // 记录断线时间，开始30秒倒计时
         let current_time = Self::current_timestamp();
         self.disconnected_players.insert(msg.player_id.clone(), current_time);
+        self.presence_dirty.insert(msg.player_id.clone());
+        self.push_presence();
         
         // 不立即从房间中移除玩家，保持其在房间状态
         // 但需要通知其他玩家该用户断线了
@@ -1641,6 +3124,7 @@ impl Handler<GetRoomInfo> for GameServer {
                 groups: Vec::new(), // 新增：空的分组列表
                 room_id: msg.room_id,
                 max_players: 16,
+                muted_players: Vec::new(),
             })
         }
     }
@@ -1652,21 +3136,7 @@ impl Handler<GetRoomList> for GameServer {
     fn handle(&mut self, _: GetRoomList, _: &mut Context<Self>) -> Self::Result {
         let room_list = self.rooms.iter()
             .filter(|(id, _)| *id != "global") // 过滤掉全局房间
-            .map(|(id, room)| {
-                (
-                    id.clone(),
-                    room.name.clone(),
-                    room.host_player_name.clone(),
-                    room.status.clone(),
-                    room.player_count,
-                    room.max_players,
-                    room.room_color.clone(),
-                    room.force_start_players.len(),
-                    (room.max_players as f32 * 0.6).ceil() as usize, // 假设需要60%玩家准备
-                    room.is_public,
-                    room.password.is_some(),
-                )
-            })
+            .map(|(id, room)| Self::room_list_entry(id, room))
             .collect();
         
         MessageResult(room_list)
@@ -1683,6 +3153,10 @@ impl Handler<CreateRoom> for GameServer {
             return Box::pin(fut::ready(Err("房间ID已存在".to_string())));
         }
 
+        if msg.is_persistent && self.persistent_room_count() >= MAX_PERSISTENT_ROOMS {
+            return Box::pin(fut::ready(Err("常驻房间数量已达上限".to_string())));
+        }
+
         let room = RoomInfo {
             name: msg.name,
             host_player_id: msg.host_id,
@@ -1696,8 +3170,9 @@ impl Handler<CreateRoom> for GameServer {
             player_count: 0,
             force_start_players: Vec::new(),
             last_activity: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-            password: msg.password,
+            password_hash: msg.password_hash,
             is_public: msg.is_public,
+            is_persistent: msg.is_persistent,
             groups: (0..9).map(|i| GroupInfo::new(i)).collect(), // 初始化9个组
             player_groups: HashMap::new(), // 初始化玩家组映射
             // 游戏回合制字段
@@ -1708,9 +3183,15 @@ impl Handler<CreateRoom> for GameServer {
             // 游戏地图字段
             game_map: None,
             player_teams: HashMap::new(),
+            // 对局统计字段
+            game_started_at: 0,
+            team_stats: HashMap::new(),
+            eliminated_teams: Vec::new(),
         };
 
         self.rooms.insert(room_id.clone(), room);
+        self.persist_room(&room_id);
+        self.sync_lobby();
         println!("房间 {} 已创建", room_id);
 
         Box::pin(fut::ready(Ok(room_id)))
@@ -1721,18 +3202,296 @@ impl Handler<UserMessage> for GameServer {
     type Result = ();
 
     fn handle(&mut self, msg: UserMessage, ctx: &mut Context<Self>) {
+        // 可能改变房间人数、状态、房主或设置的消息处理完后同步大厅
+        let affects_lobby = matches!(msg,
+            UserMessage::JoinRoomWithName { .. }
+                | UserMessage::LeaveRoom { .. }
+                | UserMessage::ForceStart { .. }
+                | UserMessage::DeForceStart { .. }
+                | UserMessage::ShouldStart { .. }
+                | UserMessage::KickPlayer { .. }
+                | UserMessage::UpdateRoomSettings { .. }
+        );
+        self.handle_user_message(msg, ctx);
+        if affects_lobby || self.lobby_dirty {
+            self.sync_lobby();
+        }
+        self.push_presence();
+    }
+}
+
+impl GameServer {
+    // 密码检查通过后把玩家加入房间，valid_invite 为本次加入使用的有效邀请 token
+    fn finish_join_room(&mut self, room_id: String, player_id: String, player_name: String, valid_invite: Option<String>, ctx: &mut Context<Self>) {
+        // 更新用户名表
+        self.user_name_table.insert(player_id.clone(), player_name.clone());
+        
+        // 更新房间活动时间
+        self.update_room_activity(&room_id);
+        
+        // 然后执行与JoinRoom相同的逻辑
+        // 首先检查用户是否已经在其他房间，如果是则先从旧房间移除（但保留global房间连接）
+        let mut old_room_id = None;
+        let mut rooms_to_remove_from: Vec<String> = Vec::new();
+        for (existing_room_id, room) in self.rooms.iter_mut() {
+            // 跳过global房间和目标房间
+            if existing_room_id != &room_id && existing_room_id != "global" && room.players.contains(&player_id) {
+                room.players.retain(|id| id != &player_id);
+                room.force_start_players.retain(|id| id != &player_id);
+                room.player_count -= 1;
+                rooms_to_remove_from.push(existing_room_id.clone());
+                old_room_id = Some(existing_room_id.clone());
+                
+                // 通知旧房间其他玩家该用户离开了
+                for other_player_id in &room.players {
+                    if let Some(recipient) = self.player_sessions.get(other_player_id) {
+                        let _ = recipient.do_send(UserMessage::Chat {
+                            room_id: room_id.clone(),
+                            sender_id: "system".to_string(),
+                            username: "系统".to_string(),
+                            content: format!("玩家 {} 离开房间", player_id),
+                        });
+                    }
+                }
+                break;
+            }
+        }
+        // 处理分组移除，避免双重可变借用
+        for remove_room_id in rooms_to_remove_from {
+            self.remove_player_from_groups(&remove_room_id, &player_id);
+        }
+
+        // 加入新房间
+        let room: &mut RoomInfo = self.rooms.entry(room_id.clone()).or_insert_with(|| RoomInfo {
+            name: format!("房间 #{}", room_id),
+            host_player_id: String::new(),
+            host_player_name: player_name.clone(),
+            admin_player_id: Some(player_id.clone()), // 临时房间初始无管理员
+            admin_player_name: Some(player_name.clone()),
+            status: "waiting".to_string(),
+            max_players: 16, // 默认最大玩家数
+            room_color: "#4F46E5".to_string(), // 默认颜色
+            players: Vec::new(),
+            force_start_players: Vec::new(),
+            player_count: 0,
+            last_activity: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            password_hash: None,
+            is_public: true, // 临时房间不公开
+            is_persistent: false,
+            groups: (0..9).map(|i| GroupInfo::new(i)).collect(), // 初始化9个组
+            player_groups: HashMap::new(), // 初始化玩家组映射
+            // 游戏回合制字段
+            game_turn: 0,
+            turn_half: true,
+            player_actions: HashMap::new(),
+            turn_start_time: None,
+            // 游戏地图字段
+            game_map: None,
+            player_teams: HashMap::new(),
+            // 对局统计字段
+            game_started_at: 0,
+            team_stats: HashMap::new(),
+            eliminated_teams: Vec::new(),
+        });
+        
+        if !room.players.contains(&player_id) {
+            // 检查房间是否已满
+            if room.player_count >= room.max_players {
+                if let Some(recipient) = self.player_sessions.get(&player_id) {
+                    let _ = recipient.do_send(UserMessage::Err("房间已满".to_string()));
+                }
+                return;
+            }
+            
+            
+            room.players.push(player_id.clone());
+            room.player_count += 1;
+            self.presence_dirty.insert(player_id.clone());
+
+            // 直接邀请只能使用一次，邀请链接在有效期内可重复使用
+            if let Some(token) = &valid_invite {
+                if self.invite_tokens.get(token).map_or(false, |invite| invite.invitee_id.is_some()) {
+                    self.invite_tokens.remove(token);
+                }
+            }
+            
+            // 自动为新玩家分配组别（内联逻辑避免双重借用）
+            {
+                let mut target_group_id = 8usize; // 默认分配到观众组
+                let mut should_redirect_to_game = false;
+                
+                // 如果游戏未开始，尝试分配到玩家组
+                if room.status == "waiting" {
+                    // 找到人数最少的玩家组（0-7）
+                    let mut smallest_group_id = 0usize;
+                    let mut smallest_group_size = usize::MAX;
+                    
+                    for i in 0..8 { // 只考虑玩家组0-7
+                        if let Some(group) = room.groups.get(i) {
+                            if group.players.len() < smallest_group_size {
+                                smallest_group_size = group.players.len();
+                                smallest_group_id = i as usize;
+                            }
+                        }
+                    }
+                    
+                    // 如果玩家组还有空位（假设每组最大2人），则分配到玩家组
+                    if smallest_group_size < 2 {
+                        target_group_id = smallest_group_id;
+                    }
+                } else if room.status == "playing" || room.status == "paused" {
+                    // 游戏进行中（或等待重连），检查玩家是否之前就在某个组中
+                    let mut found_previous_group = false;
+                    for (group_id, group) in room.groups.iter_mut().enumerate() {
+                        if group.players.contains(&player_id.to_string()) {
+                            // 玩家之前就在这个组中，保持原有分配
+                            target_group_id = group_id as usize;
+                            found_previous_group = true;
+                            should_redirect_to_game = true;
+                            println!("游戏进行中，玩家 {} 恢复到原有组 {} ({})", player_id, group_id, group.name);
+                            break;
+                        }
+                    }
+                    
+                    if !found_previous_group {
+                        // 新玩家，分配为观众
+                        target_group_id = 8;
+                        should_redirect_to_game = true;
+                        println!("游戏进行中，新玩家 {} 自动分配为观众并将跳转到游戏页面", player_id);
+                    }
+                }
+                
+                // 将玩家添加到选定的组（如果还没有在组中）
+                if let Some(group) = room.groups.get_mut(target_group_id as usize) {
+                    if !group.players.contains(&player_id.to_string()) {
+                        group.players.push(player_id.to_string());
+                    }
+                    room.player_groups.insert(player_id.to_string(), target_group_id);
+                    
+                    if target_group_id == 8 {
+                        println!("玩家 {} 被分配为观众 (游戏状态: {})", player_id, room.status);
+                    } else {
+                        println!("玩家 {} 被自动分配到组 {} ({})", player_id, target_group_id, group.name);
+                    }
+                }
+                
+                // 如果是游戏中加入，发送跳转消息
+                if should_redirect_to_game {
+                    if let Some(recipient) = self.player_sessions.get(&player_id) {
+                        let _ = recipient.do_send(UserMessage::RedirectToGame {
+                            room_id: room_id.clone(),
+                        });
+                    }
+                }
+            }
+            
+            // 如果这是第一个玩家，设置为房主
+            let host_changed = room.host_player_id.is_empty();
+            if host_changed {
+                room.host_player_id = player_id.clone();
+                room.host_player_name = player_name.clone();
+            } else if room.player_count == 1 && room.admin_player_id.is_none() {
+                // 如果房间为空（只有这一个玩家），且没有管理员，自动设为管理员
+                room.admin_player_id = Some(player_id.clone());
+                room.admin_player_name = Some(player_name.clone());
+            }
+            
+            for player in &room.players {
+                if player != &player_id {
+                    // 通知其他玩家加入房间
+                    if let Some(recipient) = self.player_sessions.get(player) {
+                        let _ = recipient.do_send(UserMessage::JoinRoomWithName {
+                            room_id: room_id.clone(),
+                            player_id: player_id.clone(),
+                            player_name: player_name.clone(),
+                            password: None, // 不向其他玩家发送密码
+                            invite_token: None,
+                        });
+                    }
+                } else {
+                    // 给自己也发一份确认
+                    if let Some(recipient) = self.player_sessions.get(&player_id) {
+                        let _ = recipient.do_send(UserMessage::JoinRoomWithName {
+                            room_id: room_id.clone(),
+                            player_id: player_id.clone(),
+                            player_name: player_name.clone(),
+                            password: None, // 不向自己发送密码
+                            invite_token: None,
+                        });
+                    }
+                }
+            }
+            
+            // 广播新房间的更新信息
+            if let Some(room_info) = self.get_room_info(&room_id) {
+                self.broadcast_room_info(&room_id, room_info);
+            }
+            
+            // 新建的临时房间在确定房主后同步到数据库
+            if host_changed {
+                self.persist_room(&room_id);
+            }
+            
+            // 玩家重连到暂停的对局时检查是否可以继续
+            self.try_resume_paused_game(&room_id, false, ctx);
+            
+            // 如果用户从旧房间移除了，也要广播旧房间的更新信息
+            if let Some(old_room) = old_room_id {
+                if let Some(old_room_info) = self.get_room_info(&old_room) {
+                    self.broadcast_room_info(&old_room, old_room_info);
+                }
+            }
+        }
+        
+        // 加入成功（或已在房间中）后发送房间最近的聊天记录
+        self.send_chat_backlog(&room_id, &player_id);
+    }
+
+    fn handle_user_message(&mut self, msg: UserMessage, ctx: &mut Context<Self>) {
         match msg {
             UserMessage::Move { room_id: _, sender_id: _, from: _, direction: _ } => {
                 // to be constructed
             }
             UserMessage::Chat { room_id, sender_id, username, content } => {
                 println!("处理聊天消息: 房间ID={}, 发送者={}, 内容={}", room_id, sender_id, content);
+
+                // 对局中只有仍在场上的玩家能使用全体频道，观战者和已被击败的玩家的消息转到观战频道，防止报点
+                let to_spectators = self.rooms.get(&room_id).map_or(false, |room| {
+                    (room.status == "playing" || room.status == "paused") && Self::chat_team(room, &sender_id).is_none()
+                });
+                if to_spectators {
+                    self.handle_user_message(UserMessage::ChannelChat {
+                        room_id,
+                        sender_id,
+                        username,
+                        content,
+                        channel: "spectator".to_string(),
+                    }, ctx);
+                    return;
+                }
+                
+                let content = match self.check_chat_message(&sender_id, Some(&room_id), &content) {
+                    Ok(content) => content,
+                    Err(message) => {
+                        if let Some(recipient) = self.player_sessions.get(&sender_id) {
+                            let _ = recipient.do_send(UserMessage::Err(message));
+                        }
+                        return;
+                    }
+                };
                 
                 // 更新房间活动时间（除了全局房间）
                 if room_id != "global" {
                     self.update_room_activity(&room_id);
                 }
                 
+                if self.rooms.contains_key(&room_id) {
+                    self.record_chat(&room_id, &sender_id, &username, &content);
+                }
+                
                 if let Some(room) = self.rooms.get(&room_id) {
                     println!("房间 {} 中有 {} 个玩家: {:?}", room_id, room.players.len(), room.players);
                     
@@ -1773,7 +3532,271 @@ impl Handler<UserMessage> for GameServer {
                     println!("警告: 找不到房间 {}", room_id);
                 }
             }
-            UserMessage::JoinRoomWithName { room_id, player_id, player_name, password } => {
+            UserMessage::Ping { room_id, player_id, x, y, marker } => {
+                let result = match self.rooms.get(&room_id) {
+                    None => Err("房间不存在".to_string()),
+                    Some(room) if room.status != "playing" => Err("只能在对局中放置标记".to_string()),
+                    Some(room) => {
+                        let in_bounds = room.game_map.as_ref()
+                            .map_or(false, |game_map| x < game_map.width && y < game_map.height);
+                        if !in_bounds {
+                            Err("标记位置超出地图范围".to_string())
+                        } else if !["attack", "defend", "danger"].contains(&marker.as_str()) {
+                            Err("未知的标记类型".to_string())
+                        } else {
+                            match Self::chat_team(room, &player_id) {
+                                Some(team_id) => Ok(MapPing {
+                                    x,
+                                    y,
+                                    marker,
+                                    username: self.user_name_table.get(&player_id).cloned().unwrap_or_else(|| "Unknown".to_string()),
+                                    player_id: player_id.clone(),
+                                    team_id,
+                                    game_started_at: room.game_started_at,
+                                    expires_turn: room.game_turn + PING_DURATION_TURNS,
+                                }),
+                                None => Err("观战者不能放置标记".to_string()),
+                            }
+                        }
+                    }
+                };
+
+                let ping = match result {
+                    Ok(ping) => ping,
+                    Err(message) => {
+                        if let Some(recipient) = self.player_sessions.get(&player_id) {
+                            let _ = recipient.do_send(UserMessage::Err(message));
+                        }
+                        return;
+                    }
+                };
+
+                // 移除过期和上一局遗留的标记，并限制每个玩家的标记数量；新标记随下一次地图更新发送
+                let (game_started_at, game_turn) = (ping.game_started_at, ping.expires_turn - PING_DURATION_TURNS);
+                let pings = self.map_pings.entry(room_id).or_insert_with(Vec::new);
+                pings.retain(|p| p.game_started_at == game_started_at && p.expires_turn > game_turn);
+                let own_count = pings.iter().filter(|p| p.player_id == player_id).count();
+                if own_count >= MAX_PINGS_PER_PLAYER {
+                    if let Some(index) = pings.iter().position(|p| p.player_id == player_id) {
+                        pings.remove(index);
+                    }
+                }
+                pings.push(ping);
+            }
+            UserMessage::ChannelChat { room_id, sender_id, username, content, channel } => {
+                // 队伍和观战频道的消息不写入聊天记录，避免通过历史记录泄露给其他玩家
+                let recipients: Result<Vec<String>, String> = match self.rooms.get(&room_id) {
+                    None => Err("房间不存在".to_string()),
+                    Some(room) if !room.players.contains(&sender_id) => Err("您不在该房间中".to_string()),
+                    Some(room) => {
+                        let sender_team = Self::chat_team(room, &sender_id);
+                        match (channel.as_str(), sender_team) {
+                            ("team", Some(team_id)) => Ok(room.players.iter()
+                                .filter(|id| Self::chat_team(room, id).as_ref() == Some(&team_id))
+                                .cloned()
+                                .collect()),
+                            ("team", None) => Err("观战者不能使用队伍频道".to_string()),
+                            ("spectator", None) => Ok(room.players.iter()
+                                .filter(|id| Self::chat_team(room, id).is_none())
+                                .cloned()
+                                .collect()),
+                            ("spectator", Some(_)) => Err("只有观战者可以使用观战频道".to_string()),
+                            _ => Err("未知的聊天频道".to_string()),
+                        }
+                    }
+                };
+                let recipients = match recipients.and_then(|recipients| {
+                    self.check_chat_message(&sender_id, Some(&room_id), &content)
+                        .map(|content| (recipients, content))
+                }) {
+                    Ok(result) => result,
+                    Err(message) => {
+                        if let Some(recipient) = self.player_sessions.get(&sender_id) {
+                            let _ = recipient.do_send(UserMessage::Err(message));
+                        }
+                        return;
+                    }
+                };
+                let (recipients, content) = recipients;
+
+                self.update_room_activity(&room_id);
+                for player_id in &recipients {
+                    if let Some(recipient) = self.player_sessions.get(player_id) {
+                        let _ = recipient.do_send(UserMessage::ChannelChat {
+                            room_id: room_id.clone(),
+                            sender_id: sender_id.clone(),
+                            username: username.clone(),
+                            content: content.clone(),
+                            channel: channel.clone(),
+                        });
+                    }
+                }
+            }
+            UserMessage::Whisper { sender_id, target_player_name, content } => {
+                // 私信只能发送给在线玩家
+                let recipient_id = self.player_sessions.keys()
+                    .find(|id| self.user_name_table.get(*id) == Some(&target_player_name))
+                    .cloned();
+                let result = match recipient_id {
+                    None => Err(format!("玩家 {} 不在线，无法发送私信", target_player_name)),
+                    Some(id) if id == sender_id => Err("不能给自己发送私信".to_string()),
+                    Some(id) => self.check_chat_message(&sender_id, None, &content).map(|content| (id, content)),
+                };
+                let (recipient_id, content) = match result {
+                    Ok(result) => result,
+                    Err(message) => {
+                        if let Some(recipient) = self.player_sessions.get(&sender_id) {
+                            let _ = recipient.do_send(UserMessage::Err(message));
+                        }
+                        return;
+                    }
+                };
+
+                let sender_name = self.user_name_table.get(&sender_id).cloned().unwrap_or_else(|| "Unknown".to_string());
+                println!("私信: {} -> {}", sender_id, recipient_id);
+
+                // 同时发给接收者和发送者，发送者据此显示已发送的消息
+                let whisper = UserMessage::WhisperMessage {
+                    sender_id: sender_id.clone(),
+                    sender_name,
+                    recipient_id: recipient_id.clone(),
+                    recipient_name: target_player_name,
+                    content: content.clone(),
+                };
+                for player_id in [&recipient_id, &sender_id] {
+                    if let Some(recipient) = self.player_sessions.get(player_id) {
+                        let _ = recipient.do_send(whisper.clone());
+                    }
+                }
+
+                let row = NewDirectMessage {
+                    sender_id,
+                    recipient_id,
+                    content,
+                    created_at: chrono::Utc::now().naive_utc(),
+                };
+                self.spawn_db_task(move |conn| {
+                    if let Err(e) = diesel::insert_into(crate::schema::direct_messages::table)
+                        .values(&row)
+                        .execute(conn)
+                    {
+                        println!("保存私信失败: {}", e);
+                    }
+                });
+            }
+            UserMessage::InvitePlayer { room_id, inviter_id, target_player_name } => {
+                // 邀请只能发送给在线且不在该房间内的玩家
+                let target_player_id = self.player_sessions.keys()
+                    .find(|id| self.user_name_table.get(*id) == Some(&target_player_name))
+                    .cloned();
+                let result = match (self.rooms.get(&room_id), target_player_id) {
+                    (None, _) => Err("房间不存在".to_string()),
+                    (Some(room), _) if !room.players.contains(&inviter_id) => Err("您不在该房间内".to_string()),
+                    (_, None) => Err(format!("玩家 {} 不在线，无法邀请", target_player_name)),
+                    (Some(room), Some(id)) if room.players.contains(&id) => Err(format!("玩家 {} 已在房间内", target_player_name)),
+                    (Some(room), Some(id)) => Ok((id, room.name.clone())),
+                };
+                let (target_player_id, room_name) = match result {
+                    Ok(result) => result,
+                    Err(message) => {
+                        if let Some(recipient) = self.player_sessions.get(&inviter_id) {
+                            let _ = recipient.do_send(UserMessage::Err(message));
+                        }
+                        return;
+                    }
+                };
+
+                let token = crate::services::auth::generate_token();
+                let expires_at = Self::current_timestamp() + INVITE_TTL_SECS;
+                self.invite_tokens.insert(token.clone(), InviteToken {
+                    room_id: room_id.clone(),
+                    inviter_id: inviter_id.clone(),
+                    invitee_id: Some(target_player_id.clone()),
+                    expires_at,
+                });
+                println!("玩家 {} 邀请 {} 加入房间 {}", inviter_id, target_player_id, room_id);
+
+                let inviter_name = self.user_name_table.get(&inviter_id).cloned().unwrap_or_else(|| "Unknown".to_string());
+                if let Some(recipient) = self.player_sessions.get(&target_player_id) {
+                    let _ = recipient.do_send(UserMessage::RoomInvite {
+                        room_id,
+                        room_name,
+                        inviter_name,
+                        token,
+                        expires_at,
+                    });
+                }
+                if let Some(recipient) = self.player_sessions.get(&inviter_id) {
+                    let _ = recipient.do_send(UserMessage::Ok);
+                }
+            }
+            UserMessage::SubscribeLobby { player_id } => {
+                // 先把已有订阅者同步到最新状态，新订阅者从完整列表开始接收变化
+                self.sync_lobby();
+                self.lobby_rooms = self.lobby_room_entries();
+                self.lobby_subscribers.insert(player_id.clone());
+                if let Some(recipient) = self.player_sessions.get(&player_id) {
+                    let _ = recipient.do_send(UserMessage::Lobby(ServerMessage::LobbyRooms {
+                        rooms: self.lobby_rooms.values().cloned().map(LobbyRoom::from).collect(),
+                    }));
+                }
+            }
+            UserMessage::UnsubscribeLobby { player_id } => {
+                self.lobby_subscribers.remove(&player_id);
+            }
+            UserMessage::DeclineInvite { player_id, token } => {
+                // 只有被邀请者本人可以拒绝邀请，拒绝后 token 立即失效
+                let is_invitee = self.invite_tokens.get(&token)
+                    .map_or(false, |invite| invite.invitee_id.as_deref() == Some(player_id.as_str()));
+                if !is_invitee {
+                    return;
+                }
+                if let Some(invite) = self.invite_tokens.remove(&token) {
+                    let player_name = self.user_name_table.get(&player_id).cloned().unwrap_or_else(|| "Unknown".to_string());
+                    if let Some(recipient) = self.player_sessions.get(&invite.inviter_id) {
+                        let _ = recipient.do_send(UserMessage::Chat {
+                            room_id: invite.room_id.clone(),
+                            sender_id: "system".to_string(),
+                            username: "系统".to_string(),
+                            content: format!("{} 拒绝了您的邀请", player_name),
+                        });
+                    }
+                }
+            }
+            UserMessage::CreateInviteLink { room_id, player_id } => {
+                let result = match self.rooms.get(&room_id) {
+                    None => Err("房间不存在".to_string()),
+                    Some(room) if room.host_player_id != player_id && room.admin_player_id.as_ref() != Some(&player_id) => {
+                        Err("只有房主或管理员可以生成邀请链接".to_string())
+                    }
+                    Some(_) => Ok(()),
+                };
+                if let Err(message) = result {
+                    if let Some(recipient) = self.player_sessions.get(&player_id) {
+                        let _ = recipient.do_send(UserMessage::Err(message));
+                    }
+                    return;
+                }
+
+                let token = crate::services::auth::generate_token();
+                let expires_at = Self::current_timestamp() + INVITE_LINK_TTL_SECS;
+                self.invite_tokens.insert(token.clone(), InviteToken {
+                    room_id: room_id.clone(),
+                    inviter_id: player_id.clone(),
+                    invitee_id: None,
+                    expires_at,
+                });
+                println!("玩家 {} 为房间 {} 生成邀请链接", player_id, room_id);
+
+                if let Some(recipient) = self.player_sessions.get(&player_id) {
+                    let _ = recipient.do_send(UserMessage::InviteLink {
+                        room_id,
+                        token,
+                        expires_at,
+                    });
+                }
+            }
+            UserMessage::JoinRoomWithName { room_id, player_id, player_name, password, invite_token } => {
                 // 检查玩家是否被踢出该房间且仍在冷却时间内
                 if self.is_player_kicked_from_room(&player_id, &room_id) {
                     if let Some(recipient) = self.player_sessions.get(&player_id) {
@@ -1797,219 +3820,79 @@ impl Handler<UserMessage> for GameServer {
                     return;
                 }
 
-                // 更新用户名表
-                self.user_name_table.insert(player_id.clone(), player_name.clone());
-                
-                // 更新房间活动时间
-                self.update_room_activity(&room_id);
-                
-                // 然后执行与JoinRoom相同的逻辑
-                // 首先检查用户是否已经在其他房间，如果是则先从旧房间移除（但保留global房间连接）
-                let mut old_room_id = None;
-                let mut rooms_to_remove_from: Vec<String> = Vec::new();
-                for (existing_room_id, room) in self.rooms.iter_mut() {
-                    // 跳过global房间和目标房间
-                    if existing_room_id != &room_id && existing_room_id != "global" && room.players.contains(&player_id) {
-                        room.players.retain(|id| id != &player_id);
-                        room.force_start_players.retain(|id| id != &player_id);
-                        room.player_count -= 1;
-                        rooms_to_remove_from.push(existing_room_id.clone());
-                        old_room_id = Some(existing_room_id.clone());
-                        
-                        // 通知旧房间其他玩家该用户离开了
-                        for other_player_id in &room.players {
-                            if let Some(recipient) = self.player_sessions.get(other_player_id) {
-                                let _ = recipient.do_send(UserMessage::Chat {
-                                    room_id: room_id.clone(),
-                                    sender_id: "system".to_string(),
-                                    username: "系统".to_string(),
-                                    content: format!("玩家 {} 离开房间", player_id),
-                                });
-                            }
-                        }
-                        break;
-                    }
-                }
-                // 处理分组移除，避免双重可变借用
-                for remove_room_id in rooms_to_remove_from {
-                    self.remove_player_from_groups(&remove_room_id, &player_id);
-                }
-
-                // 加入新房间
-                let room: &mut RoomInfo = self.rooms.entry(room_id.clone()).or_insert_with(|| RoomInfo {
-                    name: format!("房间 #{}", room_id),
-                    host_player_id: String::new(),
-                    host_player_name: player_name.clone(),
-                    admin_player_id: Some(player_id.clone()), // 临时房间初始无管理员
-                    admin_player_name: Some(player_name.clone()),
-                    status: "waiting".to_string(),
-                    max_players: 16, // 默认最大玩家数
-                    room_color: "#4F46E5".to_string(), // 默认颜色
-                    players: Vec::new(),
-                    force_start_players: Vec::new(),
-                    player_count: 0,
-                    last_activity: std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_secs(),
-                    password: None,
-                    is_public: true, // 临时房间不公开
-                    groups: (0..9).map(|i| GroupInfo::new(i)).collect(), // 初始化9个组
-                    player_groups: HashMap::new(), // 初始化玩家组映射
-                    // 游戏回合制字段
-                    game_turn: 0,
-                    turn_half: true,
-                    player_actions: HashMap::new(),
-                    turn_start_time: None,
-                    // 游戏地图字段
-                    game_map: None,
-                    player_teams: HashMap::new(),
-                });
-                
-                if !room.players.contains(&player_id) {
-                    // 检查房间是否已满
-                    if room.player_count >= room.max_players {
+                // 加入不存在的房间会创建临时房间，房间ID需要通过与创建房间相同的检查
+                if !self.rooms.contains_key(&room_id) {
+                    if let Err(message) = crate::services::create_room::validate_room_id(&room_id) {
                         if let Some(recipient) = self.player_sessions.get(&player_id) {
-                            let _ = recipient.do_send(UserMessage::Err("房间已满".to_string()));
+                            let _ = recipient.do_send(UserMessage::Err(message));
                         }
                         return;
                     }
-                    
-                    // 检查房间密码
-                    if let Some(ref room_password) = room.password {
-                        if password.as_deref() != Some(room_password) {
-                            if let Some(recipient) = self.player_sessions.get(&player_id) {
-                                // 区分未提供密码和密码错误的情况
-                                let error_message = if password.is_none() || password.as_deref() == Some("") {
-                                    "需要密码".to_string()
-                                } else {
-                                    "密码错误".to_string()
-                                };
-                                let _ = recipient.do_send(UserMessage::Err(error_message));
-                            }
-                            return;
-                        }
-                    }
-                    
-                    room.players.push(player_id.clone());
-                    room.player_count += 1;
-                    
-                    // 自动为新玩家分配组别（内联逻辑避免双重借用）
-                    {
-                        let mut target_group_id = 8usize; // 默认分配到观众组
-                        let mut should_redirect_to_game = false;
-                        
-                        // 如果游戏未开始，尝试分配到玩家组
-                        if room.status == "waiting" {
-                            // 找到人数最少的玩家组（0-7）
-                            let mut smallest_group_id = 0usize;
-                            let mut smallest_group_size = usize::MAX;
-                            
-                            for i in 0..8 { // 只考虑玩家组0-7
-                                if let Some(group) = room.groups.get(i) {
-                                    if group.players.len() < smallest_group_size {
-                                        smallest_group_size = group.players.len();
-                                        smallest_group_id = i as usize;
-                                    }
-                                }
-                            }
-                            
-                            // 如果玩家组还有空位（假设每组最大2人），则分配到玩家组
-                            if smallest_group_size < 2 {
-                                target_group_id = smallest_group_id;
-                            }
-                        } else if room.status == "playing" {
-                            // 游戏进行中，检查玩家是否之前就在某个组中
-                            let mut found_previous_group = false;
-                            for (group_id, group) in room.groups.iter_mut().enumerate() {
-                                if group.players.contains(&player_id.to_string()) {
-                                    // 玩家之前就在这个组中，保持原有分配
-                                    target_group_id = group_id as usize;
-                                    found_previous_group = true;
-                                    should_redirect_to_game = true;
-                                    println!("游戏进行中，玩家 {} 恢复到原有组 {} ({})", player_id, group_id, group.name);
-                                    break;
-                                }
-                            }
-                            
-                            if !found_previous_group {
-                                // 新玩家，分配为观众
-                                target_group_id = 8;
-                                should_redirect_to_game = true;
-                                println!("游戏进行中，新玩家 {} 自动分配为观众并将跳转到游戏页面", player_id);
-                            }
-                        }
-                        
-                        // 将玩家添加到选定的组（如果还没有在组中）
-                        if let Some(group) = room.groups.get_mut(target_group_id as usize) {
-                            if !group.players.contains(&player_id.to_string()) {
-                                group.players.push(player_id.to_string());
-                            }
-                            room.player_groups.insert(player_id.to_string(), target_group_id);
-                            
-                            if target_group_id == 8 {
-                                println!("玩家 {} 被分配为观众 (游戏状态: {})", player_id, room.status);
-                            } else {
-                                println!("玩家 {} 被自动分配到组 {} ({})", player_id, target_group_id, group.name);
-                            }
-                        }
-                        
-                        // 如果是游戏中加入，发送跳转消息
-                        if should_redirect_to_game {
-                            if let Some(recipient) = self.player_sessions.get(&player_id) {
-                                let _ = recipient.do_send(UserMessage::RedirectToGame {
-                                    room_id: room_id.clone(),
-                                });
-                            }
-                        }
-                    }
-                    
-                    // 如果这是第一个玩家，设置为房主
-                    if room.host_player_id.is_empty() {
-                        room.host_player_id = player_id.clone();
-                        room.host_player_name = player_name.clone();
-                    } else if room.player_count == 1 && room.admin_player_id.is_none() {
-                        // 如果房间为空（只有这一个玩家），且没有管理员，自动设为管理员
-                        room.admin_player_id = Some(player_id.clone());
-                        room.admin_player_name = Some(player_name.clone());
-                    }
-                    
-                    for player in &room.players {
-                        if player != &player_id {
-                            // 通知其他玩家加入房间
-                            if let Some(recipient) = self.player_sessions.get(player) {
-                                let _ = recipient.do_send(UserMessage::JoinRoomWithName {
-                                    room_id: room_id.clone(),
-                                    player_id: player_id.clone(),
-                                    player_name: player_name.clone(),
-                                    password: None, // 不向其他玩家发送密码
-                                });
-                            }
-                        } else {
-                            // 给自己也发一份确认
-                            if let Some(recipient) = self.player_sessions.get(&player_id) {
-                                let _ = recipient.do_send(UserMessage::JoinRoomWithName {
-                                    room_id: room_id.clone(),
-                                    player_id: player_id.clone(),
-                                    player_name: player_name.clone(),
-                                    password: None, // 不向自己发送密码
-                                });
-                            }
-                        }
-                    }
-                    
-                    // 广播新房间的更新信息
-                    if let Some(room_info) = self.get_room_info(&room_id) {
-                        self.broadcast_room_info(&room_id, room_info);
-                    }
-                    
-                    // 如果用户从旧房间移除了，也要广播旧房间的更新信息
-                    if let Some(old_room) = old_room_id {
-                        if let Some(old_room_info) = self.get_room_info(&old_room) {
-                            self.broadcast_room_info(&old_room, old_room_info);
+                }
+
+                // 有效的邀请 token 可以代替房间密码
+                let has_valid_invite = self.is_invite_valid(&invite_token, &room_id, &player_id);
+
+                // 检查房间密码，持有有效邀请或已在房间中时跳过
+                let required_password_hash = self.rooms.get(&room_id)
+                    .filter(|room| !has_valid_invite && !room.players.contains(&player_id))
+                    .and_then(|room| room.password_hash.clone());
+                if let Some(password_hash) = required_password_hash {
+                    // 区分输错次数过多和未提供密码的情况
+                    let error_message = match self.failed_join_cooldown(&player_id, &room_id) {
+                        Some(remaining) => Some(format!("密码错误次数过多，请 {} 秒后再试", remaining)),
+                        None if password.as_deref().map_or(true, str::is_empty) => Some("需要密码".to_string()),
+                        None => None,
+                    };
+                    if let Some(error_message) = error_message {
+                        if let Some(recipient) = self.player_sessions.get(&player_id) {
+                            let _ = recipient.do_send(UserMessage::Err(error_message));
                         }
+                        return;
                     }
+
+                    // argon2 校验耗时较长，放到阻塞线程池中执行，避免卡住所有房间的回合和聊天
+                    let password = password.unwrap_or_default();
+                    let expected_hash = password_hash.clone();
+                    tokio::task::spawn_blocking(move || crate::services::auth::verify_password(&password, &password_hash))
+                        .into_actor(self)
+                        .then(move |res, act, ctx| {
+                            // 校验期间房间可能已被删除或更换了密码
+                            let unchanged = act.rooms.get(&room_id)
+                                .map_or(false, |room| room.password_hash.as_deref() == Some(expected_hash.as_str()));
+                            let error_message = if !unchanged {
+                                Some("房间设置已变化，请重试".to_string())
+                            } else if matches!(res, Ok(true)) {
+                                None
+                            } else {
+                                act.record_failed_join(&player_id, &room_id);
+                                Some("密码错误".to_string())
+                            };
+
+                            match error_message {
+                                Some(error_message) => {
+                                    if let Some(recipient) = act.player_sessions.get(&player_id) {
+                                        let _ = recipient.do_send(UserMessage::Err(error_message));
+                                    }
+                                }
+                                None => {
+                                    if let Some(room_failures) = act.failed_joins.get_mut(&room_id) {
+                                        room_failures.remove(&player_id);
+                                    }
+                                    act.finish_join_room(room_id, player_id, player_name, None, ctx);
+                                    // 异步回调中完成加入，消息处理结束时的同步已经执行过，这里需要自行推送
+                                    act.sync_lobby();
+                                    act.push_presence();
+                                }
+                            }
+                            fut::ready(())
+                        })
+                        .spawn(ctx);
+                    return;
                 }
+
+                let valid_invite = if has_valid_invite { invite_token } else { None };
+                self.finish_join_room(room_id, player_id, player_name, valid_invite, ctx);
             }
             UserMessage::LeaveRoom { room_id, player_id } => {
                 // 更新房间活动时间
@@ -2029,6 +3912,10 @@ impl Handler<UserMessage> for GameServer {
                         room.players.retain(|id| id != &player_id);
                         room.force_start_players.retain(|id| id != &player_id); // 确保也从force_start_players中移除
                         room.player_count -= 1;
+                        self.presence_dirty.insert(player_id.clone());
+                        if Self::eliminate_absent_team(room, &player_id) {
+                            self.lobby_dirty = true;
+                        }
                         println!("玩家 {} 离开了房间 {}", player_id, room_id);
                         // 从分组中移除玩家（内联逻辑避免双重借用）
                         {
@@ -2185,11 +4072,13 @@ impl Handler<UserMessage> for GameServer {
                         
                         // 更新房间状态为游戏中，启动回合制系统
                         room.status = "playing".to_string();
+                        self.presence_dirty.extend(room.players.iter().cloned());
                         room.force_start_players.clear();
                         room.game_turn = 1;
                         room.turn_half = true;
                         room.player_actions.clear();
                         room.turn_start_time = Some(std::time::Instant::now());
+                        Self::reset_game_stats(room);
                         
                         // 根据玩家的组别分配队伍ID
                         let mut active_teams = Vec::new();
@@ -2309,6 +4198,7 @@ impl Handler<UserMessage> for GameServer {
                                             visible_tiles: formatted_tiles,
                                             successful_move_sends: vec![],
                                             player_powers,
+                                            pings: Vec::new(), // 对局刚开始，还没有标记
                                         });
                                     }
                                 }
@@ -2568,6 +4458,101 @@ impl Handler<UserMessage> for GameServer {
                 // 在房间借用结束后更新房间活动时间
                 self.update_room_activity(&room_id);
             }
+            UserMessage::MutePlayer { room_id, muter_id, target_player_name, duration_minutes } => {
+                let result = match self.rooms.get(&room_id) {
+                    None => Err("房间不存在".to_string()),
+                    Some(room) => {
+                        let is_host = room.host_player_id == muter_id;
+                        let is_admin = room.admin_player_id.as_ref() == Some(&muter_id);
+                        let target_player_id = room.players.iter()
+                            .find(|id| self.user_name_table.get(*id) == Some(&target_player_name))
+                            .cloned();
+
+                        if !is_host && !is_admin {
+                            Err("只有房主或管理员可以禁言玩家".to_string())
+                        } else {
+                            match target_player_id {
+                                None => Err("目标玩家不在房间内".to_string()),
+                                Some(id) if id == muter_id => Err("不能禁言自己".to_string()),
+                                Some(id) if id == room.host_player_id => Err("不能禁言房主".to_string()),
+                                Some(id) => Ok(id),
+                            }
+                        }
+                    }
+                };
+
+                let target_player_id = match result {
+                    Ok(id) => id,
+                    Err(message) => {
+                        if let Some(recipient) = self.player_sessions.get(&muter_id) {
+                            let _ = recipient.do_send(UserMessage::Err(message));
+                        }
+                        return;
+                    }
+                };
+
+                let minutes = duration_minutes.unwrap_or(DEFAULT_MUTE_MINUTES).clamp(1, MAX_MUTE_MINUTES);
+                self.muted_players
+                    .entry(room_id.clone())
+                    .or_insert_with(HashMap::new)
+                    .insert(target_player_id.clone(), Self::current_timestamp() + minutes * 60);
+
+                let muter_name = self.user_name_table.get(&muter_id).cloned().unwrap_or_else(|| "管理员".to_string());
+                println!("玩家 {} 在房间 {} 被 {} 禁言 {} 分钟", target_player_id, room_id, muter_id, minutes);
+
+                if let Some(recipient) = self.player_sessions.get(&muter_id) {
+                    let _ = recipient.do_send(UserMessage::Ok);
+                }
+                self.send_room_system_message(&room_id, &format!("{} 被 {} 禁言 {} 分钟", target_player_name, muter_name, minutes));
+                if let Some(room_info) = self.get_room_info(&room_id) {
+                    self.broadcast_room_info(&room_id, room_info);
+                }
+            }
+            UserMessage::UnmutePlayer { room_id, muter_id, target_player_name } => {
+                let result = match self.rooms.get(&room_id) {
+                    None => Err("房间不存在".to_string()),
+                    Some(room) => {
+                        let is_host = room.host_player_id == muter_id;
+                        let is_admin = room.admin_player_id.as_ref() == Some(&muter_id);
+                        let target_player_id = self.muted_players.get(&room_id)
+                            .and_then(|mutes| mutes.keys()
+                                .find(|id| self.user_name_table.get(*id) == Some(&target_player_name))
+                                .cloned());
+
+                        if !is_host && !is_admin {
+                            Err("只有房主或管理员可以解除禁言".to_string())
+                        } else {
+                            target_player_id.ok_or_else(|| "该玩家未被禁言".to_string())
+                        }
+                    }
+                };
+
+                let target_player_id = match result {
+                    Ok(id) => id,
+                    Err(message) => {
+                        if let Some(recipient) = self.player_sessions.get(&muter_id) {
+                            let _ = recipient.do_send(UserMessage::Err(message));
+                        }
+                        return;
+                    }
+                };
+
+                if let Some(room_mutes) = self.muted_players.get_mut(&room_id) {
+                    room_mutes.remove(&target_player_id);
+                    if room_mutes.is_empty() {
+                        self.muted_players.remove(&room_id);
+                    }
+                }
+
+                let muter_name = self.user_name_table.get(&muter_id).cloned().unwrap_or_else(|| "管理员".to_string());
+                if let Some(recipient) = self.player_sessions.get(&muter_id) {
+                    let _ = recipient.do_send(UserMessage::Ok);
+                }
+                self.send_room_system_message(&room_id, &format!("{} 被 {} 解除禁言", target_player_name, muter_name));
+                if let Some(room_info) = self.get_room_info(&room_id) {
+                    self.broadcast_room_info(&room_id, room_info);
+                }
+            }
             UserMessage::KickPlayer { room_id, kicker_id, target_player_name } => {
                 if let Some(room) = self.rooms.get_mut(&room_id) {
                     // 首先通过用户名查找用户ID
@@ -2632,6 +4617,10 @@ impl Handler<UserMessage> for GameServer {
                     // 移除玩家
                     room.players.retain(|id| id != &target_player_id);
                     room.player_count -= 1;
+                    self.presence_dirty.insert(target_player_id.clone());
+                    if Self::eliminate_absent_team(room, &target_player_id) {
+                        self.lobby_dirty = true;
+                    }
                     
                     // 如果被踢出的是管理员，清除管理员信息
                     if room.admin_player_id.as_ref() == Some(&target_player_id) {
@@ -2704,10 +4693,10 @@ impl Handler<UserMessage> for GameServer {
                     }
                 }
                 
-                // 检查房间是否为空并删除
+                // 检查房间是否为空并删除（常驻房间保留）
                 if let Some(room) = self.rooms.get(&room_id) {
-                    if room.players.is_empty() {
-                        self.rooms.remove(&room_id);
+                    if room.players.is_empty() && !room.is_persistent {
+                        self.remove_room(&room_id);
                     }
                 }
                 
@@ -2821,10 +4810,12 @@ impl Handler<UserMessage> for GameServer {
                                         
                                         // 更新房间状态为游戏中
                                         room.status = "playing".to_string();
+                                        self.presence_dirty.extend(room.players.iter().cloned());
                                         room.force_start_players.clear();
                                         room.game_turn = 1;
                                         room.turn_half = true;
                                         room.player_actions.clear();
+                                        Self::reset_game_stats(room);
                                         
                                         // 创建游戏地图和分配队伍
                                         let mut active_teams = Vec::new();
@@ -2956,8 +4947,29 @@ impl Handler<UserMessage> for GameServer {
                     
                     // 立即执行移动操作
                     if let Some(ref mut game_map) = room.game_map {
+                        // 记录目标城市移动前的归属，用于统计占领城市数
+                        let target_city_owner = match game_map.get_tile(to_x, to_y) {
+                            Some(Tile::City { user_id, .. }) => Some(user_id.clone()),
+                            _ => None,
+                        };
+                        
                         match game_map.execute_move(from_x, from_y, to_x, to_y, &team_id, is_half_move) {
                             Ok((winner_team, defeated_team)) => {
+                                // 更新对局统计
+                                if let Some(previous_owner) = target_city_owner {
+                                    let captured = previous_owner.as_deref() != Some(team_id.as_str())
+                                        && matches!(game_map.get_tile(to_x, to_y), Some(Tile::City { user_id: Some(owner), .. }) if owner == &team_id);
+                                    if captured {
+                                        room.team_stats.entry(team_id.clone()).or_default().cities_captured += 1;
+                                    }
+                                }
+                                if let Some(ref defeated) = defeated_team {
+                                    room.team_stats.entry(team_id.clone()).or_default().generals_captured += 1;
+                                    Self::record_elimination(room, defeated);
+                                    self.lobby_dirty = true;
+                                    self.presence_dirty.extend(room.players.iter().cloned());
+                                }
+                                
                                 match (winner_team, defeated_team) {
                                     (Some(winner), Some(defeated)) => {
                                         // 有玩家获胜，游戏结束
@@ -2966,6 +4978,7 @@ impl Handler<UserMessage> for GameServer {
                                         
                                         // 更新房间状态
                                         room.status = "finished".to_string();
+                                        self.presence_dirty.extend(room.players.iter().cloned());
                                         
                                         // 收集被击败的玩家
                                         let mut defeated_players = Vec::new();
@@ -2996,6 +5009,9 @@ impl Handler<UserMessage> for GameServer {
                                             }
                                         }
                                         
+                                        // 保存对局记录
+                                        self.record_game_result(&room_id, &winner);
+                                        
                                         // 向操作玩家发送成功确认
                                         if let Some(recipient) = self.player_sessions.get(&player_id) {
                                             let _ = recipient.do_send(UserMessage::Ok);
@@ -3108,6 +5124,114 @@ impl Handler<UserMessage> for GameServer {
                 // 使用方法广播地图更新
                 self.send_map_update_to_all_players(&room_id, vec![move_id]);
             }
+            UserMessage::UpdateRoomSettings { room_id, player_id, settings } => {
+                // 验证房间存在且请求者是房主
+                let is_host = match self.rooms.get(&room_id) {
+                    Some(room) => room.host_player_id == player_id,
+                    None => {
+                        if let Some(recipient) = self.player_sessions.get(&player_id) {
+                            let _ = recipient.do_send(UserMessage::Err("房间不存在".to_string()));
+                        }
+                        return;
+                    }
+                };
+                
+                if !is_host {
+                    if let Some(recipient) = self.player_sessions.get(&player_id) {
+                        let _ = recipient.do_send(UserMessage::Err("只有房主可以修改房间设置".to_string()));
+                    }
+                    return;
+                }
+                
+                // 使用与创建房间相同的规则验证新设置
+                if let Err((_, message)) = crate::services::create_room::validate_room_settings(
+                    settings.name.as_deref(),
+                    settings.max_players,
+                    settings.room_color.as_deref(),
+                    None, // 密码已由会话校验并哈希
+                ) {
+                    if let Some(recipient) = self.player_sessions.get(&player_id) {
+                        let _ = recipient.do_send(UserMessage::Err(message));
+                    }
+                    return;
+                }
+
+                // 只有管理员可以把房间设为常驻，且总数不能超过上限
+                if settings.is_persistent == Some(true) {
+                    let already_persistent = self.rooms.get(&room_id).map_or(false, |room| room.is_persistent);
+                    let error = if !crate::services::auth::is_admin(&player_id) {
+                        Some("只有管理员可以设置常驻房间")
+                    } else if !already_persistent && self.persistent_room_count() >= MAX_PERSISTENT_ROOMS {
+                        Some("常驻房间数量已达上限")
+                    } else {
+                        None
+                    };
+                    if let Some(error) = error {
+                        if let Some(recipient) = self.player_sessions.get(&player_id) {
+                            let _ = recipient.do_send(UserMessage::Err(error.to_string()));
+                        }
+                        return;
+                    }
+                }
+
+                let password_hash = settings.password_hash;
+                if password_hash.is_some() {
+                    // 密码已更换，之前的失败记录不再有意义
+                    self.failed_joins.remove(&room_id);
+                }
+                
+                if let Some(room) = self.rooms.get_mut(&room_id) {
+                    if let Some(max_players) = settings.max_players {
+                        if max_players < room.player_count {
+                            if let Some(recipient) = self.player_sessions.get(&player_id) {
+                                let _ = recipient.do_send(UserMessage::Err("最大玩家数不能少于当前房间人数".to_string()));
+                            }
+                            return;
+                        }
+                        room.max_players = max_players;
+                    }
+                    if let Some(name) = settings.name {
+                        room.name = name;
+                    }
+                    if let Some(room_color) = settings.room_color {
+                        room.room_color = room_color;
+                    }
+                    if let Some(password_hash) = password_hash {
+                        room.password_hash = password_hash;
+                    }
+                    if let Some(is_public) = settings.is_public {
+                        room.is_public = is_public;
+                    }
+                    if let Some(is_persistent) = settings.is_persistent {
+                        room.is_persistent = is_persistent;
+                    }
+                    
+                    // 通知房间内所有玩家
+                    for p_id in &room.players {
+                        if let Some(recipient) = self.player_sessions.get(p_id) {
+                            let _ = recipient.do_send(UserMessage::Chat {
+                                room_id: room_id.clone(),
+                                sender_id: "system".to_string(),
+                                username: "系统".to_string(),
+                                content: "房主更新了房间设置".to_string(),
+                            });
+                        }
+                    }
+                }
+                
+                self.update_room_activity(&room_id);
+                self.persist_room(&room_id);
+                
+                // 向房主发送确认
+                if let Some(recipient) = self.player_sessions.get(&player_id) {
+                    let _ = recipient.do_send(UserMessage::Ok);
+                }
+                
+                // 广播房间信息更新
+                if let Some(room_info) = self.get_room_info(&room_id) {
+                    self.broadcast_room_info(&room_id, room_info);
+                }
+            }
             _ => {
                 // 其他消息类型暂不处理
             }
@@ -3115,64 +5239,474 @@ impl Handler<UserMessage> for GameServer {
     }
 }
 
-pub fn create_game_server() -> Addr<GameServer> {
-    GameServer::default().start()
+pub fn create_game_server(db_pool: DbPool) -> Addr<GameServer> {
+    let mut game_server = GameServer {
+        db_writer: Some(spawn_db_writer(db_pool.clone())),
+        db_pool: Some(db_pool),
+        ..GameServer::default()
+    };
+    game_server.restore_persistent_rooms();
+    game_server.restore_game_snapshots();
+    game_server.restore_chat_history();
+    game_server.start()
+}
+
+// (x, y, tile_type, count, user_id, has_vision)
+type VisibleTile = (usize, usize, String, usize, Option<String>, bool);
+
+// 客户端当前持有的某个对局的地图，用于计算增量
+#[derive(Default)]
+struct MapView {
+    seq: u64,
+    tiles: Option<HashMap<(usize, usize), VisibleTile>>, // None 表示下一次需要发送完整快照
+    teams: Vec<String>, // 二进制帧中格子归属使用的队伍表，同一对局内只增不减
 }
 
 // 全局WebSocket会话，不绑定特定房间
 pub struct GlobalUserSession {
     user_id: String,
     username: String,
+    ip: String, // 对端IP，用于限流
     addr: Addr<GameServer>,
+    matchmaker: Addr<Matchmaker>,
     successful_move_sends: Vec<usize>,
+    protocol_version: u32, // 与客户端协商后的协议版本，收到 hello 前为 0
+    features: HashSet<String>, // 协商后启用的可选功能
+    map_views: HashMap<String, MapView>, // room_id -> 客户端持有的地图
 }
 
-impl GlobalUserSession {
-    pub fn new(user_id: String, username: String, addr: Addr<GameServer>) -> Self {
-        Self {
-            user_id,
-            username,
-            addr,
-            successful_move_sends: Vec::new(),
-        }
-    }
+impl GlobalUserSession {
+    pub fn new(user_id: String, username: String, ip: String, addr: Addr<GameServer>, matchmaker: Addr<Matchmaker>) -> Self {
+        Self {
+            user_id,
+            username,
+            ip,
+            addr,
+            matchmaker,
+            successful_move_sends: Vec::new(),
+            protocol_version: 0,
+            features: HashSet::new(),
+            map_views: HashMap::new(),
+        }
+    }
+
+    fn send(ctx: &mut ws::WebsocketContext<Self>, message: ServerMessage) {
+        ctx.text(message.to_text());
+    }
+
+    fn handle_join_room(&mut self, room_id: String, player_name: String, password: Option<String>, invite_token: Option<String>, ctx: &mut ws::WebsocketContext<Self>) {
+        let player_id = self.user_id.clone();
+
+        // 带密码的加入请求按IP限流，防止换用新的游客账户绕过按用户计数的冷却
+        if password.as_deref().is_some_and(|p| !p.is_empty()) && PASSWORD_JOINS_BY_IP.record(&self.ip) {
+            println!("IP {} 的房间密码尝试次数过多，拒绝用户 {} 加入房间 {}", self.ip, player_id, room_id);
+            Self::send(ctx, ServerMessage::JoinRoomError {
+                room_id,
+                error: "密码尝试次数过多，请稍后再试".to_string(),
+            });
+            return;
+        }
+
+        // 加入房间后的第一次地图更新发送完整快照
+        self.map_views.remove(&room_id);
+        
+        self.addr
+            .send(UserMessage::JoinRoomWithName {
+                room_id: room_id.clone(),
+                player_id,
+                player_name,
+                password,
+                invite_token,
+            })
+            .into_actor(self)
+            .then(move |res, _act, ctx| {
+                match res {
+                    Ok(()) => {
+                        Self::send(ctx, ServerMessage::JoinRoomSuccess {
+                            room_id,
+                            message: "成功加入房间".to_string(),
+                        });
+                    }
+                    Err(_) => {
+                        Self::send(ctx, ServerMessage::JoinRoomError {
+                            room_id,
+                            error: "服务器内部错误".to_string(),
+                        });
+                    }
+                }
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    // 协商了 map_delta 时只发送与客户端持有的地图相比变化的格子，否则发送完整快照
+    fn map_message(
+        &mut self,
+        room_id: String,
+        visible_tiles: Vec<VisibleTile>,
+        successful_move_sends: Vec<usize>,
+        player_powers: Vec<(String, usize, u32, String)>,
+        pings: Vec<PingMarker>,
+    ) -> ServerMessage {
+        let delta_enabled = self.features.contains(MAP_DELTA_FEATURE);
+        let view = self.map_views.entry(room_id.clone()).or_default();
+        view.seq += 1;
+
+        let previous = match view.tiles.take() {
+            Some(previous) if delta_enabled => previous,
+            _ => {
+                if delta_enabled {
+                    view.tiles = Some(visible_tiles.iter().map(|tile| ((tile.0, tile.1), tile.clone())).collect());
+                }
+                return ServerMessage::MapUpdate {
+                    room_id,
+                    seq: view.seq,
+                    visible_tiles,
+                    successful_move_sends,
+                    player_powers,
+                    pings,
+                };
+            }
+        };
+
+        let current: HashMap<(usize, usize), VisibleTile> = visible_tiles
+            .into_iter()
+            .map(|tile| ((tile.0, tile.1), tile))
+            .collect();
+        let changed_tiles = current
+            .values()
+            .filter(|tile| previous.get(&(tile.0, tile.1)) != Some(*tile))
+            .cloned()
+            .collect();
+        let removed_tiles = previous
+            .keys()
+            .filter(|position| !current.contains_key(*position))
+            .copied()
+            .collect();
+        view.tiles = Some(current);
+
+        ServerMessage::MapDelta {
+            room_id,
+            seq: view.seq,
+            changed_tiles,
+            removed_tiles,
+            successful_move_sends,
+            player_powers,
+            pings,
+        }
+    }
+
+    // 协商协议版本：版本过旧的客户端会收到明确的错误并被断开，版本更新的客户端按服务器版本通信
+    fn handle_hello(&mut self, client_version: u32, features: Vec<String>, ctx: &mut ws::WebsocketContext<Self>) {
+        if client_version < MIN_PROTOCOL_VERSION {
+            self.reject_protocol(client_version, ctx);
+            return;
+        }
+
+        self.protocol_version = client_version.min(PROTOCOL_VERSION);
+        self.features = features
+            .into_iter()
+            .filter(|feature| SERVER_FEATURES.contains(&feature.as_str()))
+            .collect();
+
+        let mut enabled: Vec<String> = self.features.iter().cloned().collect();
+        enabled.sort();
+        println!("用户 {} 协商协议版本 {}，启用功能: {:?}", self.user_id, self.protocol_version, enabled);
+        Self::send(ctx, ServerMessage::Handshake {
+            protocol_version: self.protocol_version,
+            features: enabled,
+        });
+    }
+
+    // 拒绝版本过旧的客户端并断开连接
+    fn reject_protocol(&mut self, client_version: u32, ctx: &mut ws::WebsocketContext<Self>) {
+        println!("用户 {} 的客户端协议版本 {} 过旧，最低支持 {}", self.user_id, client_version, MIN_PROTOCOL_VERSION);
+        Self::send(ctx, ServerMessage::ProtocolMismatch {
+            message: format!("客户端版本过旧（协议版本 {}，服务器最低支持 {}），请刷新页面", client_version, MIN_PROTOCOL_VERSION),
+            client_version,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            protocol_version: PROTOCOL_VERSION,
+        });
+        ctx.close(Some((ws::CloseCode::Policy, "protocol version too old").into()));
+        ctx.stop();
+    }
+
+    // 处理解析后的客户端消息
+    fn handle_client_message(&mut self, message: ClientMessage, ctx: &mut ws::WebsocketContext<Self>) {
+        // hello 必须是第一条消息，先发送其他消息的是不支持协商的旧客户端（协议版本 1）
+        if self.protocol_version == 0 && !matches!(message, ClientMessage::Hello { .. }) {
+            self.reject_protocol(1, ctx);
+            return;
+        }
+
+        match message {
+            ClientMessage::Hello { protocol_version, features } => {
+                self.handle_hello(protocol_version, features.unwrap_or_default(), ctx);
+            }
+            ClientMessage::JoinRoom { room_id, player_name, password, invite_token } => {
+                if player_name == self.username {
+                    self.handle_join_room(room_id, player_name, password, invite_token, ctx);
+                } else {
+                    println!("警告: 用户名不匹配，忽略加入房间请求。");
+                }
+            }
+            ClientMessage::LeaveRoom { room_id } => {
+                self.addr.do_send(UserMessage::LeaveRoom {
+                    room_id,
+                    player_id: self.user_id.clone(),
+                });
+            }
+            ClientMessage::Chat { room_id, content, channel } => {
+                // 指定了 team/spectator 频道时只发送给队友或观战者
+                if let Some(channel) = channel.filter(|c| c != "all") {
+                    self.addr.do_send(UserMessage::ChannelChat {
+                        room_id,
+                        sender_id: self.user_id.clone(),
+                        username: self.username.clone(),
+                        content,
+                        channel,
+                    });
+                } else {
+                    println!("GlobalUserSession 发送聊天消息: 房间={}, 用户={}, 内容={}", room_id, self.user_id, content);
+                    self.addr.do_send(UserMessage::Chat {
+                        room_id,
+                        sender_id: self.user_id.clone(),
+                        username: self.username.clone(),
+                        content,
+                    });
+                }
+            }
+            ClientMessage::Ping { room_id, x, y, marker } => {
+                self.addr.do_send(UserMessage::Ping {
+                    room_id,
+                    player_id: self.user_id.clone(),
+                    x,
+                    y,
+                    marker,
+                });
+            }
+            ClientMessage::Whisper { target_player_name, content } => {
+                self.addr.do_send(UserMessage::Whisper {
+                    sender_id: self.user_id.clone(),
+                    target_player_name,
+                    content,
+                });
+            }
+            ClientMessage::QuickPlay => {
+                let game_server = self.addr.clone();
+                let user_id = self.user_id.clone();
+                let username = self.username.clone();
+                async move {
+                    crate::services::quick_play::find_or_create_room(&game_server, &user_id, &username).await
+                }
+                    .into_actor(self)
+                    .map(|res, _act, ctx| {
+                        let response = match res {
+                            Ok(result) => ServerMessage::QuickPlay {
+                                room_id: result.room_id,
+                                name: result.name,
+                                created: result.created,
+                            },
+                            Err(message) => ServerMessage::Error { message },
+                        };
+                        Self::send(ctx, response);
+                    })
+                    .spawn(ctx);
+            }
+            ClientMessage::JoinQueue => {
+                self.matchmaker.do_send(JoinQueue {
+                    user_id: self.user_id.clone(),
+                    username: self.username.clone(),
+                    recipient: ctx.address().recipient(),
+                });
+            }
+            ClientMessage::LeaveQueue => {
+                self.matchmaker.do_send(LeaveQueue {
+                    user_id: self.user_id.clone(),
+                });
+            }
+            ClientMessage::SubscribeLobby => {
+                self.addr.do_send(UserMessage::SubscribeLobby {
+                    player_id: self.user_id.clone(),
+                });
+            }
+            ClientMessage::UnsubscribeLobby => {
+                self.addr.do_send(UserMessage::UnsubscribeLobby {
+                    player_id: self.user_id.clone(),
+                });
+            }
+            ClientMessage::Invite { room_id, target_player_name } => {
+                self.addr.do_send(UserMessage::InvitePlayer {
+                    room_id,
+                    inviter_id: self.user_id.clone(),
+                    target_player_name,
+                });
+            }
+            ClientMessage::DeclineInvite { token } => {
+                self.addr.do_send(UserMessage::DeclineInvite {
+                    player_id: self.user_id.clone(),
+                    token,
+                });
+            }
+            ClientMessage::CreateInviteLink { room_id } => {
+                self.addr.do_send(UserMessage::CreateInviteLink {
+                    room_id,
+                    player_id: self.user_id.clone(),
+                });
+            }
+            ClientMessage::GetRoomInfo { room_id } => {
+                let game_server_addr = self.addr.clone();
+                let ctx_addr = ctx.address();
+                
+                let fut = async move {
+                    match game_server_addr.send(GetRoomInfo {
+                        room_id: room_id.clone(),
+                    }).await {
+                        Ok(room_info) => {
+                            ctx_addr.do_send(room_info);
+                        }
+                        Err(_) => {
+                            println!("获取房间信息失败: {}", room_id);
+                        }
+                    }
+                };
+                
+                Arbiter::current().spawn(fut);
+            }
+            ClientMessage::ForceStart { room_id } => {
+                self.addr.do_send(UserMessage::ForceStart {
+                    room_id,
+                    player_id: self.user_id.clone(),
+                });
+            }
+            ClientMessage::DeForceStart { room_id } => {
+                self.addr.do_send(UserMessage::DeForceStart {
+                    room_id,
+                    player_id: self.user_id.clone(),
+                });
+            }
+            ClientMessage::ShouldStart { room_id } => {
+                println!("收到should_start请求，房间: {}", room_id);
+                self.addr.do_send(UserMessage::ShouldStart { room_id });
+            }
+            ClientMessage::GameAction { room_id, action } => {
+                self.addr.do_send(UserMessage::GameAction {
+                    room_id,
+                    player_id: self.user_id.clone(),
+                    action,
+                });
+            }
+            ClientMessage::RequestMapSnapshot { room_id } => {
+                println!("用户 {} 请求房间 {} 的地图快照", self.user_id, room_id);
+                if let Some(view) = self.map_views.get_mut(&room_id) {
+                    view.tiles = None;
+                }
+            }
+            ClientMessage::GameMove { room_id, from_x, from_y, to_x, to_y, move_id, is_half_move } => {
+                println!("向GameServer发送GameMove: room_id={}, from=({},{}), to=({},{}), move_id={}", 
+                         room_id, from_x, from_y, to_x, to_y, move_id);
+                self.addr.do_send(UserMessage::GameMove {
+                    room_id,
+                    player_id: self.user_id.clone(),
+                    from_x,
+                    from_y,
+                    to_x,
+                    to_y,
+                    move_id,
+                    is_half_move: is_half_move.unwrap_or(false),
+                });
+            }
+            ClientMessage::SetAdmin { room_id, target_player_name } => {
+                self.addr.do_send(UserMessage::SetAdmin {
+                    room_id,
+                    host_id: self.user_id.clone(),
+                    target_player_name,
+                });
+            }
+            ClientMessage::RemoveAdmin { room_id } => {
+                self.addr.do_send(UserMessage::RemoveAdmin {
+                    room_id,
+                    host_id: self.user_id.clone(),
+                });
+            }
+            ClientMessage::KickPlayer { room_id, target_player_name } => {
+                self.addr.do_send(UserMessage::KickPlayer {
+                    room_id,
+                    kicker_id: self.user_id.clone(),
+                    target_player_name,
+                });
+            }
+            ClientMessage::MutePlayer { room_id, target_player_name, duration_minutes } => {
+                self.addr.do_send(UserMessage::MutePlayer {
+                    room_id,
+                    muter_id: self.user_id.clone(),
+                    target_player_name,
+                    duration_minutes,
+                });
+            }
+            ClientMessage::UnmutePlayer { room_id, target_player_name } => {
+                self.addr.do_send(UserMessage::UnmutePlayer {
+                    room_id,
+                    muter_id: self.user_id.clone(),
+                    target_player_name,
+                });
+            }
+            ClientMessage::ChangeGroup { room_id, target_group_id } => {
+                self.addr.do_send(UserMessage::ChangeGroup {
+                    room_id,
+                    player_id: self.user_id.clone(),
+                    target_group_id,
+                });
+            }
+            ClientMessage::UpdateRoomSettings { room_id, name, max_players, room_color, password, is_public, is_persistent } => {
+                let player_id = self.user_id.clone();
+                let settings = RoomSettingsUpdate {
+                    name,
+                    max_players,
+                    room_color,
+                    password_hash: None,
+                    is_public,
+                    is_persistent,
+                };
+
+                // 空字符串与 null 一样表示移除密码
+                match password.map(|p| p.filter(|p| !p.is_empty())) {
+                    Some(Some(password)) => {
+                        if let Err((_, message)) = crate::services::create_room::validate_room_settings(None, None, None, Some(&password)) {
+                            Self::send(ctx, ServerMessage::Error { message });
+                            return;
+                        }
 
-    fn handle_join_room(&mut self, room_id: &str, player_name: &str, password: &Option<String>, ctx: &mut ws::WebsocketContext<Self>) {
-        let room_id = room_id.to_string();
-        let player_name = player_name.to_string();
-        let password = password.clone();
-        let player_id = self.user_id.clone();
-        
-        self.addr
-            .send(UserMessage::JoinRoomWithName {
-                room_id: room_id.clone(),
-                player_id,
-                player_name: player_name.clone(),
-                password,
-            })
-            .into_actor(self)
-            .then(move |res, _act, ctx| {
-                match res {
-                    Ok(()) => {
-                        let response = serde_json::json!({
-                            "type": "join_room_success",
-                            "room_id": room_id,
-                            "message": "成功加入房间"
-                        });
-                        ctx.text(response.to_string());
-                    }
-                    Err(_) => {
-                        let response = serde_json::json!({
-                            "type": "join_room_error",
-                            "room_id": room_id, 
-                            "error": "服务器内部错误"
-                        });
-                        ctx.text(response.to_string());
+                        // 密码哈希计算量较大，放到阻塞线程池中执行，GameServer 只保存结果
+                        web::block(move || crate::services::auth::hash_password(&password))
+                            .into_actor(self)
+                            .then(move |res, act, ctx| {
+                                match res {
+                                    Ok(Ok(password_hash)) => act.addr.do_send(UserMessage::UpdateRoomSettings {
+                                        room_id,
+                                        player_id,
+                                        settings: RoomSettingsUpdate {
+                                            password_hash: Some(Some(password_hash)),
+                                            ..settings
+                                        },
+                                    }),
+                                    _ => {
+                                        println!("房间密码哈希失败");
+                                        Self::send(ctx, ServerMessage::Error { message: "设置房间密码失败".to_string() });
+                                    }
+                                }
+                                fut::ready(())
+                            })
+                            .spawn(ctx);
                     }
+                    password_hash => self.addr.do_send(UserMessage::UpdateRoomSettings {
+                        room_id,
+                        player_id,
+                        settings: RoomSettingsUpdate { password_hash, ..settings },
+                    }),
                 }
-                fut::ready(())
-            })
-            .wait(ctx);
+            }
+        }
     }
 }
 
@@ -3180,6 +5714,13 @@ impl Actor for GlobalUserSession {
     type Context = ws::WebsocketContext<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
+        // 超时仍未发送 hello 的旧客户端同样按版本 1 拒绝
+        ctx.run_later(std::time::Duration::from_secs(HELLO_TIMEOUT_SECS), |act, ctx| {
+            if act.protocol_version == 0 {
+                act.reject_protocol(1, ctx);
+            }
+        });
+
         let addr: Addr<GlobalUserSession> = ctx.address();
         let user_id = self.user_id.clone();
         let username = self.username.clone();
@@ -3197,20 +5738,17 @@ impl Actor for GlobalUserSession {
                         println!("全局WebSocket会话建立成功，用户ID: {}, 用户名: {}", user_id, username);
                         
                         // 发送连接成功消息
-                        let response = serde_json::json!({
-                            "type": "connected",
-                            "user_id": user_id,
-                            "username": username
+                        Self::send(ctx, ServerMessage::Connected {
+                            user_id,
+                            username,
+                            protocol_version: PROTOCOL_VERSION,
+                            min_protocol_version: MIN_PROTOCOL_VERSION,
+                            features: SERVER_FEATURES.iter().map(|feature| feature.to_string()).collect(),
                         });
-                        ctx.text(response.to_string());
                     }
                     Ok(Err(error)) => {
                         println!("用户连接失败: {}", error);
-                        let error_response = serde_json::json!({
-                            "type": "error",
-                            "message": error
-                        });
-                        ctx.text(error_response.to_string());
+                        Self::send(ctx, ServerMessage::Error { message: error });
                         ctx.stop();
                     }
                     Err(_) => {
@@ -3224,6 +5762,7 @@ impl Actor for GlobalUserSession {
     }
 
     fn stopped(&mut self, _: &mut Self::Context) {
+        self.matchmaker.do_send(LeaveQueue { user_id: self.user_id.clone() });
         self.addr.do_send(Disconnect { player_id: self.user_id.clone() });
     }
 }
@@ -3232,155 +5771,129 @@ impl Handler<UserMessage> for GlobalUserSession {
     type Result = ();
 
     fn handle(&mut self, msg: UserMessage, ctx: &mut Self::Context) {
-        match msg {
-            UserMessage::Chat { room_id, sender_id, username, content } => {
-                let chat_json = serde_json::json!({
-                    "type": "chat_message",
-                    "room_id": room_id,
-                    "user_id": sender_id.to_string(),
-                    "username": username,
-                    "message": content,
-                    "content": content, // 同时提供 content 字段以兼容前端
-                });
-                println!("GlobalUserSession 向客户端发送聊天消息: {}", chat_json);
-                ctx.text(chat_json.to_string());
-            }
-            UserMessage::JoinRoomWithName { room_id, player_id, player_name, password: _ } => {
-                let join_json = serde_json::json!({
-                    "type": "join_room",
-                    "room_id": room_id,
-                    "player_id": player_id,
-                    "player_name": player_name,
-                });
-                ctx.text(join_json.to_string());
-            }
-            UserMessage::LeaveRoom { room_id, player_id } => {
-                let leave_json = serde_json::json!({
-                    "type": "leave_room",
-                    "room_id": room_id,
-                    "player_id": player_id,
-                });
-                ctx.text(leave_json.to_string());
-            }
-            UserMessage::RoomInfoUpdate(room_info) => {
-                // 手动构建分组JSON数组，只传递ID和玩家列表
-                let groups_json: Vec<serde_json::Value> = room_info.groups
-                    .iter()
-                    .map(|group| serde_json::json!({
-                        "id": group.id,
-                        "players": group.players
-                    }))
-                    .collect();
-
-                let room_info_json = serde_json::json!({
-                    "type": "room_info",
-                    "name": room_info.name,
-                    "host_player_name": room_info.host_player_name,
-                    "admin_player_name": room_info.admin_player_name,
-                    "status": room_info.status,
-                    "players": room_info.players,
-                    "player_count": room_info.player_count,
-                    "force_start_players": room_info.force_start_players,
-                    "required_to_start": room_info.required_to_start,
-                    "groups": groups_json,
-                    "room_id": room_info.room_id,
-                    "max_players": room_info.max_players,
-                });
-                println!("GlobalUserSession 发送房间信息更新: {}", room_info_json);
-                ctx.text(room_info_json.to_string());
-            }
-            UserMessage::Ok => {
-                let ok_json = serde_json::json!({
-                    "type": "ok",
-                });
-                ctx.text(ok_json.to_string());
-            }
-            UserMessage::MoveOk{} => {
-                let move_ok_json = serde_json::json!({
-                    "type": "move_ok",
-                });
-                ctx.text(move_ok_json.to_string());
-            }
-            UserMessage::Err(err_msg) => {
-                let err_json = serde_json::json!({
-                    "type": "error",
-                    "message": err_msg,
-                });
-                ctx.text(err_json.to_string());
-            }
-            UserMessage::RedirectToHome { reason } => {
-                let redirect_json = serde_json::json!({
-                    "type": "redirect_to_home",
-                    "reason": reason,
-                });
-                ctx.text(redirect_json.to_string());
-            }
-            UserMessage::RedirectToGame { room_id } => {
-                let redirect_json = serde_json::json!({
-                    "type": "redirect_to_game",
-                    "room_id": room_id,
-                });
-                ctx.text(redirect_json.to_string());
+        let message = match msg {
+            UserMessage::Chat { room_id, sender_id, username, content } => ServerMessage::ChatMessage {
+                room_id,
+                user_id: sender_id,
+                username,
+                message: content.clone(),
+                content,
+                channel: None,
+            },
+            UserMessage::ChannelChat { room_id, sender_id, username, content, channel } => ServerMessage::ChatMessage {
+                room_id,
+                user_id: sender_id,
+                username,
+                message: content.clone(),
+                content,
+                channel: Some(channel),
+            },
+            UserMessage::JoinRoomWithName { room_id, player_id, player_name, .. } => ServerMessage::JoinRoom {
+                room_id,
+                player_id,
+                player_name,
+            },
+            UserMessage::LeaveRoom { room_id, player_id } => ServerMessage::LeaveRoom { room_id, player_id },
+            UserMessage::RoomInfoUpdate(room_info) => room_info.into(),
+            UserMessage::ChatHistory { room_id, messages } => ServerMessage::ChatHistory { room_id, messages },
+            UserMessage::FriendUpdate { user_id, username, event, presence } => ServerMessage::FriendUpdate {
+                user_id,
+                username,
+                event,
+                presence,
+            },
+            UserMessage::MatchFound { room_id, opponent_name, rating, opponent_rating } => ServerMessage::MatchFound {
+                room_id,
+                opponent_name,
+                rating,
+                opponent_rating,
+            },
+            UserMessage::RoomInvite { room_id, room_name, inviter_name, token, expires_at } => ServerMessage::RoomInvite {
+                room_id,
+                room_name,
+                inviter_name,
+                token,
+                expires_at,
+            },
+            UserMessage::InviteLink { room_id, token, expires_at } => ServerMessage::InviteLink { room_id, token, expires_at },
+            UserMessage::WhisperMessage { sender_id, sender_name, recipient_id, recipient_name, content } => ServerMessage::Whisper {
+                sender_id,
+                username: sender_name,
+                recipient_id,
+                recipient_name,
+                content,
+            },
+            UserMessage::SetUserInfo { user_id, username } => {
+                // 用户名被修改后同步到当前会话
+                self.username = username.clone();
+                ServerMessage::UsernameChanged { user_id, username }
             }
+            UserMessage::Ok => ServerMessage::Ok,
+            UserMessage::MoveOk{} => ServerMessage::MoveOk,
+            UserMessage::Err(message) => ServerMessage::Error { message },
+            UserMessage::RedirectToHome { reason } => ServerMessage::RedirectToHome { reason },
+            UserMessage::RedirectToGame { room_id } => ServerMessage::RedirectToGame { room_id },
             UserMessage::StartGame { room_id } => {
-                let start_game_json = serde_json::json!({
-                    "type": "start_game",
-                    "room_id": room_id,
-                });
-                println!("GlobalUserSession 发送游戏开始消息: {}", start_game_json);
-                ctx.text(start_game_json.to_string());
+                self.map_views.remove(&room_id);
+                ServerMessage::StartGame { room_id }
             }
             UserMessage::EndGame { room_id } => {
-                let end_game_json = serde_json::json!({
-                    "type": "end_game",
-                    "room_id": room_id,
-                });
-                println!("GlobalUserSession 发送游戏结束消息: {}", end_game_json);
-                ctx.text(end_game_json.to_string());
-            }
-            UserMessage::GameTurnUpdate { room_id, turn, turn_half, actions } => {
-                let turn_update_json = serde_json::json!({
-                    "type": "game_turn_update",
-                    "room_id": room_id,
-                    "turn": turn,
-                    "turn_half": turn_half,
-                    "actions": actions,
-                });
-                println!("GlobalUserSession 发送回合更新消息: {}", turn_update_json);
-                ctx.text(turn_update_json.to_string());
-            }
-            UserMessage::MapUpdate { room_id, visible_tiles, successful_move_sends, player_powers } => {
-                let map_update_json = serde_json::json!({
-                    "type": "map_update",
-                    "room_id": room_id,
-                    "visible_tiles": visible_tiles,
-                    "successful_move_sends": successful_move_sends,
-                    "player_powers": player_powers,
-                });
-                println!("GlobalUserSession 发送地图更新消息: {}", map_update_json);
-                ctx.text(map_update_json.to_string());
-            }
-            UserMessage::GameWin { room_id, winner } => {
-                let game_win_json = serde_json::json!({
-                    "type": "game_win",
-                    "room_id": room_id,
-                    "winner": winner,
-                });
-                println!("GlobalUserSession 发送游戏胜利消息: {}", game_win_json);
-                ctx.text(game_win_json.to_string());
-            }
-            UserMessage::PlayerEliminated { room_id, eliminated_player, eliminated_by } => {
-                let eliminated_json = serde_json::json!({
-                    "type": "player_eliminated",
-                    "room_id": room_id,
-                    "eliminated_player": eliminated_player,
-                    "eliminated_by": eliminated_by,
-                });
-                println!("GlobalUserSession 发送玩家被击败消息: {}", eliminated_json);
-                ctx.text(eliminated_json.to_string());
+                self.map_views.remove(&room_id);
+                ServerMessage::EndGame { room_id }
+            }
+            UserMessage::GameTurnUpdate { room_id, turn, turn_half, actions } => ServerMessage::GameTurnUpdate {
+                room_id,
+                turn,
+                turn_half,
+                actions,
+            },
+            UserMessage::MapUpdate { room_id, visible_tiles, successful_move_sends, player_powers, pings } => {
+                let pings = pings.iter().map(PingMarker::from).collect();
+                self.map_message(room_id, visible_tiles, successful_move_sends, player_powers, pings)
             }
+            UserMessage::GameWin { room_id, winner } => ServerMessage::GameWin { room_id, winner },
+            UserMessage::PlayerEliminated { room_id, eliminated_player, eliminated_by } => ServerMessage::PlayerEliminated {
+                room_id,
+                eliminated_player,
+                eliminated_by,
+            },
+            UserMessage::Lobby(message) => message,
+            _ => return,
+        };
+
+        if message.is_game_traffic() && self.features.contains(MSGPACK_FEATURE) {
+            let frame = match &message {
+                ServerMessage::MapUpdate { room_id, .. } | ServerMessage::MapDelta { room_id, .. } => {
+                    message.to_msgpack(&mut self.map_views.entry(room_id.clone()).or_default().teams)
+                }
+                _ => message.to_msgpack(&mut Vec::new()),
+            };
+            ctx.binary(frame);
+            return;
+        }
+
+        let text = message.to_text();
+        match message {
+            ServerMessage::ChatMessage { .. } => println!("GlobalUserSession 向客户端发送聊天消息: {}", text),
+            ServerMessage::RoomInfo { .. } => println!("GlobalUserSession 发送房间信息更新: {}", text),
+            ServerMessage::StartGame { .. }
+            | ServerMessage::EndGame { .. }
+            | ServerMessage::GameWin { .. }
+            | ServerMessage::PlayerEliminated { .. } => println!("GlobalUserSession 发送游戏消息: {}", text),
             _ => {}
         }
+        ctx.text(text);
+    }
+}
+
+impl Handler<QueueStatus> for GlobalUserSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: QueueStatus, ctx: &mut Self::Context) {
+        Self::send(ctx, ServerMessage::QueueStatus {
+            status: msg.status,
+            rating: msg.rating,
+        });
     }
 }
 
@@ -3388,31 +5901,9 @@ impl Handler<ReturnedRoomInfo> for GlobalUserSession {
     type Result = ();
 
     fn handle(&mut self, msg: ReturnedRoomInfo, ctx: &mut Self::Context) {
-        // 手动构建分组JSON数组，只传递ID和玩家列表
-        let groups_json: Vec<serde_json::Value> = msg.groups
-            .iter()
-            .map(|group| serde_json::json!({
-                "id": group.id,
-                "players": group.players
-            }))
-            .collect();
-
-        let room_info_json = serde_json::json!({
-            "type": "room_info",
-            "name": msg.name,
-            "host_player_name": msg.host_player_name,
-            "admin_player_name": msg.admin_player_name,
-            "status": msg.status,
-            "players": msg.players,
-            "player_count": msg.player_count,
-            "force_start_players": msg.force_start_players,
-            "required_to_start": msg.required_to_start,
-            "groups": groups_json,
-            "room_id": msg.room_id,
-            "max_players": msg.max_players,
-        });
-        println!("GlobalUserSession 发送获取的房间信息: {}", room_info_json);
-        ctx.text(room_info_json.to_string());
+        let text = ServerMessage::from(msg).to_text();
+        println!("GlobalUserSession 发送获取的房间信息: {}", text);
+        ctx.text(text);
     }
 }
 
@@ -3422,194 +5913,13 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for GlobalUserSession
             Ok(ws::Message::Text(text)) => {
                 println!("收到全局WebSocket消息从用户 {}: {}", self.user_id, text);
                 
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) {
-                    if let Some(msg_type) = json["type"].as_str() {
-                        println!("解析消息类型: {}", msg_type);
-                        match msg_type {
-                            "join_room" => {
-                                if let (Some(room_id), Some(player_name)) = (
-                                    json.get("room_id").and_then(|v| v.as_str()),
-                                    json.get("player_name").and_then(|v| v.as_str()),
-                            ) {
-                                if player_name == &self.username {
-                                    let password = json.get("password").and_then(|v| v.as_str()).map(String::from);
-                                    self.handle_join_room(room_id, player_name, &password, ctx);
-                                } else {
-                                    println!("警告: 用户名不匹配，忽略加入房间请求。");
-                                }
-                            }
-                            }
-                            "leave_room" => {
-                                if let Some(room_id) = json["room_id"].as_str() {
-                                    self.addr.do_send(UserMessage::LeaveRoom {
-                                        room_id: room_id.to_string(),
-                                        player_id: self.user_id.clone(),
-                                    });
-                                }
-                            }
-                            "chat" | "chat_message" => {
-                                if let (Some(room_id), Some(content)) = (
-                                    json["room_id"].as_str(),
-                                    json["message"].as_str().or(json["content"].as_str())
-                                ) {
-                                    println!("GlobalUserSession 发送聊天消息: 房间={}, 用户={}, 内容={}", room_id, self.user_id, content);
-                                    self.addr.do_send(UserMessage::Chat {
-                                        room_id: room_id.to_string(),
-                                        sender_id: self.user_id.clone(),
-                                        username: self.username.clone(),
-                                        content: content.to_string(),
-                                    });
-                                }
-                            }
-                            "get_room_info" => {
-                                if let Some(room_id) = json["room_id"].as_str() {
-                                    let game_server_addr = self.addr.clone();
-                                    let ctx_addr = ctx.address();
-                                    let room_id = room_id.to_string();
-                                    
-                                    let fut = async move {
-                                        match game_server_addr.send(GetRoomInfo {
-                                            room_id: room_id.clone(),
-                                        }).await {
-                                            Ok(room_info) => {
-                                                ctx_addr.do_send(room_info);
-                                            }
-                                            Err(_) => {
-                                                println!("获取房间信息失败: {}", room_id);
-                                            }
-                                        }
-                                    };
-                                    
-                                    Arbiter::current().spawn(fut);
-                                }
-                            }
-                            "force_start" => {
-                                if let Some(room_id) = json["room_id"].as_str() {
-                                    self.addr.do_send(UserMessage::ForceStart {
-                                        room_id: room_id.to_string(),
-                                        player_id: self.user_id.clone(),
-                                    });
-                                }
-                            }
-                            "de_force_start" => {
-                                if let Some(room_id) = json["room_id"].as_str() {
-                                    self.addr.do_send(UserMessage::DeForceStart {
-                                        room_id: room_id.to_string(),
-                                        player_id: self.user_id.clone(),
-                                    });
-                                }
-                            }
-                            "should_start" => {
-                                if let Some(room_id) = json["room_id"].as_str() {
-                                    println!("收到should_start请求，房间: {}", room_id);
-                                    self.addr.do_send(UserMessage::ShouldStart {
-                                        room_id: room_id.to_string(),
-                                    });
-                                }
-                            }
-                            "game_action" => {
-                                if let (Some(room_id), Some(action)) = (
-                                    json["room_id"].as_str(),
-                                    json["action"].as_str()
-                                ) {
-                                    self.addr.do_send(UserMessage::GameAction {
-                                        room_id: room_id.to_string(),
-                                        player_id: self.user_id.clone(),
-                                        action: action.to_string(),
-                                    });
-                                }
-                            }
-                            "game_move" => {
-                                println!("解析game_move消息: {}", text);
-                                if let (
-                                    Some(room_id),
-                                    Some(from_x),
-                                    Some(from_y),
-                                    Some(to_x),
-                                    Some(to_y),
-                                    Some(move_id)
-                                ) = (
-                                    json["room_id"].as_str(),
-                                    json["from_x"].as_u64(),
-                                    json["from_y"].as_u64(),
-                                    json["to_x"].as_u64(),
-                                    json["to_y"].as_u64(),
-                                    json["move_id"].as_u64(), // 解析 move_id 但暂不使用
-                                ) {
-                                    println!("向GameServer发送GameMove: room_id={}, from=({},{}), to=({},{}), move_id={}", 
-                                             room_id, from_x, from_y, to_x, to_y,move_id);
-                                    self.addr.do_send(UserMessage::GameMove {
-                                        room_id: room_id.to_string(),
-                                        player_id: self.user_id.clone(),
-                                        from_x: from_x as usize,
-                                        from_y: from_y as usize,
-                                        to_x: to_x as usize,
-                                        to_y: to_y as usize,
-                                        move_id: move_id as usize,
-                                        is_half_move: json["is_half_move"].as_bool().unwrap_or(false),
-                                    });
-                                } else {
-                                    println!("game_move消息解析失败: room_id={:?}, from_x={:?}, from_y={:?}, to_x={:?}, to_y={:?}",
-                                             json["room_id"].as_str(),
-                                             json["from_x"].as_u64(),
-                                             json["from_y"].as_u64(),
-                                             json["to_x"].as_u64(),
-                                             json["to_y"].as_u64());
-                                }
-                            }
-                            "set_admin" => {
-                                if let (Some(room_id), Some(target_player_name)) = (
-                                    json["room_id"].as_str(),
-                                    json["target_player_name"].as_str()
-                                ) {
-                                    self.addr.do_send(UserMessage::SetAdmin {
-                                        room_id: room_id.to_string(),
-                                        host_id: self.user_id.clone(),
-                                        target_player_name: target_player_name.to_string(),
-                                    });
-                                }
-                            }
-                            "remove_admin" => {
-                                if let Some(room_id) = json["room_id"].as_str() {
-                                    self.addr.do_send(UserMessage::RemoveAdmin {
-                                        room_id: room_id.to_string(),
-                                        host_id: self.user_id.clone(),
-                                    });
-                                }
-                            }
-                            "kick_player" => {
-                                if let (Some(room_id), Some(target_player_name)) = (
-                                    json["room_id"].as_str(),
-                                    json["target_player_name"].as_str()
-                                ) {
-                                    self.addr.do_send(UserMessage::KickPlayer {
-                                        room_id: room_id.to_string(),
-                                        kicker_id: self.user_id.clone(),
-                                        target_player_name: target_player_name.to_string(),
-                                    });
-                                }
-                            }
-                            "change_group" => {
-                                if let (Some(room_id), Some(target_group_id)) = (
-                                    json["room_id"].as_str(),
-                                    json["target_group_id"].as_u64()
-                                ) {
-                                    self.addr.do_send(UserMessage::ChangeGroup {
-                                        room_id: room_id.to_string(),
-                                        player_id: self.user_id.clone(),
-                                        target_group_id: target_group_id as usize,
-                                    });
-                                }
-                            }
-                            _ => {
-                                println!("未知的消息类型: {}", msg_type);
-                            }
-                        }
-                    } else {
-                        println!("消息缺少type字段: {}", text);
+                match ClientMessage::parse(&text) {
+                    Ok(message) => self.handle_client_message(message, ctx),
+                    Err(error) => {
+                        // 返回结构化的解析错误，方便客户端定位问题
+                        println!("无法解析用户 {} 的消息: {}", self.user_id, error.message);
+                        Self::send(ctx, error.into());
                     }
-                } else {
-                    println!("JSON解析失败: {}", text);
                 }
             }
             Ok(ws::Message::Close(reason)) => {
@@ -3625,38 +5935,30 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for GlobalUserSession
 pub async fn global_websocket_handler(
     req: HttpRequest,
     stream: web::Payload,
+    pool: web::Data<DbPool>,
     game_server: web::Data<Addr<GameServer>>,
+    matchmaker: web::Data<Addr<Matchmaker>>,
 ) -> Result<HttpResponse, actix_web::Error> {
     println!("新的全局WebSocket连接建立");
     
-    // 从查询参数中获取用户信息
-    let query_string = req.query_string();
-    let query = web::Query::<HashMap<String, String>>::from_query(query_string)
-        .unwrap_or_else(|_| web::Query(HashMap::new()));
-    
-    let user_id = match query.get("user_id") {
-        Some(id) if !id.is_empty() => id.clone(),
-        _ => {
-            println!("缺少必要的user_id参数");
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "Missing user_id parameter"
-            })));
-        }
-    };
-    
-    let username = match query.get("username") {
-        Some(name) if !name.is_empty() => name.clone(),
-        _ => {
-            println!("缺少必要的username参数");
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "Missing username parameter"
-            })));
+    // 通过查询参数中的会话token验证身份，用户ID和用户名以数据库为准
+    let user = match crate::services::auth::authenticate(&pool, &req).await {
+        Ok(user) => user,
+        Err(response) => {
+            println!("WebSocket连接认证失败");
+            return Ok(response);
         }
     };
     
-    println!("创建全局WebSocket会话，用户ID: {}, 用户名: {}", user_id, username);
+    println!("创建全局WebSocket会话，用户ID: {}, 用户名: {}", user.id, user.username);
     
-    let global_session = GlobalUserSession::new(user_id, username, game_server.get_ref().clone());
+    let global_session = GlobalUserSession::new(
+        user.id,
+        user.username,
+        crate::services::rate_limit::client_ip(&req),
+        game_server.get_ref().clone(),
+        matchmaker.get_ref().clone(),
+    );
     ws::start(global_session, &req, stream)
 }
 
@@ -3716,6 +6018,7 @@ impl Handler<GameTurnMessage> for GameServer {
                         // 只剩一个活跃队伍，游戏结束
                         let winner_team = active_teams[0].clone();
                         room.status = "ended".to_string();
+                        self.presence_dirty.extend(room.players.iter().cloned());
                         
                         // 向所有玩家发送胜利消息
                         for player_id in &room.players {
@@ -3726,6 +6029,11 @@ impl Handler<GameTurnMessage> for GameServer {
                                 });
                             }
                         }
+                        
+                        // 保存对局记录
+                        self.record_game_result(&msg.room_id, &winner_team);
+                        self.sync_lobby();
+                        self.push_presence();
                         return; // 游戏结束，不再继续处理回合
                     }
                 }
@@ -3819,6 +6127,7 @@ impl Handler<GameTurnMessage> for GameServer {
                                     visible_tiles: formatted_tiles,
                                     successful_move_sends: vec![],
                                     player_powers,
+                                    pings: Self::room_visible_pings(room, self.map_pings.get(&msg.room_id), player_id),
                                 });
                             }
                         }
@@ -3875,6 +6184,12 @@ impl Handler<GameTurnMessage> for GameServer {
                 });
             }
         }
+
+        // 只在有玩家被淘汰后更新大厅中的存活人数，避免每个半回合都比较所有房间
+        if self.lobby_dirty {
+            self.sync_lobby();
+        }
+        self.push_presence();
     }
 }
 
@@ -3882,9 +6197,11 @@ impl Handler<EndGameMessage> for GameServer {
     type Result = ();
 
     fn handle(&mut self, msg: EndGameMessage, _: &mut Context<Self>) {
+        self.delete_game_snapshot(&msg.room_id);
         if let Some(room) = self.rooms.get_mut(&msg.room_id) {
             // 更新房间状态为等待
             room.status = "waiting".to_string();
+            self.presence_dirty.extend(room.players.iter().cloned());
             
             // 向房间内所有玩家发送游戏结束事件
             for player_id in &room.players {
@@ -3906,6 +6223,8 @@ impl Handler<EndGameMessage> for GameServer {
                 self.broadcast_room_info(&msg.room_id, room_info);
             }
         }
+        self.sync_lobby();
+        self.push_presence();
     }
 }
 
@@ -3937,6 +6256,9 @@ impl Handler<CleanupInactiveRooms> for GameServer {
                     room.players.retain(|id| id != player_id);
                     room.force_start_players.retain(|id| id != player_id);
                     room.player_count = room.players.len();
+                    if Self::eliminate_absent_team(room, player_id) {
+                        self.lobby_dirty = true;
+                    }
                     // 不要在这里删除用户名，因为玩家可能在其他房间中
                     println!("清理已断开连接的玩家: {} 从房间: {}", player_id, room_id);
                 }
@@ -3957,6 +6279,11 @@ impl Handler<CleanupInactiveRooms> for GameServer {
                 continue;
             }
             
+            // 常驻房间不会被自动清理
+            if room.is_persistent {
+                continue;
+            }
+            
             // 如果房间为空且超过1小时没有活动，标记为删除
             if room.player_count == 0 && (current_time - room.last_activity) > one_hour {
                 rooms_to_remove.push(room_id.clone());
@@ -3966,8 +6293,9 @@ impl Handler<CleanupInactiveRooms> for GameServer {
         // 删除过期房间
         for room_id in rooms_to_remove {
             println!("删除空闲房间: {}", room_id);
-            self.rooms.remove(&room_id);
+            self.remove_room(&room_id);
         }
+        self.sync_lobby();
         
         // 清理过期的踢出记录
         let mut rooms_to_clean = Vec::new();
@@ -3994,15 +6322,276 @@ impl Handler<CleanupInactiveRooms> for GameServer {
         for room_id in rooms_to_clean {
             self.kicked_players.remove(&room_id);
         }
+        
+        // 清理过期的禁言记录和已删除房间的禁言记录
+        let rooms = &self.rooms;
+        self.muted_players.retain(|room_id, room_mutes| {
+            room_mutes.retain(|_, until| *until > current_time);
+            !room_mutes.is_empty() && rooms.contains_key(room_id)
+        });
+
+        // 清理冷却已结束的输错密码记录和已删除房间的记录
+        self.failed_joins.retain(|room_id, room_failures| {
+            room_failures.retain(|_, (_, last_failure)| current_time.saturating_sub(*last_failure) < FAILED_JOIN_COOLDOWN_SECS);
+            !room_failures.is_empty() && rooms.contains_key(room_id)
+        });
+
+        // 清理过期的邀请和已删除房间的邀请
+        self.invite_tokens.retain(|_, invite| {
+            invite.expires_at > current_time && rooms.contains_key(&invite.room_id)
+        });
+        
+        // 回收已离线或长时间未发言用户的聊天令牌桶
+        let player_sessions = &self.player_sessions;
+        self.chat_buckets.retain(|player_id, bucket| {
+            player_sessions.contains_key(player_id) && !bucket.is_idle()
+        });
+    }
+}
+
+impl Handler<GetChatHistory> for GameServer {
+    type Result = Result<Vec<ChatMessage>, String>;
+
+    fn handle(&mut self, msg: GetChatHistory, _: &mut Context<Self>) -> Self::Result {
+        let room = self.rooms.get(&msg.room_id).ok_or_else(|| "房间不存在".to_string())?;
+        if !room.players.contains(&msg.user_id) {
+            return Err("只有房间内的玩家可以查看聊天记录".to_string());
+        }
+
+        let messages: Vec<ChatMessage> = match self.chat_history.get(&msg.room_id) {
+            Some(history) => history.iter()
+                .filter(|message| msg.before.map_or(true, |before| message.id < before))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        let skip = messages.len().saturating_sub(msg.limit);
+        Ok(messages.into_iter().skip(skip).collect())
+    }
+}
+
+impl Handler<UpdateUsername> for GameServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: UpdateUsername, _: &mut Context<Self>) {
+        let old_username = self.user_name_table.insert(msg.user_id.clone(), msg.username.clone());
+        
+        // 同步房主和管理员名称，记录需要刷新的房间
+        let mut affected_rooms = Vec::new();
+        let mut hosted_rooms = Vec::new();
+        for (room_id, room) in self.rooms.iter_mut() {
+            let mut changed = room.players.contains(&msg.user_id);
+            if room.host_player_id == msg.user_id {
+                room.host_player_name = msg.username.clone();
+                hosted_rooms.push(room_id.clone());
+                changed = true;
+            }
+            if room.admin_player_id.as_deref() == Some(msg.user_id.as_str()) {
+                room.admin_player_name = Some(msg.username.clone());
+                changed = true;
+            }
+            if changed {
+                affected_rooms.push(room_id.clone());
+            }
+        }
+        
+        // 通知该用户的会话更新用户名
+        if let Some(recipient) = self.player_sessions.get(&msg.user_id) {
+            let _ = recipient.do_send(UserMessage::SetUserInfo {
+                user_id: msg.user_id.clone(),
+                username: msg.username.clone(),
+            });
+        }
+        
+        for room_id in &hosted_rooms {
+            self.persist_room(room_id);
+        }
+        
+        for room_id in affected_rooms {
+            // 全局房间只刷新房间信息，不发送改名通知
+            if room_id != "global" {
+                if let (Some(room), Some(old_username)) = (self.rooms.get(&room_id), &old_username) {
+                    for p_id in &room.players {
+                        if let Some(recipient) = self.player_sessions.get(p_id) {
+                            let _ = recipient.do_send(UserMessage::Chat {
+                                room_id: room_id.clone(),
+                                sender_id: "system".to_string(),
+                                username: "系统".to_string(),
+                                content: format!("玩家 {} 改名为 {}", old_username, msg.username),
+                            });
+                        }
+                    }
+                }
+            }
+            
+            if let Some(room_info) = self.get_room_info(&room_id) {
+                self.broadcast_room_info(&room_id, room_info);
+            }
+        }
+        self.sync_lobby();
+    }
+}
+
+impl Handler<SetFriends> for GameServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetFriends, _: &mut Context<Self>) {
+        if self.player_sessions.contains_key(&msg.user_id) {
+            self.friends.insert(msg.user_id.clone(), msg.friend_ids.into_iter().collect());
+            self.presence_dirty.insert(msg.user_id);
+            self.push_presence();
+        }
+    }
+}
+
+impl Handler<FriendshipChanged> for GameServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: FriendshipChanged, _: &mut Context<Self>) {
+        match msg.event.as_str() {
+            "accepted" => {
+                if let Some(friend_ids) = self.friends.get_mut(&msg.user_id) {
+                    friend_ids.insert(msg.friend_id.clone());
+                }
+                if let Some(friend_ids) = self.friends.get_mut(&msg.friend_id) {
+                    friend_ids.insert(msg.user_id.clone());
+                }
+            }
+            "removed" => {
+                if let Some(friend_ids) = self.friends.get_mut(&msg.user_id) {
+                    friend_ids.remove(&msg.friend_id);
+                }
+                if let Some(friend_ids) = self.friends.get_mut(&msg.friend_id) {
+                    friend_ids.remove(&msg.user_id);
+                }
+            }
+            _ => {}
+        }
+
+        // 通知对方；好友请求被接受时对方同时获得操作者当前的在线状态
+        let presence = (msg.event == "accepted").then(|| self.presence_of(&msg.user_id));
+        if let Some(recipient) = self.player_sessions.get(&msg.friend_id) {
+            let _ = recipient.do_send(UserMessage::FriendUpdate {
+                user_id: msg.user_id.clone(),
+                username: msg.username,
+                event: msg.event,
+                presence,
+            });
+        }
+    }
+}
+
+impl Handler<GetPresence> for GameServer {
+    type Result = MessageResult<GetPresence>;
+
+    fn handle(&mut self, msg: GetPresence, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(msg.user_ids.into_iter()
+            .map(|user_id| {
+                let presence = self.presence_of(&user_id);
+                (user_id, presence)
+            })
+            .collect())
+    }
+}
+
+impl Handler<StartRankedMatch> for GameServer {
+    type Result = Result<(), Vec<String>>;
+
+    fn handle(&mut self, msg: StartRankedMatch, ctx: &mut Context<Self>) -> Self::Result {
+        let room_id = msg.room_id;
+
+        // 配对后玩家可能已经离线或进入了其他对局，由 Matchmaker 将其余玩家重新放回队列
+        let unavailable: Vec<String> = msg.players.iter()
+            .filter(|(user_id, _, _)| matches!(self.presence_of(user_id).status.as_str(), "offline" | "playing"))
+            .map(|(user_id, _, _)| user_id.clone())
+            .collect();
+        if !unavailable.is_empty() {
+            println!("排位房间 {} 取消: 玩家 {:?} 无法开始对局", room_id, unavailable);
+            self.remove_room(&room_id);
+            return Err(unavailable);
+        }
+
+        println!("排位房间 {} 创建完成，自动开始对局", room_id);
+        self.ranked_rooms.insert(room_id.clone());
+
+        // 双方依次加入房间并放入组0和组1
+        for (group_id, (user_id, username, _)) in msg.players.iter().enumerate() {
+            self.handle(UserMessage::JoinRoomWithName {
+                room_id: room_id.clone(),
+                player_id: user_id.clone(),
+                player_name: username.clone(),
+                password: None,
+                invite_token: None,
+            }, ctx);
+            if let Err(e) = self.change_player_group(&room_id, user_id, group_id) {
+                println!("排位房间 {} 分组失败: {}", room_id, e);
+            }
+        }
+
+        // 双方都发起强制开始即进入对局
+        for (user_id, _, _) in &msg.players {
+            self.handle(UserMessage::ForceStart {
+                room_id: room_id.clone(),
+                player_id: user_id.clone(),
+            }, ctx);
+        }
+
+        for (user_id, _, rating) in &msg.players {
+            if let Some((_, opponent_name, opponent_rating)) = msg.players.iter().find(|(id, _, _)| id != user_id) {
+                if let Some(recipient) = self.player_sessions.get(user_id) {
+                    let _ = recipient.do_send(UserMessage::MatchFound {
+                        room_id: room_id.clone(),
+                        opponent_name: opponent_name.clone(),
+                        rating: *rating,
+                        opponent_rating: *opponent_rating,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Handler<SnapshotGames> for GameServer {
+    type Result = ();
+
+    fn handle(&mut self, _msg: SnapshotGames, _: &mut Context<Self>) {
+        self.save_game_snapshots();
+    }
+}
+
+impl Handler<PurgeStaleGuests> for GameServer {
+    type Result = ();
+
+    fn handle(&mut self, _msg: PurgeStaleGuests, _: &mut Context<Self>) {
+        // 在线的游客（包括断线保留期内的）不清理
+        let online: Vec<String> = self.player_sessions.keys().cloned().collect();
+        self.spawn_db_task(move |conn| {
+            match crate::services::user::purge_stale_guests(conn, &online) {
+                Ok(0) => {}
+                Ok(count) => println!("已清理 {} 个未使用的游客账户", count),
+                Err(e) => println!("清理游客账户失败: {}", e),
+            }
+        });
     }
 }
 
 impl Handler<CleanupDisconnectedPlayers> for GameServer {
     type Result = ();
 
-    fn handle(&mut self, _msg: CleanupDisconnectedPlayers, _: &mut Context<Self>) {
+    fn handle(&mut self, _msg: CleanupDisconnectedPlayers, ctx: &mut Context<Self>) {
         let current_time = Self::current_timestamp();
         let session_timeout = 30; // 30秒会话保留时间
+        let resume_timeout = 120; // 恢复的对局最多等待玩家重连2分钟
+        
+        // 等待超时的暂停对局：有玩家返回则继续游戏
+        let timed_out_rooms: Vec<String> = self.paused_rooms.iter()
+            .filter(|(_, paused_at)| current_time - **paused_at > resume_timeout)
+            .map(|(room_id, _)| room_id.clone())
+            .collect();
+        for room_id in timed_out_rooms {
+            self.try_resume_paused_game(&room_id, true, ctx);
+        }
         
         let mut players_to_remove = Vec::new();
         
@@ -4028,6 +6617,9 @@ impl Handler<CleanupDisconnectedPlayers> for GameServer {
                     room.force_start_players.retain(|id| id != &player_id);
                     room.player_count -= 1;
                     rooms_to_update.push(room_id.clone());
+                    if Self::eliminate_absent_team(room, &player_id) {
+                        self.lobby_dirty = true;
+                    }
                     
                     // 通知房间内其他玩家
                     for other_player_id in &room.players {
@@ -4087,6 +6679,7 @@ impl Handler<CleanupDisconnectedPlayers> for GameServer {
                         // 更新房间状态
                         if let Some(room_mut) = self.rooms.get_mut(&room_id) {
                             room_mut.status = "ended".to_string();
+                            self.presence_dirty.extend(room_mut.players.iter().cloned());
                         }
                         
                         // 向所有玩家发送胜利消息
@@ -4098,6 +6691,9 @@ impl Handler<CleanupDisconnectedPlayers> for GameServer {
                                 });
                             }
                         }
+                        
+                        // 保存对局记录
+                        self.record_game_result(&room_id, &winner_team);
                     }
                 }
                 
@@ -4118,5 +6714,7 @@ impl Handler<CleanupDisconnectedPlayers> for GameServer {
                 self.user_name_table.remove(&player_id);
             }
         }
+        self.sync_lobby();
+        self.push_presence();
     }
 }
@@ -10,6 +10,7 @@ import { AuthProvider, useAuth } from "./contexts/AuthContext";
 import { AuthGuard } from "./components/AuthGuard";
 import { useAuthenticatedWebSocket } from "./hooks/useAuthenticatedWebSocket";
 import GlobalChatWrapper from "./components/GlobalChatWrapper";
+import RoomInvitePrompt from "./components/RoomInvitePrompt";
 import "./App.css";
 
 const AppContent = () => {
@@ -126,6 +127,9 @@ const AppContent = () => {
       {/* 全局提示框 */}
       <Toaster />
 
+      {/* 房间邀请提示 */}
+      {isAuthenticated && <RoomInvitePrompt />}
+
       {/* 智能聊天组件 */}
       <GlobalChatWrapper />
     </Box>
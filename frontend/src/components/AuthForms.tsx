@@ -1,7 +1,10 @@
-import React from "react";
-import { VStack, Box, SegmentGroup } from "@chakra-ui/react";
+import React, { useState } from "react";
+import { VStack, Box, SegmentGroup, Button } from "@chakra-ui/react";
+import { LuGhost } from "react-icons/lu";
+import { toaster } from "@/components/ui/toaster";
 import { RegisterForm } from "./RegisterForm";
 import { LoginForm } from "./LoginForm";
+import { buildApiUrl, API_ENDPOINTS } from "../config/api";
 
 interface AuthFormsProps {
   onRegisterSuccess?: (user: any) => void;
@@ -13,6 +16,37 @@ export const AuthForms: React.FC<AuthFormsProps> = ({
   onLoginSuccess,
 }) => {
   const [value, setValue] = React.useState<string | null>("注册");
+  const [isGuestLoading, setIsGuestLoading] = useState(false);
+
+  // 以游客身份开始游戏，服务器生成用户名并返回会话token
+  const handleGuestPlay = async () => {
+    setIsGuestLoading(true);
+    try {
+      const response = await fetch(buildApiUrl(API_ENDPOINTS.USER_GUEST), {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+      });
+      const data = await response.json();
+      if (response.ok) {
+        onLoginSuccess?.(data);
+      } else {
+        toaster.create({
+          title: "进入失败",
+          description: data.message || "创建游客账户失败",
+          type: "error",
+        });
+      }
+    } catch (error) {
+      console.error("创建游客账户错误:", error);
+      toaster.create({
+        title: "系统错误",
+        description: "网络错误，请稍后再试",
+        type: "error",
+      });
+    } finally {
+      setIsGuestLoading(false);
+    }
+  };
 
   return (
     <VStack gap={6} w="100%" maxW="400px">
@@ -32,6 +66,17 @@ export const AuthForms: React.FC<AuthFormsProps> = ({
           <LoginForm onLoginSuccess={onLoginSuccess} />
         )}
       </Box>
+
+      <Button
+        variant="outline"
+        colorPalette="gray"
+        w="100%"
+        loading={isGuestLoading}
+        onClick={handleGuestPlay}
+      >
+        <LuGhost />
+        游客试玩
+      </Button>
     </VStack>
   );
 };
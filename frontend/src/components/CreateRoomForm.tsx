@@ -16,8 +16,7 @@ import {
 } from "@chakra-ui/react";
 import { PasswordInput } from "@/components/ui/password-input";
 import { LuPlus, LuUsers, LuPalette, LuSettings2 } from "react-icons/lu";
-import { useAuth } from "../contexts/AuthContext";
-import { buildApiUrl, API_ENDPOINTS } from "../config/api";
+import { buildApiUrl, API_ENDPOINTS, authHeaders } from "../config/api";
 
 interface CreateRoomFormProps {
   onRoomCreated: (roomId: string) => void;
@@ -31,8 +30,6 @@ interface CreateRoomRequest {
   name: string;
   max_players: number;
   room_color: string;
-  host_id: string;
-  host_name: string;
   password?: string;
   is_public: boolean;
 }
@@ -54,7 +51,6 @@ export const CreateRoomForm: React.FC<CreateRoomFormProps> = ({
   colorSelected,
   onColorChange,
 }) => {
-  const { user } = useAuth();
   const [customRoomId, setCustomRoomId] = useState("");
   const [roomName, setRoomName] = useState("");
   const [maxPlayers, setMaxPlayers] = useState(8);
@@ -165,8 +161,6 @@ export const CreateRoomForm: React.FC<CreateRoomFormProps> = ({
 
     try {
       const request: CreateRoomRequest = {
-        host_id: user?.user_id || "abc",
-        host_name: user?.username || "Unknown",
         name: roomName.trim(),
         max_players: maxPlayers,
         room_color: roomColor,
@@ -178,7 +172,7 @@ export const CreateRoomForm: React.FC<CreateRoomFormProps> = ({
 
       const response = await fetch(buildApiUrl(API_ENDPOINTS.CREATE_ROOM), {
         method: "POST",
-        headers: { "Content-Type": "application/json" },
+        headers: authHeaders(),
         body: JSON.stringify(request),
       });
 
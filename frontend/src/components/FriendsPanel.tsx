@@ -0,0 +1,214 @@
+import React, { useCallback, useEffect, useState } from "react";
+import { VStack, HStack, Input, Button, Text, Badge, Box } from "@chakra-ui/react";
+import { toaster } from "@/components/ui/toaster";
+import { buildApiUrl, API_ENDPOINTS, authHeaders } from "../config/api";
+import { wsManager, type ChatMessage } from "../hooks/wsManager";
+
+interface Presence {
+  status: "offline" | "lobby" | "waiting" | "playing" | "spectating";
+  room_id?: string | null;
+}
+
+interface FriendInfo {
+  user_id: string;
+  username: string;
+  presence?: Presence | null;
+}
+
+interface FriendList {
+  friends: FriendInfo[];
+  incoming: FriendInfo[];
+  outgoing: FriendInfo[];
+}
+
+// 在线状态显示文字和颜色
+const describePresence = (presence?: Presence | null) => {
+  switch (presence?.status) {
+    case "lobby":
+      return { label: "在大厅", color: "green" };
+    case "waiting":
+      return { label: `在房间 ${presence.room_id} 等待`, color: "blue" };
+    case "playing":
+      return { label: `在房间 ${presence.room_id} 对局中`, color: "purple" };
+    case "spectating":
+      return { label: `在房间 ${presence.room_id} 观战`, color: "orange" };
+    default:
+      return { label: "离线", color: "gray" };
+  }
+};
+
+// 好友列表：添加/接受/删除好友，并通过全局 WebSocket 实时更新在线状态
+export const FriendsPanel: React.FC = () => {
+  const [list, setList] = useState<FriendList>({
+    friends: [],
+    incoming: [],
+    outgoing: [],
+  });
+  const [username, setUsername] = useState("");
+
+  const loadFriends = useCallback(async () => {
+    try {
+      const response = await fetch(buildApiUrl(API_ENDPOINTS.FRIENDS), {
+        headers: authHeaders(),
+      });
+      if (response.ok) {
+        setList(await response.json());
+      }
+    } catch (error) {
+      console.error("获取好友列表失败:", error);
+    }
+  }, []);
+
+  useEffect(() => {
+    loadFriends();
+
+    const unsubscribe = wsManager.subscribeToMessages((message: ChatMessage) => {
+      if (message.type !== "friend_update") return;
+
+      if (message.event === "presence") {
+        // 只更新对应好友的状态，不重新请求列表
+        setList((prev) => ({
+          ...prev,
+          friends: prev.friends.map((friend) =>
+            friend.user_id === message.user_id
+              ? { ...friend, presence: message.presence }
+              : friend
+          ),
+        }));
+      } else {
+        if (message.event === "request") {
+          toaster.create({
+            title: "好友请求",
+            description: `${message.username} 想添加您为好友`,
+            type: "info",
+          });
+        }
+        loadFriends();
+      }
+    });
+    return unsubscribe;
+  }, [loadFriends]);
+
+  // 发送请求、接受、删除共用的请求处理
+  const request = async (url: string, method: string, body?: object) => {
+    try {
+      const response = await fetch(url, {
+        method,
+        headers: authHeaders(),
+        body: body ? JSON.stringify(body) : undefined,
+      });
+      if (!response.ok) {
+        const result = await response.json();
+        toaster.create({
+          title: "操作失败",
+          description: result.message || "发生未知错误",
+          type: "error",
+        });
+        return false;
+      }
+      await loadFriends();
+      return true;
+    } catch (error) {
+      console.error("好友操作失败:", error);
+      return false;
+    }
+  };
+
+  const sendRequest = async () => {
+    if (!username.trim()) return;
+    const ok = await request(buildApiUrl(API_ENDPOINTS.FRIEND_REQUEST), "POST", {
+      username: username.trim(),
+    });
+    if (ok) {
+      setUsername("");
+    }
+  };
+
+  const friendUrl = (userId: string) =>
+    buildApiUrl(`${API_ENDPOINTS.FRIENDS}/${encodeURIComponent(userId)}`);
+
+  return (
+    <Box borderWidth="1px" borderRadius="md" p={4} minW="320px">
+      <VStack align="stretch" gap={3}>
+        <Text fontWeight="bold">好友</Text>
+        <HStack>
+          <Input
+            size="sm"
+            placeholder="输入用户名添加好友"
+            value={username}
+            onChange={(e) => setUsername(e.target.value)}
+            onKeyDown={(e) => e.key === "Enter" && sendRequest()}
+          />
+          <Button size="sm" onClick={sendRequest}>
+            添加
+          </Button>
+        </HStack>
+
+        {list.incoming.map((friend) => (
+          <HStack key={friend.user_id} justify="space-between">
+            <Text fontSize="sm">{friend.username} 请求添加您为好友</Text>
+            <HStack>
+              <Button
+                size="xs"
+                colorPalette="green"
+                onClick={() => request(`${friendUrl(friend.user_id)}/accept`, "POST")}
+              >
+                接受
+              </Button>
+              <Button
+                size="xs"
+                variant="outline"
+                onClick={() => request(friendUrl(friend.user_id), "DELETE")}
+              >
+                拒绝
+              </Button>
+            </HStack>
+          </HStack>
+        ))}
+
+        {list.friends.map((friend) => {
+          const presence = describePresence(friend.presence);
+          return (
+            <HStack key={friend.user_id} justify="space-between">
+              <HStack>
+                <Text fontSize="sm">{friend.username}</Text>
+                <Badge size="sm" colorPalette={presence.color}>
+                  {presence.label}
+                </Badge>
+              </HStack>
+              <Button
+                size="xs"
+                variant="outline"
+                colorPalette="red"
+                onClick={() => request(friendUrl(friend.user_id), "DELETE")}
+              >
+                删除
+              </Button>
+            </HStack>
+          );
+        })}
+
+        {list.outgoing.map((friend) => (
+          <HStack key={friend.user_id} justify="space-between">
+            <Text fontSize="sm" color="gray.500">
+              等待 {friend.username} 接受
+            </Text>
+            <Button
+              size="xs"
+              variant="outline"
+              onClick={() => request(friendUrl(friend.user_id), "DELETE")}
+            >
+              撤回
+            </Button>
+          </HStack>
+        ))}
+
+        {list.friends.length === 0 && list.incoming.length === 0 && (
+          <Text fontSize="sm" color="gray.500">
+            暂无好友
+          </Text>
+        )}
+      </VStack>
+    </Box>
+  );
+};
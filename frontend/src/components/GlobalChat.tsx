@@ -37,8 +37,6 @@ const GlobalChat: React.FC = () => {
     wsManager.send({
       type: "chat",
       room_id: "global",
-      sender_id: user.user_id,
-      username: user.username,
       content: inputMessage.trim(),
     });
 
@@ -2,10 +2,12 @@ import React, { useState } from "react";
 import { VStack, Input, Field, Button, InputGroup } from "@chakra-ui/react";
 import { useForm } from "react-hook-form";
 import { toaster } from "@/components/ui/toaster";
+import { PasswordInput } from "@/components/ui/password-input";
 import { LuText, LuTerminal } from "react-icons/lu";
 import { buildApiUrl, API_ENDPOINTS } from "../config/api";
 interface LoginUserData {
   user_id: string;
+  password: string;
 }
 
 interface LoginFormProps {
@@ -51,16 +53,17 @@ export const LoginForm: React.FC<LoginFormProps> = ({
         return;
       }
 
-      // 调用登录 API - 直接使用字符串
-      const response = await fetch(
-        buildApiUrl(API_ENDPOINTS.USERS, { user_id: userId }),
-        {
-          method: "GET",
-          headers: {
-            "Content-Type": "application/json",
-          },
-        }
-      );
+      // 调用登录 API，成功后返回会话token
+      const response = await fetch(buildApiUrl(API_ENDPOINTS.USER_LOGIN), {
+        method: "POST",
+        headers: {
+          "Content-Type": "application/json",
+        },
+        body: JSON.stringify({
+          user_id: userId,
+          password: data.password,
+        }),
+      });
 
       if (response.ok) {
         const user = await response.json();
@@ -73,10 +76,15 @@ export const LoginForm: React.FC<LoginFormProps> = ({
         if (onSubmit) {
           onSubmit(data);
         }
-      } else if (response.status === 404) {
+      } else if (
+        response.status === 401 ||
+        response.status === 400 ||
+        response.status === 429
+      ) {
+        const errorData = await response.json().catch(() => null);
         toaster.create({
           title: "登录失败",
-          description: "用户不存在，请检查 User ID 或先进行注册",
+          description: errorData?.message || "User ID 或密码错误",
           type: "warning",
         });
       } else {
@@ -112,6 +120,13 @@ export const LoginForm: React.FC<LoginFormProps> = ({
           </InputGroup>
           <Field.HelperText>账号注册时设置的 User ID</Field.HelperText>
         </Field.Root>
+        <Field.Root required>
+          <Field.Label>
+            密码 <Field.RequiredIndicator />
+          </Field.Label>
+          <PasswordInput placeholder="请输入密码" {...register("password")} />
+          <Field.HelperText>旧账户首次登录时将设置为此密码</Field.HelperText>
+        </Field.Root>
         <Button
           type="submit"
           loading={isLoading}
@@ -9,16 +9,36 @@ import {
   CloseButton,
   SegmentGroup,
 } from "@chakra-ui/react";
-import { LuGamepad2, LuDoorOpen, LuMap } from "react-icons/lu";
+import { LuGamepad2, LuDoorOpen, LuMap, LuZap } from "react-icons/lu";
 import { RoomGrid } from "./RoomGrid";
 import { CreateRoomForm } from "./CreateRoomForm";
 import { useNavigate } from "react-router-dom";
+import { wsManager, type ChatMessage } from "../hooks/wsManager";
 export const MainButtons: React.FC = () => {
   const [value, setValue] = React.useState<string | null>("加入房间");
   const [isDialogOpen, setIsDialogOpen] = React.useState(false);
   const [selectedColor, setSelectedColor] = React.useState("#4F46E5"); // 添加颜色状态
+  const [isQuickPlaying, setIsQuickPlaying] = React.useState(false);
   const navigate = useNavigate();
 
+  // 快速开始：服务器返回人数最多的可加入房间（或新建的房间）后直接进入
+  React.useEffect(() => {
+    const unsubscribe = wsManager.subscribeToMessages((message: ChatMessage) => {
+      if (message.type === "quick_play" && message.room_id) {
+        setIsQuickPlaying(false);
+        navigate(`/rooms/${encodeURIComponent(String(message.room_id))}`);
+      } else if (message.type === "error") {
+        setIsQuickPlaying(false);
+      }
+    });
+    return unsubscribe;
+  }, [navigate]);
+
+  const handleQuickPlay = () => {
+    setIsQuickPlaying(true);
+    wsManager.send({ type: "quick_play" });
+  };
+
   const handleRoomCreated = (roomId: string) => {
     console.log(`房间创建成功，ID: ${roomId}`);
     setIsDialogOpen(false);
@@ -47,6 +67,17 @@ export const MainButtons: React.FC = () => {
       </Text>
 
       <VStack gap={4} w="100%">
+        <Button
+          size="lg"
+          colorPalette="orange"
+          w="100%"
+          minH="60px"
+          loading={isQuickPlaying}
+          onClick={handleQuickPlay}
+        >
+          <LuZap size={20} />
+          快速开始
+        </Button>
         <Button
           size="lg"
           colorPalette="purple"
@@ -75,12 +75,10 @@ const MultiChat: React.FC<MultiChatProps> = ({ roomId, gameState }) => {
   const sendMessage = () => {
     if (!currentMessage.trim() || !user) return;
 
-    const message = {
+    const message: ClientMessage = {
       type: "chat",
-      message: currentMessage,
-      username: user.username,
-      context: activeTab,
-      room_id: roomId,
+      content: currentMessage,
+      room_id: activeTab === "global" ? "global" : roomId || "global",
     };
 
     wsManager.send(message);
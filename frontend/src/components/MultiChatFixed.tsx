@@ -99,8 +99,6 @@ const MultiChat: React.FC<MultiChatProps> = ({ currentContext, roomId }) => {
     wsManager.send({
       type: "chat",
       room_id: targetRoomId,
-      sender_id: user.user_id,
-      username: user.username,
       content: messageContent,
     });
 
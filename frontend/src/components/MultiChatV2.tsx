@@ -123,6 +123,7 @@ const MultiChat = forwardRef<MultiChatRef, MultiChatProps>(
           (msg: ChatMessage) =>
             msg.room_id === "global" ||
             msg.type === "system" ||
+            msg.type === "whisper" ||
             (!msg.room_id && msg.type === "chat")
         );
 
@@ -152,13 +153,33 @@ const MultiChat = forwardRef<MultiChatRef, MultiChatProps>(
     const handleSendMessage = () => {
       if (!inputMessage.trim() || !user) return;
 
-      const messageData = {
+      // "/w 用户名 内容" 发送私信
+      const whisperMatch = inputMessage.trim().match(/^\/w\s+(\S+)\s+([\s\S]+)$/);
+      if (whisperMatch) {
+        wsManager.send({
+          type: "whisper",
+          target_player_name: whisperMatch[1],
+          content: whisperMatch[2],
+        });
+        setInputMessage("");
+        return;
+      }
+
+      // 房间中 "/t 内容" 发送到队伍频道，"/s 内容" 发送到观战频道
+      let content = inputMessage.trim();
+      let channel: "team" | "spectator" | undefined;
+      const channelMatch =
+        activeContext === "room" && content.match(/^\/([ts])\s+([\s\S]+)$/);
+      if (channelMatch) {
+        channel = channelMatch[1] === "t" ? "team" : "spectator";
+        content = channelMatch[2];
+      }
+
+      const messageData: ClientMessage = {
         type: "chat",
-        room_id: activeContext === "global" ? "global" : roomId,
-        sender_id: user.user_id,
-        username: user.username,
-        content: inputMessage.trim(),
-        isInGame: activeContext === "room" && isGameStarted(), // 标识是否在游戏中发送
+        room_id: activeContext === "global" ? "global" : roomId || "global",
+        content,
+        channel,
       };
 
       wsManager.send(messageData);
@@ -394,7 +415,13 @@ const MultiChat = forwardRef<MultiChatRef, MultiChatProps>(
                             }}
                           >
                             {(message as any).isInGame && "[游戏] "}
-                            {message.username || "匿名用户"}:
+                            {message.type === "whisper" && "[私信] "}
+                            {message.channel === "team" && "[队伍] "}
+                            {message.channel === "spectator" && "[观战] "}
+                            {message.username || "匿名用户"}
+                            {message.type === "whisper" &&
+                              ` → ${message.recipient_name}`}
+                            :
                           </span>
                           <span
                             style={{
@@ -505,6 +532,7 @@ const MultiChat = forwardRef<MultiChatRef, MultiChatProps>(
                 value={inputMessage}
                 onChange={(e) => setInputMessage(e.target.value)}
                 onKeyPress={handleKeyPress}
+                maxLength={200}
                 placeholder={`输入${currentConfig.label}消息并按 Enter 发送...`}
                 style={{
                   flex: 1,
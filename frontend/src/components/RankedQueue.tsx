@@ -0,0 +1,112 @@
+import React, { useEffect, useState } from "react";
+import { Box, VStack, HStack, Text, Button, Badge } from "@chakra-ui/react";
+import { useNavigate } from "react-router-dom";
+import { toaster } from "@/components/ui/toaster";
+import { wsManager, type ChatMessage } from "../hooks/wsManager";
+
+type QueueState = "idle" | "searching" | "matched";
+
+// 1v1 排位匹配：加入队列后等待服务器配对，配对成功自动进入对局
+export const RankedQueue: React.FC = () => {
+  const navigate = useNavigate();
+  const [state, setState] = useState<QueueState>("idle");
+  const [rating, setRating] = useState<number | null>(null);
+  const [waitedSecs, setWaitedSecs] = useState(0);
+
+  useEffect(() => {
+    const unsubscribe = wsManager.subscribeToMessages((message: ChatMessage) => {
+      if (message.type === "queue_status") {
+        if (message.rating != null) setRating(message.rating);
+        switch (message.status) {
+          case "searching":
+            setWaitedSecs(0);
+            setState("searching");
+            break;
+          case "matched":
+            setState("matched");
+            break;
+          case "requeued":
+            // 对手配对后离线或进入了其他对局，服务器已将自己放回队列
+            setWaitedSecs(0);
+            setState("searching");
+            toaster.create({
+              title: "对手无法开始对局",
+              description: "已重新为你匹配",
+              type: "info",
+            });
+            break;
+          case "guest":
+            setState("idle");
+            toaster.create({
+              title: "无法参加排位",
+              description: "游客不能参加排位赛，请先注册账户",
+              type: "warning",
+            });
+            break;
+          case "failed":
+            setState("idle");
+            toaster.create({
+              title: "匹配失败",
+              description: "请稍后重新匹配",
+              type: "error",
+            });
+            break;
+          default:
+            setState("idle");
+        }
+      } else if (message.type === "match_found" && message.room_id) {
+        setState("idle");
+        toaster.create({
+          title: "匹配成功",
+          description: `对手: ${message.opponent_name} (${message.opponent_rating})`,
+          type: "success",
+          duration: 3000,
+        });
+        navigate(`/rooms/${encodeURIComponent(String(message.room_id))}/game`);
+      } else if (message.type === "error" && state === "matched") {
+        // 配对后对手离线等原因导致匹配取消
+        setState("idle");
+      }
+    });
+    return unsubscribe;
+  }, [navigate, state]);
+
+  // 显示已等待的时间
+  useEffect(() => {
+    if (state !== "searching") return;
+    const timer = setInterval(() => setWaitedSecs((secs) => secs + 1), 1000);
+    return () => clearInterval(timer);
+  }, [state]);
+
+  const joinQueue = () => wsManager.send({ type: "join_queue" });
+  const leaveQueue = () => wsManager.send({ type: "leave_queue" });
+
+  return (
+    <Box borderWidth="1px" borderRadius="md" p={4} minW="320px">
+      <VStack align="stretch" gap={3}>
+        <HStack justify="space-between">
+          <Text fontWeight="bold">1v1 排位</Text>
+          {rating != null && <Badge colorPalette="purple">排位分 {rating}</Badge>}
+        </HStack>
+        {state === "searching" ? (
+          <HStack justify="space-between">
+            <Text fontSize="sm" color="gray.600">
+              正在寻找对手... {waitedSecs} 秒
+            </Text>
+            <Button size="sm" variant="outline" onClick={leaveQueue}>
+              取消匹配
+            </Button>
+          </HStack>
+        ) : state === "matched" ? (
+          <Text fontSize="sm" color="green.600">
+            已找到对手，正在创建房间...
+          </Text>
+        ) : (
+          <Button size="sm" colorPalette="purple" onClick={joinQueue}>
+            开始匹配
+          </Button>
+        )}
+      </VStack>
+    </Box>
+  );
+};
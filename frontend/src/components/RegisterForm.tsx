@@ -1,4 +1,4 @@
-import React, { useState } from "react";
+import React, { useEffect, useState } from "react";
 import {
   VStack,
   Input,
@@ -9,12 +9,14 @@ import {
 } from "@chakra-ui/react";
 import { useForm } from "react-hook-form";
 import { toaster } from "@/components/ui/toaster";
+import { PasswordInput } from "@/components/ui/password-input";
 import { LuText, LuCircleUser, LuTerminal } from "react-icons/lu";
-import { validateUsername } from "../utils/usernameValidator";
+import { validateUsername, loadUsernameRules } from "../utils/usernameValidator";
 import { buildApiUrl, API_ENDPOINTS } from "../config/api";
 interface RegisterUserData {
   user_id: string;
   username: string;
+  password: string;
 }
 
 interface RegisterFormProps {
@@ -28,6 +30,11 @@ export const RegisterForm: React.FC<RegisterFormProps> = ({
 }) => {
   const [isLoading, setIsLoading] = useState(false);
 
+  // 同步服务端的用户名校验规则
+  useEffect(() => {
+    loadUsernameRules();
+  }, []);
+
   const {
     register,
     handleSubmit,
@@ -78,6 +85,17 @@ export const RegisterForm: React.FC<RegisterFormProps> = ({
         return;
       }
 
+      // 密码验证
+      if (data.password.length < 6 || data.password.length > 64) {
+        toaster.create({
+          title: "验证错误",
+          description: "密码长度必须在6-64个字符之间",
+          type: "error",
+        });
+        setIsLoading(false);
+        return;
+      }
+
       // 直接注册，让后端处理重复检查
       const registerResponse = await fetch(buildApiUrl(API_ENDPOINTS.USERS), {
         method: "POST",
@@ -87,6 +105,7 @@ export const RegisterForm: React.FC<RegisterFormProps> = ({
         body: JSON.stringify({
           user_id: userId, // 改为字符串字段
           username: data.username.trim(),
+          password: data.password,
         }),
       });
 
@@ -188,6 +207,13 @@ export const RegisterForm: React.FC<RegisterFormProps> = ({
           </InputGroup>
           <Field.HelperText>游戏内显示的名称</Field.HelperText>
         </Field.Root>
+        <Field.Root required>
+          <Field.Label>
+            密码 <Field.RequiredIndicator />
+          </Field.Label>
+          <PasswordInput placeholder="6-64个字符" {...register("password")} />
+          <Field.HelperText>登录时需要输入，请妥善保管</Field.HelperText>
+        </Field.Root>
         <Button
           type="submit"
           loading={isLoading}
@@ -9,7 +9,7 @@ import {
   Button,
   ColorSwatch,
 } from "@chakra-ui/react";
-import { LuUsers, LuCrown, LuPlay, LuLock } from "react-icons/lu";
+import { LuUsers, LuCrown, LuPlay, LuLock, LuSwords } from "react-icons/lu";
 import { PasswordPrompt } from "./PasswordPrompt";
 
 export interface RoomInfo {
@@ -24,6 +24,8 @@ export interface RoomInfo {
   required_to_start: number;
   is_active: boolean;
   has_password: boolean; // 新增
+  game_turn?: number | null; // 对局进行中时的当前回合
+  alive_players?: number | null; // 对局进行中时尚未被淘汰的玩家数
 }
 
 interface RoomCardProps {
@@ -134,22 +136,34 @@ export const RoomCard: React.FC<RoomCardProps> = ({ room }) => {
                 </Text>
               </HStack>
 
-              <HStack justify="space-between">
-                <HStack gap={1} color="gray.600">
-                  <LuPlay size={14} />
-                  <Text fontSize="sm">开始所需</Text>
-                </HStack>
-                <HStack gap={1}>
+              {room.game_turn != null ? (
+                <HStack justify="space-between">
+                  <HStack gap={1} color="gray.600">
+                    <LuSwords size={14} />
+                    <Text fontSize="sm">对局进度</Text>
+                  </HStack>
                   <Text fontSize="sm" fontWeight="medium">
-                    {room.required_to_start}
+                    第 {room.game_turn} 回合 · 存活 {room.alive_players ?? 0} 人
                   </Text>
-                  {canStart && (
-                    <Badge size="xs" colorPalette="green" variant="solid">
-                      可开始
-                    </Badge>
-                  )}
                 </HStack>
-              </HStack>
+              ) : (
+                <HStack justify="space-between">
+                  <HStack gap={1} color="gray.600">
+                    <LuPlay size={14} />
+                    <Text fontSize="sm">开始所需</Text>
+                  </HStack>
+                  <HStack gap={1}>
+                    <Text fontSize="sm" fontWeight="medium">
+                      {room.required_to_start}
+                    </Text>
+                    {canStart && (
+                      <Badge size="xs" colorPalette="green" variant="solid">
+                        可开始
+                      </Badge>
+                    )}
+                  </HStack>
+                </HStack>
+              )}
             </VStack>
 
             {/* 加入按钮 */}
@@ -1,4 +1,4 @@
-import React, { useState, useEffect } from "react";
+import React, { useState, useEffect, useRef } from "react";
 import {
   SimpleGrid,
   VStack,
@@ -7,13 +7,56 @@ import {
   Button,
   HStack,
   Alert,
+  Input,
+  NativeSelect,
+  Switch,
 } from "@chakra-ui/react";
 import { LuRefreshCw, LuInfo } from "react-icons/lu";
 import { RoomCard, RoomInfo } from "./RoomCard";
 import { buildApiUrl, API_ENDPOINTS } from "../config/api";
+import { wsManager, type ChatMessage } from "../hooks/wsManager";
 
 interface RoomGridProps {}
 
+// 房间列表筛选条件，对应 getRooms 接口的查询参数
+interface RoomFilters {
+  search: string;
+  status: string; // 空字符串表示不限
+  hideLocked: boolean;
+  freeSlotsOnly: boolean;
+  sort: "players" | "free_slots" | "name" | "activity";
+}
+
+// 判断房间是否满足筛选条件，与 getRooms 接口的筛选规则一致
+const matchesFilters = (room: RoomInfo, filters: RoomFilters) => {
+  if (filters.status && room.status !== filters.status) return false;
+  if (filters.hideLocked && room.has_password) return false;
+  if (filters.freeSlotsOnly && room.player_count >= room.max_players) return false;
+  const search = filters.search.trim().toLowerCase();
+  if (
+    search &&
+    !room.name.toLowerCase().includes(search) &&
+    !room.room_id.toLowerCase().includes(search)
+  ) {
+    return false;
+  }
+  return true;
+};
+
+// 与 getRooms 接口一致的排序；列表中没有活动时间，按最近活跃排序时由调用方把变化的房间放到最前
+const compareRooms = (a: RoomInfo, b: RoomInfo, sort: RoomFilters["sort"]) => {
+  let order = 0;
+  if (sort === "free_slots") {
+    order = b.max_players - b.player_count - (a.max_players - a.player_count);
+  } else if (sort === "name") {
+    order = a.name < b.name ? -1 : a.name > b.name ? 1 : 0;
+  } else if (sort === "players") {
+    order = b.player_count - a.player_count;
+  }
+  if (order !== 0) return order;
+  return a.room_id < b.room_id ? -1 : a.room_id > b.room_id ? 1 : 0;
+};
+
 interface GetRoomsResponse {
   rooms: RoomInfo[];
   total_count: number;
@@ -28,19 +71,44 @@ export const RoomGrid: React.FC<RoomGridProps> = () => {
   const [error, setError] = useState<string | null>(null);
   const [page, setPage] = useState(0);
   const [hasMore, setHasMore] = useState(false);
+  const [totalCount, setTotalCount] = useState(0);
+  const [filters, setFilters] = useState<RoomFilters>({
+    search: "",
+    status: "",
+    hideLocked: false,
+    freeSlotsOnly: false,
+    sort: "players",
+  });
 
   const ROOMS_PER_PAGE = 8;
 
+  // 大厅推送的监听器只注册一次，通过 ref 读取最新的列表和筛选条件
+  const roomsRef = useRef(rooms);
+  const filtersRef = useRef(filters);
+  const hasMoreRef = useRef(hasMore);
+  roomsRef.current = rooms;
+  filtersRef.current = filters;
+  hasMoreRef.current = hasMore;
+
+  const updateFilters = (changes: Partial<RoomFilters>) =>
+    setFilters((prev) => ({ ...prev, ...changes }));
+
   const fetchRooms = async (startIndex: number = 0) => {
     try {
       setLoading(true);
       setError(null);
 
       const endIndex = startIndex + ROOMS_PER_PAGE;
-      const url = buildApiUrl(API_ENDPOINTS.GET_ROOMS, {
+      const params: Record<string, string | number> = {
         start: startIndex,
         end: endIndex,
-      });
+        sort: filters.sort,
+      };
+      if (filters.search.trim()) params.search = filters.search.trim();
+      if (filters.status) params.status = filters.status;
+      if (filters.hideLocked) params.has_password = "false";
+      if (filters.freeSlotsOnly) params.has_free_slots = "true";
+      const url = buildApiUrl(API_ENDPOINTS.GET_ROOMS, params);
       const response = await fetch(url);
 
       if (!response.ok) {
@@ -52,10 +120,17 @@ export const RoomGrid: React.FC<RoomGridProps> = () => {
       if (startIndex === 0) {
         setRooms(data.rooms);
       } else {
-        setRooms((prev) => [...prev, ...data.rooms]);
+        // 推送插入的房间可能使分页偏移，去掉已在列表中的房间
+        setRooms((prev) => [
+          ...prev,
+          ...data.rooms.filter(
+            (room) => !prev.some((item) => item.room_id === room.room_id)
+          ),
+        ]);
       }
 
       setHasMore(data.has_more);
+      setTotalCount(data.total_count);
       setPage(Math.floor(endIndex / ROOMS_PER_PAGE));
     } catch (err) {
       setError(err instanceof Error ? err.message : "获取房间列表失败");
@@ -65,6 +140,82 @@ export const RoomGrid: React.FC<RoomGridProps> = () => {
     }
   };
 
+  const fetchRoomsRef = useRef(fetchRooms);
+  fetchRoomsRef.current = fetchRooms;
+
+  // 同一时刻可能连续收到多条推送，立即更新 ref 避免后一条基于旧列表计算
+  const commitRooms = (next: RoomInfo[]) => {
+    roomsRef.current = next;
+    setRooms(next);
+  };
+
+  // 应用大厅推送的房间新增或变化
+  const applyRoomChange = (room: RoomInfo) => {
+    const currentRooms = roomsRef.current;
+    const currentFilters = filtersRef.current;
+    const existed = currentRooms.some((item) => item.room_id === room.room_id);
+    const rest = currentRooms.filter((item) => item.room_id !== room.room_id);
+
+    if (!matchesFilters(room, currentFilters)) {
+      if (existed) {
+        commitRooms(rest);
+        setTotalCount((count) => Math.max(0, count - 1));
+      }
+      return;
+    }
+    if (!existed) setTotalCount((count) => count + 1);
+
+    if (currentFilters.sort === "activity") {
+      commitRooms([room, ...rest]);
+      return;
+    }
+    // 排在已加载的最后一项之后的房间属于还没加载的页面，只更新总数
+    const last = rest[rest.length - 1];
+    if (
+      hasMoreRef.current &&
+      last &&
+      compareRooms(room, last, currentFilters.sort) > 0
+    ) {
+      commitRooms(rest);
+      return;
+    }
+    commitRooms(
+      [...rest, room].sort((a, b) => compareRooms(a, b, currentFilters.sort))
+    );
+  };
+
+  const removeRoom = (roomId: string) => {
+    const currentRooms = roomsRef.current;
+    if (!currentRooms.some((item) => item.room_id === roomId)) return;
+    commitRooms(currentRooms.filter((item) => item.room_id !== roomId));
+    setTotalCount((count) => Math.max(0, count - 1));
+  };
+
+  // 订阅大厅，房间的新增、变化和删除由服务器实时推送，无需轮询
+  useEffect(() => {
+    wsManager.send({ type: "subscribe_lobby" });
+    const unsubscribe = wsManager.subscribeToMessages((message: ChatMessage) => {
+      switch (message.type) {
+        case "connected":
+          // 重连后重新订阅，并刷新以补上断线期间错过的变化
+          wsManager.send({ type: "subscribe_lobby" });
+          fetchRoomsRef.current(0);
+          break;
+        case "room_created":
+        case "room_updated":
+          if (message.room) applyRoomChange(message.room);
+          break;
+        case "room_removed":
+          if (message.room_id != null) removeRoom(String(message.room_id));
+          break;
+      }
+    });
+    return () => {
+      unsubscribe();
+      wsManager.send({ type: "unsubscribe_lobby" });
+    };
+  }, []);
+
   const loadMore = () => {
     if (!loading && hasMore) {
       fetchRooms(page * ROOMS_PER_PAGE);
@@ -76,9 +227,14 @@ export const RoomGrid: React.FC<RoomGridProps> = () => {
     fetchRooms(0);
   };
 
+  // 筛选条件变化后重新加载，搜索输入稍作延迟
   useEffect(() => {
-    fetchRooms(0);
-  }, []);
+    const timer = setTimeout(() => {
+      setPage(0);
+      fetchRooms(0);
+    }, 300);
+    return () => clearTimeout(timer);
+  }, [filters]);
 
   if (loading && rooms.length === 0) {
     return (
@@ -109,10 +265,67 @@ export const RoomGrid: React.FC<RoomGridProps> = () => {
 
   return (
     <VStack gap={4} w="100%">
+      {/* 筛选和排序 */}
+      <HStack w="100%" gap={2} wrap="wrap">
+        <Input
+          size="sm"
+          flex="1"
+          minW="160px"
+          placeholder="搜索房间名称或ID"
+          value={filters.search}
+          onChange={(e) => updateFilters({ search: e.target.value })}
+        />
+        <NativeSelect.Root size="sm" width="auto">
+          <NativeSelect.Field
+            value={filters.status}
+            onChange={(e) => updateFilters({ status: e.target.value })}
+          >
+            <option value="">全部状态</option>
+            <option value="waiting">等待中</option>
+            <option value="playing">游戏中</option>
+          </NativeSelect.Field>
+          <NativeSelect.Indicator />
+        </NativeSelect.Root>
+        <NativeSelect.Root size="sm" width="auto">
+          <NativeSelect.Field
+            value={filters.sort}
+            onChange={(e) =>
+              updateFilters({ sort: e.target.value as RoomFilters["sort"] })
+            }
+          >
+            <option value="players">人数最多</option>
+            <option value="free_slots">空位最多</option>
+            <option value="activity">最近活跃</option>
+            <option value="name">名称</option>
+          </NativeSelect.Field>
+          <NativeSelect.Indicator />
+        </NativeSelect.Root>
+        <Switch.Root
+          size="sm"
+          checked={filters.hideLocked}
+          onCheckedChange={(e) => updateFilters({ hideLocked: e.checked })}
+          colorPalette="blue"
+        >
+          <Switch.HiddenInput />
+          <Switch.Control />
+          <Switch.Label>隐藏有密码</Switch.Label>
+        </Switch.Root>
+        <Switch.Root
+          size="sm"
+          checked={filters.freeSlotsOnly}
+          onCheckedChange={(e) => updateFilters({ freeSlotsOnly: e.checked })}
+          colorPalette="blue"
+        >
+          <Switch.HiddenInput />
+          <Switch.Control />
+          <Switch.Label>只看有空位</Switch.Label>
+        </Switch.Root>
+      </HStack>
+
       {/* 顶部操作栏 */}
       <HStack justify="space-between" w="100%">
         <Text fontSize="sm" color="gray.600">
-          找到 {rooms.length} 个房间
+          找到 {totalCount} 个房间
         </Text>
         <Button size="sm" variant="ghost" onClick={refresh} disabled={loading}>
           <LuRefreshCw size={14} />
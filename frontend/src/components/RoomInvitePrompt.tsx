@@ -0,0 +1,95 @@
+import React, { useEffect, useState } from "react";
+import { Box, VStack, HStack, Text, Button } from "@chakra-ui/react";
+import { useNavigate } from "react-router-dom";
+import { wsManager, type ChatMessage } from "../hooks/wsManager";
+
+interface RoomInvite {
+  room_id: string;
+  room_name: string;
+  inviter_name: string;
+  token: string;
+  expires_at: number; // 秒级时间戳
+}
+
+// 收到房间邀请时在右下角显示接受/拒绝提示
+export const RoomInvitePrompt: React.FC = () => {
+  const navigate = useNavigate();
+  const [invites, setInvites] = useState<RoomInvite[]>([]);
+
+  useEffect(() => {
+    const unsubscribe = wsManager.subscribeToMessages((message: ChatMessage) => {
+      if (message.type !== "room_invite" || !message.token) return;
+      const invite: RoomInvite = {
+        room_id: String(message.room_id),
+        room_name: message.room_name || `房间 #${message.room_id}`,
+        inviter_name: message.inviter_name || "Unknown",
+        token: message.token,
+        expires_at: message.expires_at || 0,
+      };
+      // 同一房间只保留最新的邀请
+      setInvites((prev) => [
+        ...prev.filter((item) => item.room_id !== invite.room_id),
+        invite,
+      ]);
+    });
+    return unsubscribe;
+  }, []);
+
+  // 定期移除已过期的邀请
+  useEffect(() => {
+    if (invites.length === 0) return;
+    const timer = setInterval(() => {
+      const now = Date.now() / 1000;
+      setInvites((prev) => prev.filter((item) => item.expires_at > now));
+    }, 5000);
+    return () => clearInterval(timer);
+  }, [invites.length]);
+
+  const dismiss = (token: string) =>
+    setInvites((prev) => prev.filter((item) => item.token !== token));
+
+  const accept = (invite: RoomInvite) => {
+    dismiss(invite.token);
+    navigate(
+      `/rooms/${encodeURIComponent(invite.room_id)}?invite=${invite.token}`
+    );
+  };
+
+  const decline = (invite: RoomInvite) => {
+    dismiss(invite.token);
+    wsManager.send({ type: "decline_invite", token: invite.token });
+  };
+
+  if (invites.length === 0) return null;
+
+  return (
+    <VStack position="fixed" bottom={4} left={4} zIndex={1000} align="stretch" gap={2}>
+      {invites.map((invite) => (
+        <Box
+          key={invite.token}
+          borderWidth="1px"
+          borderRadius="md"
+          bg="white"
+          shadow="md"
+          p={3}
+          minW="260px"
+        >
+          <Text fontWeight="bold">房间邀请</Text>
+          <Text fontSize="sm" color="gray.600" mb={2}>
+            {invite.inviter_name} 邀请您加入 {invite.room_name}
+          </Text>
+          <HStack justify="flex-end">
+            <Button size="xs" variant="outline" onClick={() => decline(invite)}>
+              拒绝
+            </Button>
+            <Button size="xs" colorPalette="blue" onClick={() => accept(invite)}>
+              接受
+            </Button>
+          </HStack>
+        </Box>
+      ))}
+    </VStack>
+  );
+};
+
+export default RoomInvitePrompt;
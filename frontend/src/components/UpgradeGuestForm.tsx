@@ -0,0 +1,124 @@
+import React, { useEffect, useState } from "react";
+import { VStack, Input, Field, Button, Text, InputGroup } from "@chakra-ui/react";
+import { useForm } from "react-hook-form";
+import { toaster } from "@/components/ui/toaster";
+import { PasswordInput } from "@/components/ui/password-input";
+import { LuCircleUser, LuShieldCheck } from "react-icons/lu";
+import { validateUsername, loadUsernameRules } from "../utils/usernameValidator";
+import { buildApiUrl, API_ENDPOINTS, authHeaders } from "../config/api";
+
+interface UpgradeGuestData {
+  username: string;
+  password: string;
+}
+
+interface UpgradeGuestFormProps {
+  onUpgradeSuccess?: () => void;
+}
+
+// 游客升级为正式账户：设置密码（可选修改用户名），对局记录保留
+export const UpgradeGuestForm: React.FC<UpgradeGuestFormProps> = ({
+  onUpgradeSuccess,
+}) => {
+  const [isLoading, setIsLoading] = useState(false);
+
+  // 同步服务端的用户名校验规则
+  useEffect(() => {
+    loadUsernameRules();
+  }, []);
+
+  const { register, handleSubmit } = useForm<UpgradeGuestData>();
+
+  const handleUpgrade = async (data: UpgradeGuestData) => {
+    const username = data.username.trim();
+    if (username) {
+      const usernameValidation = validateUsername(username);
+      if (!usernameValidation.isValid) {
+        toaster.create({
+          title: "验证错误",
+          description: usernameValidation.error || "用户名格式不正确",
+          type: "error",
+        });
+        return;
+      }
+    }
+
+    if (data.password.length < 6 || data.password.length > 64) {
+      toaster.create({
+        title: "验证错误",
+        description: "密码长度必须在6-64个字符之间",
+        type: "error",
+      });
+      return;
+    }
+
+    setIsLoading(true);
+    try {
+      const response = await fetch(buildApiUrl(API_ENDPOINTS.USER_UPGRADE), {
+        method: "POST",
+        headers: authHeaders(),
+        body: JSON.stringify({
+          password: data.password,
+          username: username || undefined,
+        }),
+      });
+      const result = await response.json();
+
+      if (response.ok) {
+        toaster.create({
+          title: "升级成功",
+          description: `账户已升级，请记住您的 User ID: ${result.id}`,
+          type: "success",
+        });
+        onUpgradeSuccess?.();
+      } else {
+        toaster.create({
+          title: "升级失败",
+          description: result.message || "升级账户时发生错误",
+          type: "error",
+        });
+      }
+    } catch (error) {
+      console.error("升级账户错误:", error);
+      toaster.create({
+        title: "系统错误",
+        description: "网络错误，请稍后再试",
+        type: "error",
+      });
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
+  return (
+    <form onSubmit={handleSubmit(handleUpgrade)}>
+      <VStack mt={6} maxW="400px">
+        <Text fontSize="sm" color="gray.600">
+          您正在使用游客账户，设置密码即可保留对局记录并在其他设备登录
+        </Text>
+        <Field.Root>
+          <Field.Label>新用户名 (可选)</Field.Label>
+          <InputGroup startAddon={<LuCircleUser />}>
+            <Input placeholder="留空则保留当前用户名" {...register("username")} />
+          </InputGroup>
+        </Field.Root>
+        <Field.Root required>
+          <Field.Label>
+            密码 <Field.RequiredIndicator />
+          </Field.Label>
+          <PasswordInput placeholder="6-64个字符" {...register("password")} />
+        </Field.Root>
+        <Button
+          type="submit"
+          loading={isLoading}
+          colorPalette="teal"
+          w="100%"
+          minW="300px"
+        >
+          <LuShieldCheck />
+          {isLoading ? "升级中..." : "升级为正式账户"}
+        </Button>
+      </VStack>
+    </form>
+  );
+};
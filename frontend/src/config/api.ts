@@ -45,7 +45,17 @@ export const getWsBaseUrl = (): string => {
 export const API_ENDPOINTS = {
   GET_ROOMS: "/api/getRooms",
   CREATE_ROOM: "/api/createRoom",
+  QUICK_PLAY: "/api/quickPlay",
   USERS: "/api/users",
+  USER_LOGIN: "/api/users/login",
+  USER_LOGOUT: "/api/users/logout",
+  USER_ME: "/api/users/me",
+  USER_GUEST: "/api/users/guest",
+  USER_UPGRADE: "/api/users/upgrade",
+  USER_RENAME: "/api/users/rename",
+  USERNAME_RULES: "/api/users/username_rules",
+  FRIENDS: "/api/friends",
+  FRIEND_REQUEST: "/api/friends/request",
   GLOBAL_WS: "/global_ws",
 } as const;
 
@@ -68,6 +78,19 @@ export const buildApiUrl = (
   return url;
 };
 
+// 读取登录会话token
+export const getAuthToken = (): string | null => {
+  return localStorage.getItem("token");
+};
+
+// 构建带认证信息的请求头
+export const authHeaders = (): Record<string, string> => {
+  const token = getAuthToken();
+  return token
+    ? { "Content-Type": "application/json", Authorization: `Bearer ${token}` }
+    : { "Content-Type": "application/json" };
+};
+
 // 构建WebSocket URL
 export const buildWsUrl = (endpoint: string): string => {
   const baseUrl = getWsBaseUrl();
@@ -80,4 +103,6 @@ export default {
   API_ENDPOINTS,
   buildApiUrl,
   buildWsUrl,
+  getAuthToken,
+  authHeaders,
 };
@@ -27,7 +27,7 @@ import { useWebSocket } from "../hooks/websocket";
 import { useAuthenticatedWebSocket } from "../hooks/useAuthenticatedWebSocket";
 import { wsManager } from "../hooks/wsManager";
 import { toaster } from "@/components/ui/toaster";
-import type { ChatMessage } from "../hooks/wsManager";
+import type { ChatMessage, MapPing } from "../hooks/wsManager";
 import { set } from "react-hook-form";
 import MultiChat, { MultiChatRef } from "../components/MultiChatV2";
 import PlayerPowerRanking, {
@@ -44,6 +44,18 @@ interface MapTile {
   hasVision?: boolean; // 是否在视野内
 }
 
+// 地图标记图标和名称
+const PING_ICONS: Record<MapPing["marker"], string> = {
+  attack: "⚔️",
+  defend: "🛡️",
+  danger: "⚠️",
+};
+const PING_LABELS: Record<MapPing["marker"], string> = {
+  attack: "进攻",
+  defend: "防守",
+  danger: "危险",
+};
+
 interface MoveEvent {
   from_x: number;
   from_y: number;
@@ -136,6 +148,7 @@ const GamePage: React.FC = () => {
   const [lastActionSent, setLastActionSent] = useState("");
   const [isInitialized, setIsInitialized] = useState(false);
   const [gameMap, setGameMap] = useState<MapTile[]>([]);
+  const [pings, setPings] = useState<MapPing[]>([]); // 队友的地图标记
   const [selectedTile, setSelectedTile] = useState<{
     x: number;
     y: number;
@@ -1128,6 +1141,25 @@ const GamePage: React.FC = () => {
     return false;
   };
 
+  // 右键放置地图标记：右键进攻，Shift+右键防守，Alt+右键危险
+  const handleTileContextMenu = (e: React.MouseEvent, x: number, y: number) => {
+    e.preventDefault();
+    if (isObserver || !roomId) return;
+
+    const marker: MapPing["marker"] = e.shiftKey
+      ? "defend"
+      : e.altKey
+        ? "danger"
+        : "attack";
+    wsManager.send({
+      type: "ping",
+      room_id: roomId,
+      x,
+      y,
+      marker,
+    });
+  };
+
   // 处理瓦片点击 - 构建移动轨迹
   const handleTileClick = (x: number, y: number) => {
     // 观众模式下禁用所有操作
@@ -1852,6 +1884,8 @@ const GamePage: React.FC = () => {
           case "map_update":
             //console.log("收到 map_update 消息:", message);
             if (message.room_id == roomId && message.visible_tiles) {
+              setPings(message.pings || []);
+
               // 如果收到地图更新但游戏还没开始，说明游戏已经在进行
               if (!gameStarted) {
                 console.log("通过 map_update 设置游戏开始状态为 true");
@@ -2500,9 +2534,31 @@ const GamePage: React.FC = () => {
                                 ? undefined // 不可操作的地形不响应点击
                                 : () => handleTileClick(x, y)
                             }
+                            onContextMenu={(e: React.MouseEvent) =>
+                              handleTileContextMenu(e, x, y)
+                            }
                             opacity={1} // 所有tile都是不透明的
                           >
                             {getTileText(tile)}
+                            {(() => {
+                              const ping = pings.find(
+                                (p) => p.x === x && p.y === y
+                              );
+                              return (
+                                ping && (
+                                  <Box
+                                    position="absolute"
+                                    top="0"
+                                    right="1px"
+                                    fontSize="sm"
+                                    pointerEvents="none"
+                                    title={`${ping.username}: ${PING_LABELS[ping.marker]}`}
+                                  >
+                                    {PING_ICONS[ping.marker]}
+                                  </Box>
+                                )
+                              );
+                            })()}
                           </Box>
                         );
                       })}
@@ -1,11 +1,14 @@
 import React from "react";
 import { Center, VStack, Text, HStack, Link, Box } from "@chakra-ui/react";
 import { AuthForms } from "../components/AuthForms";
+import { UpgradeGuestForm } from "../components/UpgradeGuestForm";
 import { MainButtons } from "../components/MainButtons";
+import { FriendsPanel } from "../components/FriendsPanel";
+import { RankedQueue } from "../components/RankedQueue";
 import { useAuth } from "../contexts/AuthContext";
 
 export const Home = () => {
-  const { user, isLoading, isAuthenticated, login } = useAuth();
+  const { user, isLoading, isAuthenticated, login, refreshAuth } = useAuth();
 
   const handleRegisterSuccess = (userData: any) => {
     console.log("注册成功，用户信息:", userData);
@@ -13,6 +16,8 @@ export const Home = () => {
     login({
       user_id: userData.id,
       username: userData.username,
+      token: userData.token,
+      is_guest: !!userData.is_guest,
     });
   };
 
@@ -22,6 +27,8 @@ export const Home = () => {
     login({
       user_id: userData.id,
       username: userData.username,
+      token: userData.token,
+      is_guest: !!userData.is_guest,
     });
   };
 
@@ -72,6 +79,9 @@ export const Home = () => {
               欢迎回来，{user?.username}！
             </Text>
             <MainButtons />
+            {user?.is_guest && <UpgradeGuestForm onUpgradeSuccess={refreshAuth} />}
+            <RankedQueue />
+            <FriendsPanel />
           </>
         ) : (
           <AuthForms
@@ -41,6 +41,7 @@ interface RoomInfo {
   required_to_start: number;
   groups?: { [key: number]: GroupInfo }; // 新增：房间分组信息
   max_players?: number; // 可选的最大玩家数
+  muted_players?: string[]; // 正在禁言中的玩家
 }
 
 const RoomPage: React.FC = () => {
@@ -58,6 +59,7 @@ const RoomPage: React.FC = () => {
   const [inputMessage, setInputMessage] = useState("");
   const [showPasswordPrompt, setShowPasswordPrompt] = useState(false);
   const [hasTriedJoin, setHasTriedJoin] = useState(false);
+  const [inviteName, setInviteName] = useState("");
 
   // 从wsManager获取房间消息
   const messages = roomId ? wsManager.getRoomMessages(roomId) : [];
@@ -73,7 +75,10 @@ const RoomPage: React.FC = () => {
     if (gameState.isConnected && !hasTriedJoin) {
       setHasTriedJoin(true);
       const password = location.state?.password;
-      wsManager.joinRoom(roomId, user.username, password);
+      // 通过邀请或邀请链接进入时携带 token，可免密码加入
+      const inviteToken =
+        new URLSearchParams(location.search).get("invite") || undefined;
+      wsManager.joinRoom(roomId, user.username, password, inviteToken);
       // 请求房间详细信息
       setTimeout(() => {
         wsManager.send({
@@ -114,6 +119,7 @@ const RoomPage: React.FC = () => {
                 required_to_start: message.required_to_start || 0,
                 groups: message.groups || {}, // 新增：分组信息
                 max_players: message.max_players || 16, // 默认最大玩家数为16
+                muted_players: message.muted_players || [],
               };
               setRoomInfo(roomData);
 
@@ -134,6 +140,31 @@ const RoomPage: React.FC = () => {
             // 聊天消息已经由wsManager处理，这里不需要额外处理
             break;
 
+          case "invite_link":
+            if (message.room_id == roomId && message.token) {
+              const link = `${window.location.origin}/rooms/${encodeURIComponent(
+                roomId || ""
+              )}?invite=${message.token}`;
+              navigator.clipboard
+                ?.writeText(link)
+                .then(() =>
+                  toaster.create({
+                    title: "邀请链接已复制",
+                    description: "链接24小时内有效",
+                    type: "success",
+                  })
+                )
+                .catch(() =>
+                  toaster.create({
+                    title: "邀请链接",
+                    description: link,
+                    type: "info",
+                    duration: 15000,
+                  })
+                );
+            }
+            break;
+
           case "join_room":
             // 有新玩家加入
             if (
@@ -178,9 +209,9 @@ const RoomPage: React.FC = () => {
               description: message.message || "发生未知错误",
               type: "error",
             });
-            // 如果是密码错误或房间已满，则返回房间列表
+            // 如果是密码错误（包括输错次数过多）或房间已满，则返回房间列表
             if (
-              message.message === "密码错误" ||
+              message.message?.startsWith("密码错误") ||
               message.message === "房间已满" ||
               message.message === "房间不存在"
             ) {
@@ -207,7 +238,7 @@ const RoomPage: React.FC = () => {
 
           case "end_game":
             // 游戏结束事件（在游戏页面处理，这里主要用于房间状态更新）
-            if (message.room_id == roomId) {
+            if (roomId && message.room_id == roomId) {
               // 重新请求房间信息以更新状态
               wsManager.send({
                 type: "get_room_info",
@@ -304,6 +335,51 @@ const RoomPage: React.FC = () => {
     }
   };
 
+  // 禁言玩家（默认5分钟）
+  const mutePlayer = (targetPlayerName: string) => {
+    if (user && roomId) {
+      wsManager.send({
+        type: "mute_player",
+        room_id: roomId,
+        target_player_name: targetPlayerName,
+        duration_minutes: 5,
+      });
+    }
+  };
+
+  // 解除禁言
+  const unmutePlayer = (targetPlayerName: string) => {
+    if (user && roomId) {
+      wsManager.send({
+        type: "unmute_player",
+        room_id: roomId,
+        target_player_name: targetPlayerName,
+      });
+    }
+  };
+
+  // 邀请在线玩家加入房间
+  const invitePlayer = () => {
+    if (user && roomId && inviteName.trim()) {
+      wsManager.send({
+        type: "invite",
+        room_id: roomId,
+        target_player_name: inviteName.trim(),
+      });
+      setInviteName("");
+    }
+  };
+
+  // 生成邀请链接（房主和管理员）
+  const createInviteLink = () => {
+    if (user && roomId) {
+      wsManager.send({
+        type: "create_invite_link",
+        room_id: roomId,
+      });
+    }
+  };
+
   // 观战游戏
   const watchGame = () => {
     if (roomId) {
@@ -317,7 +393,6 @@ const RoomPage: React.FC = () => {
       wsManager.send({
         type: "force_start",
         room_id: roomId,
-        player_id: user.username,
       });
     }
   };
@@ -328,7 +403,6 @@ const RoomPage: React.FC = () => {
       wsManager.send({
         type: "de_force_start",
         room_id: roomId,
-        player_id: user.username,
       });
     }
   };
@@ -584,6 +658,11 @@ const RoomPage: React.FC = () => {
                           管理员
                         </Badge>
                       )}
+                      {roomInfo?.muted_players?.includes(player.username) && (
+                        <Badge size="sm" colorPalette="gray">
+                          禁言中
+                        </Badge>
+                      )}
                     </HStack>
 
                     <HStack>
@@ -629,6 +708,31 @@ const RoomPage: React.FC = () => {
                             踢出
                           </Button>
                         )}
+
+                      {/* 禁言/解除禁言按钮，房主和管理员都能看到，但不能禁言房主 */}
+                      {(user?.username === roomInfo?.host_player_name ||
+                        user?.username === roomInfo?.admin_player_name) &&
+                        player.username !== roomInfo?.host_player_name &&
+                        player.username !== user?.username &&
+                        (roomInfo?.muted_players?.includes(player.username) ? (
+                          <Button
+                            size="xs"
+                            variant="outline"
+                            colorPalette="gray"
+                            onClick={() => unmutePlayer(player.username)}
+                          >
+                            解除禁言
+                          </Button>
+                        ) : (
+                          <Button
+                            size="xs"
+                            variant="outline"
+                            colorPalette="gray"
+                            onClick={() => mutePlayer(player.username)}
+                          >
+                            禁言
+                          </Button>
+                        ))}
                     </HStack>
                   </Flex>
                 ))}
@@ -640,6 +744,46 @@ const RoomPage: React.FC = () => {
               </VStack>
             </Card.Body>
           </Card.Root>
+
+          {/* 邀请玩家 */}
+          <Card.Root>
+            <Card.Header>
+              <Heading size="md">邀请玩家</Heading>
+            </Card.Header>
+            <Card.Body>
+              <VStack align="stretch" gap={2}>
+                <HStack>
+                  <Input
+                    size="sm"
+                    placeholder="输入在线玩家用户名"
+                    value={inviteName}
+                    onChange={(e) => setInviteName(e.target.value)}
+                    onKeyDown={(e) => e.key === "Enter" && invitePlayer()}
+                    disabled={!connected}
+                  />
+                  <Button
+                    size="sm"
+                    colorPalette="blue"
+                    onClick={invitePlayer}
+                    disabled={!inviteName.trim() || !connected}
+                  >
+                    邀请
+                  </Button>
+                </HStack>
+                {(user?.username === roomInfo?.host_player_name ||
+                  user?.username === roomInfo?.admin_player_name) && (
+                  <Button
+                    size="sm"
+                    variant="outline"
+                    onClick={createInviteLink}
+                    disabled={!connected}
+                  >
+                    生成邀请链接
+                  </Button>
+                )}
+              </VStack>
+            </Card.Body>
+          </Card.Root>
         </VStack>
       </HStack>
 
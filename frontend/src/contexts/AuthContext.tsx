@@ -7,10 +7,14 @@ import React, {
 } from "react";
 import { checkLoginStatus } from "../hooks/checkLoginStatus";
 import { toaster } from "@/components/ui/toaster";
+import { buildApiUrl, API_ENDPOINTS, authHeaders } from "../config/api";
 
 interface User {
   user_id: string;
   username: string;
+  token?: string; // 登录或注册时服务器签发的会话token
+  is_guest?: boolean; // 游客账户
+  rating?: number; // 1v1 排位分
 }
 
 interface AuthContextType {
@@ -51,12 +55,14 @@ export const AuthProvider: React.FC<AuthProviderProps> = ({ children }) => {
           // 清理可能被篡改的localStorage
           localStorage.removeItem("user_id");
           localStorage.removeItem("username");
+          localStorage.removeItem("token");
         }
       } catch (error) {
         console.error("认证初始化失败:", error);
         setUser(null);
         localStorage.removeItem("user_id");
         localStorage.removeItem("username");
+        localStorage.removeItem("token");
       } finally {
         setIsLoading(false);
       }
@@ -71,6 +77,9 @@ export const AuthProvider: React.FC<AuthProviderProps> = ({ children }) => {
     // 同步到localStorage（作为备份）
     localStorage.setItem("user_id", userData.user_id);
     localStorage.setItem("username", userData.username);
+    if (userData.token) {
+      localStorage.setItem("token", userData.token);
+    }
 
     console.log("用户已登录到Context:", userData);
     toaster.create({
@@ -82,9 +91,16 @@ export const AuthProvider: React.FC<AuthProviderProps> = ({ children }) => {
 
   // 登出函数
   const logout = () => {
+    // 通知服务器注销当前会话（失败不影响本地登出）
+    fetch(buildApiUrl(API_ENDPOINTS.USER_LOGOUT), {
+      method: "POST",
+      headers: authHeaders(),
+    }).catch((error) => console.error("注销会话失败:", error));
+
     setUser(null);
     localStorage.removeItem("user_id");
     localStorage.removeItem("username");
+    localStorage.removeItem("token");
 
     console.log("用户已登出");
     toaster.create({
@@ -109,6 +125,7 @@ export const AuthProvider: React.FC<AuthProviderProps> = ({ children }) => {
         setUser(null);
         localStorage.removeItem("user_id");
         localStorage.removeItem("username");
+        localStorage.removeItem("token");
         toaster.create({
           title: "认证失败",
           description: "用户信息验证失败，请重新登录",
@@ -120,6 +137,7 @@ export const AuthProvider: React.FC<AuthProviderProps> = ({ children }) => {
       setUser(null);
       localStorage.removeItem("user_id");
       localStorage.removeItem("username");
+      localStorage.removeItem("token");
     } finally {
       setIsLoading(false);
     }
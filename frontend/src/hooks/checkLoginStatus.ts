@@ -1,27 +1,22 @@
-import { buildApiUrl, API_ENDPOINTS } from "../config/api";
+import { buildApiUrl, API_ENDPOINTS, authHeaders, getAuthToken } from "../config/api";
 
 export const checkLoginStatus = async (): Promise<
-  { user_id: string; username: string } | false
+  { user_id: string; username: string; is_guest: boolean } | false
 > => {
-  // 读取localStorage中的user_id
-  const user_id = localStorage.getItem("user_id");
+  // 读取localStorage中的会话token
+  const token = getAuthToken();
 
-  // 如果没有读取到user_id，返回false
-  if (!user_id) {
+  // 如果没有读取到token，返回false
+  if (!token) {
     return false;
   }
 
   try {
-    // 调用后端接口获取用户信息
-    const response = await fetch(
-      buildApiUrl(API_ENDPOINTS.USERS, { user_id }),
-      {
-        method: "GET",
-        headers: {
-          "Content-Type": "application/json",
-        },
-      }
-    );
+    // 调用后端接口获取当前登录用户信息
+    const response = await fetch(buildApiUrl(API_ENDPOINTS.USER_ME), {
+      method: "GET",
+      headers: authHeaders(),
+    });
 
     // 如果响应成功
     if (response.ok) {
@@ -29,24 +24,28 @@ export const checkLoginStatus = async (): Promise<
 
       // 检查返回的数据是否包含username
       if (userData && userData.username) {
-        // 更新localStorage中的username
+        // 更新localStorage中的用户信息
+        localStorage.setItem("user_id", userData.id);
         localStorage.setItem("username", userData.username);
         console.log("用户信息已更新:", userData);
         // 返回用户信息
         return {
-          user_id: user_id,
+          user_id: userData.id,
           username: userData.username,
+          is_guest: !!userData.is_guest,
         };
       } else {
         // 如果返回的数据格式不正确，清除localStorage并返回false
         localStorage.removeItem("user_id");
         localStorage.removeItem("username");
+        localStorage.removeItem("token");
         return false;
       }
     } else {
       // 如果后端响应错误（如用户不存在），清除localStorage并返回false
       localStorage.removeItem("user_id");
       localStorage.removeItem("username");
+      localStorage.removeItem("token");
       return false;
     }
   } catch (error) {
@@ -54,6 +53,7 @@ export const checkLoginStatus = async (): Promise<
     console.error("检查登录状态时发生错误:", error);
     localStorage.removeItem("user_id");
     localStorage.removeItem("username");
+    localStorage.removeItem("token");
     return false;
   }
 };
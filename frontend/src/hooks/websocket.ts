@@ -19,9 +19,7 @@ export function useWebSocket() {
     sendChat: (content: string) => wsManager.sendChat(content),
     joinRoom: (roomId: number) => wsManager.joinRoom(roomId),
     leaveRoom: () => wsManager.leaveRoom(),
-    changeName: (newName: string) => wsManager.changeName(newName),
     toggleForceStart: () => wsManager.toggleForceStart(),
-    sendMove: (direction: string) => wsManager.sendMove(direction),
     clearMessages: () => wsManager.clearMessages(),
   };
 }
@@ -1,5 +1,12 @@
 import { createToaster } from "@chakra-ui/react";
-import { buildWsUrl, API_ENDPOINTS } from "../config/api";
+import { buildWsUrl, API_ENDPOINTS, getAuthToken } from "../config/api";
+import type { RoomInfo } from "../components/RoomCard";
+import { decodeMsgpack, unpackTiles, type PackedTile } from "../utils/msgpack";
+
+// 客户端使用的协议版本，与后端 protocol.rs 中的 PROTOCOL_VERSION 保持一致
+const PROTOCOL_VERSION = 2;
+// 客户端支持的可选功能，实际启用的以服务器 handshake 返回的为准
+const CLIENT_FEATURES = ["lobby", "invites", "ranked", "quick_play", "map_delta", "msgpack"];
 
 // 创建 toaster 实例
 const toaster = createToaster({
@@ -13,34 +20,44 @@ interface User {
 }
 
 export interface ChatMessage {
+  // 服务器发送的消息类型来自后端导出的 ServerMessage，其余为前端本地生成的消息
   type:
+    | ServerMessage["type"]
     | "chat"
-    | "chat_message" // 支持后端发送的聊天消息格式
-    | "join_room"
-    | "leave_room"
-    | "change_name"
     | "system"
-    | "room_info"
-    | "ok"
-    | "error"
-    | "connected" // 认证成功消息
-    | "redirect_to_home" // 重定向到首页消息
-    | "redirect_to_game" // 重定向到游戏页面消息 - 新增
-    | "change_group" // 切换组别消息
-    | "start_game" // 游戏开始消息
-    | "end_game" // 游戏结束消息
-    | "game_turn_update" // 游戏回合更新消息
-    | "map_update" // 地图更新消息
-    | "game_win" // 游戏胜利消息
-    | "move_ok"; // 移动成功确认消息
+    | "change_name"
+    | "change_group"; // 切换组别消息
   room_id?: number | string; // 支持数字和字符串类型的房间ID
   sender_id?: number;
   player_id?: number;
   player_name?: string;
   username?: string; // 添加用户名字段用于全局聊天
+  recipient_name?: string; // 私信接收者用户名
+  channel?: "team" | "spectator"; // 房间内的队伍/观战频道，不填为全体
+  user_id?: string; // friend_update 中的好友ID
+  event?: "presence" | "request" | "accepted" | "removed"; // friend_update 事件类型
+  presence?: {
+    status: "offline" | "lobby" | "waiting" | "playing" | "spectating";
+    room_id?: string | null;
+  } | null;
+  room_name?: string; // room_invite 中的房间名称
+  inviter_name?: string; // room_invite 中的邀请者用户名
+  token?: string; // 邀请 token
+  expires_at?: number; // 邀请过期时间（秒级时间戳）
+  rating?: number | null; // 排位分
+  opponent_name?: string; // match_found 中的对手用户名
+  opponent_rating?: number; // match_found 中的对手排位分
+  room?: RoomInfo | null; // room_created / room_updated 中的房间
+  rooms?: RoomInfo[]; // lobby_rooms 中的房间列表
   new_name?: string;
   content?: string;
   message?: string;
+  error?: string; // protocol_error 中的错误代码
+  request_type?: string; // protocol_error 中无法处理的消息类型
+  protocol_version?: number; // connected / handshake 中的协议版本
+  min_protocol_version?: number; // 服务器兼容的最低协议版本
+  client_version?: number; // protocol_mismatch 中客户端声明的版本
+  features?: string[]; // connected 中服务器支持的功能，handshake 中协商后启用的功能
   reason?: string; // 重定向原因
   timestamp?: string;
   players?: [number, string][] | string[]; // 支持两种格式：[id, name]数组或用户名数组
@@ -56,15 +73,43 @@ export interface ChatMessage {
   groups?: GroupInfo[];
   target_group_id?: number; // 切换组别时的目标组ID
   max_players?: number; // 可选的最大玩家数
+  muted_players?: string[]; // 正在禁言中的玩家用户名
   // 游戏回合相关字段
   turn?: number; // 当前回合数
   turn_half?: boolean; // true为上半回合, false为下半回合
   actions?: [string, string][]; // 玩家动作列表 [player_name, action]
   // 地图相关字段
   successful_move_sends?: number[];
-  visible_tiles?: [number, number, string, number, string | null][]; // [x, y, tile_type, count, user_id]
+  visible_tiles?: VisibleTile[];
+  seq?: number; // 地图更新序号
+  changed_tiles?: VisibleTile[]; // map_delta 中变化的格子
+  removed_tiles?: [number, number][]; // map_delta 中不再可见的格子
   player_powers?: [string, number, number, string][]; // [username, group_id, total_power, status]
   winner?: string; // 游戏胜利者
+  messages?: ChatHistoryEntry[]; // chat_history 消息携带的聊天记录
+  pings?: MapPing[]; // map_update 中当前玩家可见的地图标记
+}
+
+// 地图格子 [x, y, tile_type, count, user_id, has_vision]
+export type VisibleTile = [number, number, string, number, string | null, boolean];
+
+// 地图标记，只对队友和观战者可见
+export interface MapPing {
+  x: number;
+  y: number;
+  marker: "attack" | "defend" | "danger";
+  username: string;
+  expires_turn: number;
+}
+
+// 服务端保存的聊天记录
+export interface ChatHistoryEntry {
+  id: number;
+  room_id: string;
+  sender_id: string;
+  username: string;
+  content: string;
+  created_at: string; // UTC 时间
 }
 
 // 新增：分组信息接口
@@ -111,7 +156,23 @@ class WebSocketManager {
 
   private stateListeners: Set<GameStateListener> = new Set();
   private messageListeners: Set<MessageListener> = new Set();
-  private messageQueue: any[] = []; // 消息队列
+  private messageQueue: ClientMessage[] = []; // 消息队列
+
+  // 协议协商结果，收到 handshake 前按最低版本处理
+  private protocolVersion: number = 1;
+  private features: Set<string> = new Set();
+  private protocolRejected: boolean = false; // 客户端版本过旧被服务器拒绝，不再自动重连
+
+  // 各房间当前持有的地图，用于合并 map_delta
+  private mapStates: {
+    [roomId: string]: {
+      seq: number;
+      tiles: Map<string, VisibleTile>;
+      awaitingSnapshot: boolean; // 已请求快照，在收到之前丢弃增量
+    };
+  } = {};
+  // 各房间二进制帧中格子归属使用的队伍表，随快照或新增队伍时下发
+  private teamTables: { [roomId: string]: string[] } = {};
 
   // 重连相关状态
   private reconnectAttempts: number = 0;
@@ -213,20 +274,20 @@ class WebSocketManager {
         let wsUrl = buildWsUrl(API_ENDPOINTS.GLOBAL_WS);
 
         const currentUser = this.getCurrentUser();
+        const token = getAuthToken();
 
-        if (currentUser && currentUser.user_id && currentUser.username) {
-          // 使用认证参数
-          const params = new URLSearchParams({
-            user_id: currentUser.user_id,
-            username: currentUser.username,
-          });
+        if (currentUser && token) {
+          // 使用会话token认证，用户身份由服务器确定
+          const params = new URLSearchParams({ token });
           wsUrl += `?${params.toString()}`;
-          console.log("使用认证参数连接WebSocket:", wsUrl);
+          console.log("使用会话token连接WebSocket");
         } else {
           console.warn("未找到用户信息，WebSocket连接可能失败");
         }
 
         this.ws = new WebSocket(wsUrl);
+        // 协商了 msgpack 后地图和回合更新以二进制帧发送
+        this.ws.binaryType = "arraybuffer";
 
         this.ws.onopen = () => {
           // 重连成功，重置重连计数器
@@ -259,6 +320,13 @@ class WebSocketManager {
             });
           }
 
+          // 声明客户端的协议版本和支持的功能
+          this.send({
+            type: "hello",
+            protocol_version: PROTOCOL_VERSION,
+            features: CLIENT_FEATURES,
+          });
+
           // 处理队列中的消息
           this.processMessageQueue();
 
@@ -281,9 +349,19 @@ class WebSocketManager {
             isForcingStart: false,
           });
           this.ws = null;
+          // 重连后需要重新协商
+          this.protocolVersion = 1;
+          this.features = new Set();
+          this.teamTables = {};
 
           const wasIntentional = event.code === 1000 || event.code === 1001;
 
+          if (this.protocolRejected) {
+            // 版本不兼容时重连也会被拒绝，提示已在收到 protocol_mismatch 时给出
+            this.addSystemMessage("🔌 客户端版本过旧，连接已关闭，请刷新页面");
+            return;
+          }
+
           if (
             !wasIntentional &&
             this.reconnectAttempts < this.maxReconnectAttempts
@@ -377,9 +455,20 @@ class WebSocketManager {
   // 处理接收到的消息
   private handleMessage(event: MessageEvent) {
     try {
-      const message: ChatMessage = JSON.parse(event.data);
+      const message: ChatMessage =
+        event.data instanceof ArrayBuffer
+          ? this.decodeBinaryMessage(event.data)
+          : JSON.parse(event.data);
       message.timestamp = new Date().toLocaleTimeString();
 
+      // 地图增量先合并为完整地图，监听器只需处理 map_update
+      if (
+        (message.type === "map_update" || message.type === "map_delta") &&
+        !this.applyMapMessage(message)
+      ) {
+        return;
+      }
+
       // 通知消息监听器
       this.notifyMessage(message);
 
@@ -427,6 +516,56 @@ class WebSocketManager {
         case "change_name":
           this.handleChangeName(message);
           break;
+        case "username_changed":
+          this.handleUsernameChanged(message);
+          break;
+        case "chat_history":
+          this.handleChatHistory(message);
+          break;
+        case "whisper":
+          // 私信显示在全局聊天中
+          this.updateState({
+            messages: [...this.gameState.messages, message],
+          });
+          break;
+        case "handshake":
+          this.protocolVersion = message.protocol_version ?? 1;
+          this.features = new Set(message.features ?? []);
+          console.log("协议协商完成:", this.protocolVersion, [...this.features]);
+          break;
+        case "protocol_mismatch":
+          this.protocolRejected = true;
+          this.addSystemMessage(`❌ ${message.message}`);
+          toaster.create({
+            title: "客户端版本过旧",
+            description: message.message || "请刷新页面以更新客户端",
+            type: "error",
+            duration: 10000,
+          });
+          break;
+        case "protocol_error":
+          if (message.request_type === "hello") {
+            // 服务器尚不支持版本协商，按最低版本继续使用
+            this.protocolVersion = 1;
+            this.features = new Set();
+            console.warn("服务器不支持协议协商，按协议版本 1 通信");
+            break;
+          }
+          // 发送的消息与协议不符，属于前端问题，只在控制台提示
+          console.warn("服务器无法解析消息:", message.request_type, message.error, message.message);
+          break;
+        case "friend_update":
+        case "room_invite":
+        case "invite_link":
+        case "queue_status":
+        case "match_found":
+        case "quick_play":
+        case "lobby_rooms":
+        case "room_created":
+        case "room_updated":
+        case "room_removed":
+          // 由好友列表、邀请提示等组件通过消息监听器处理，不加入聊天历史
+          break;
         default:
           // 其他消息类型添加到聊天历史中
           this.updateState({
@@ -449,7 +588,7 @@ class WebSocketManager {
         // 获取房间玩家信息
         this.send({
           type: "get_room_info",
-          room_id: message.room_id,
+          room_id: String(message.room_id),
         });
       }
     } catch (error) {
@@ -497,6 +636,32 @@ class WebSocketManager {
     this.notifyMessage(chatMessage);
   }
 
+  // 处理聊天记录：替换对应房间已有的聊天消息，避免重连后重复显示
+  private handleChatHistory(message: ChatMessage) {
+    if (!message.room_id || !Array.isArray(message.messages)) return;
+    const roomId = String(message.room_id);
+
+    const history: ChatMessage[] = message.messages.map((entry) => ({
+      type: "chat_message",
+      room_id: entry.room_id,
+      username: entry.username,
+      content: entry.content,
+      message: entry.content,
+      timestamp: new Date(`${entry.created_at}Z`).toLocaleTimeString(),
+    }));
+
+    if (roomId === "global") {
+      const others = this.gameState.messages.filter(
+        (msg) => !(msg.type === "chat_message" && msg.room_id === "global")
+      );
+      this.updateState({ messages: [...history, ...others] });
+    } else {
+      const roomMessages = { ...this.gameState.roomMessages };
+      roomMessages[roomId] = history;
+      this.updateState({ roomMessages });
+    }
+  }
+
   // 处理房间信息
   private handleRoomInfo(message: ChatMessage) {
     const updates: Partial<GameState> = {};
@@ -617,7 +782,7 @@ class WebSocketManager {
       // 加入房间后主动获取房间玩家信息
       this.send({
         type: "get_room_info",
-        room_id: message.room_id,
+        room_id: String(message.room_id),
       });
     } else {
       // 其他玩家加入房间的通知
@@ -630,7 +795,7 @@ class WebSocketManager {
       // 有玩家加入也刷新房间玩家信息
       this.send({
         type: "get_room_info",
-        room_id: message.room_id,
+        room_id: String(message.room_id),
       });
     }
   }
@@ -676,7 +841,7 @@ class WebSocketManager {
     if (message.room_id && this.gameState.currentRoomId === message.room_id) {
       this.send({
         type: "get_room_info",
-        room_id: message.room_id,
+        room_id: String(message.room_id),
       });
     }
   }
@@ -769,13 +934,101 @@ class WebSocketManager {
     if (this.gameState.currentRoomId) {
       this.send({
         type: "get_room_info",
-        room_id: this.gameState.currentRoomId,
+        room_id: String(this.gameState.currentRoomId),
       });
     }
   }
 
-  // 发送消息
-  send(message: any) {
+  // 处理用户名修改：同步当前用户信息
+  private handleUsernameChanged(message: ChatMessage) {
+    const currentUser = this.getCurrentUser();
+    if (currentUser && message.username) {
+      this.updateState({
+        currentUser: { ...currentUser, username: message.username },
+      });
+      localStorage.setItem("username", message.username);
+      this.addSystemMessage(`👤 用户名已更改为: ${message.username}`);
+    }
+  }
+
+  // 解码二进制帧，地图格子按该房间的队伍表还原为与 JSON 消息相同的格式
+  private decodeBinaryMessage(data: ArrayBuffer): ChatMessage {
+    const message = decodeMsgpack(data) as ChatMessage & { teams?: string[] };
+    if (message.type === "map_update" || message.type === "map_delta") {
+      const roomId = String(message.room_id);
+      if (message.teams) {
+        this.teamTables[roomId] = message.teams;
+        delete message.teams;
+      }
+      const teams = this.teamTables[roomId] ?? [];
+      if (message.visible_tiles) {
+        message.visible_tiles = unpackTiles(message.visible_tiles as unknown as PackedTile[], teams);
+      }
+      if (message.changed_tiles) {
+        message.changed_tiles = unpackTiles(message.changed_tiles as unknown as PackedTile[], teams);
+      }
+    }
+    return message;
+  }
+
+  // 合并地图快照和增量，返回 false 表示该消息应丢弃
+  private applyMapMessage(message: ChatMessage): boolean {
+    const roomId = String(message.room_id);
+    const seq = message.seq ?? 0;
+    const tileKey = (x: number, y: number) => `${x},${y}`;
+
+    if (message.type === "map_update") {
+      const tiles = new Map<string, VisibleTile>();
+      message.visible_tiles?.forEach((tile) =>
+        tiles.set(tileKey(tile[0], tile[1]), tile)
+      );
+      this.mapStates[roomId] = { seq, tiles, awaitingSnapshot: false };
+      return true;
+    }
+
+    const state = this.mapStates[roomId];
+    if (!state || state.awaitingSnapshot || seq !== state.seq + 1) {
+      // 漏掉了中间的更新，丢弃增量并请求完整快照
+      if (!state?.awaitingSnapshot) {
+        console.warn(
+          `地图更新序号不连续: 期望 ${state ? state.seq + 1 : "快照"}，收到 ${seq}，请求完整快照`
+        );
+        this.mapStates[roomId] = {
+          seq: state?.seq ?? 0,
+          tiles: state?.tiles ?? new Map(),
+          awaitingSnapshot: true,
+        };
+        this.send({ type: "request_map_snapshot", room_id: roomId });
+      }
+      return false;
+    }
+
+    message.removed_tiles?.forEach(([x, y]) => state.tiles.delete(tileKey(x, y)));
+    message.changed_tiles?.forEach((tile) =>
+      state.tiles.set(tileKey(tile[0], tile[1]), tile)
+    );
+    state.seq = seq;
+
+    // 转换为完整的 map_update，页面组件无需区分快照和增量
+    message.type = "map_update";
+    message.visible_tiles = Array.from(state.tiles.values());
+    delete message.changed_tiles;
+    delete message.removed_tiles;
+    return true;
+  }
+
+  // 服务器是否启用了某个可选功能
+  hasFeature(feature: string) {
+    return this.features.has(feature);
+  }
+
+  // 与服务器协商后的协议版本
+  getProtocolVersion() {
+    return this.protocolVersion;
+  }
+
+  // 发送消息，消息格式由后端导出的 ClientMessage 定义
+  send(message: ClientMessage) {
     console.log("wsManager.send 被调用:", message);
     if (
       this.ws &&
@@ -831,13 +1084,18 @@ class WebSocketManager {
 
     return this.send({
       type: "chat",
-      room_id: targetRoomId,
+      room_id: String(targetRoomId),
       content,
     });
   }
 
   // 加入房间
-  joinRoom(roomId: string | number, playerName?: string, password?: string) {
+  joinRoom(
+    roomId: string | number,
+    playerName?: string,
+    password?: string,
+    inviteToken?: string
+  ) {
     const currentUser = this.getCurrentUser();
     const playerNameToUse = playerName || currentUser?.username || "Unknown";
 
@@ -849,25 +1107,27 @@ class WebSocketManager {
     ) {
       this.send({
         type: "leave_room",
-        room_id: this.gameState.currentRoomId,
+        room_id: String(this.gameState.currentRoomId),
       });
       this.addSystemMessage(`🚪 离开房间 ${this.gameState.currentRoomId}`);
       // 等待后端处理后再加入新房间，防止room_id错误
       setTimeout(() => {
         this.send({
           type: "join_room",
-          room_id: roomId,
+          room_id: String(roomId),
           player_name: playerNameToUse,
           password: password, // 发送密码
+          invite_token: inviteToken, // 邀请 token，可代替密码
         });
         this.addSystemMessage(`🚪 尝试加入房间 ${roomId}`);
       }, 100);
     } else {
       this.send({
         type: "join_room",
-        room_id: roomId,
+        room_id: String(roomId),
         player_name: playerNameToUse,
         password: password, // 发送密码
+        invite_token: inviteToken, // 邀请 token，可代替密码
       });
       this.addSystemMessage(`🚪 尝试加入房间 ${roomId}`);
     }
@@ -881,7 +1141,7 @@ class WebSocketManager {
     ) {
       this.send({
         type: "leave_room",
-        room_id: this.gameState.currentRoomId,
+        room_id: String(this.gameState.currentRoomId),
       });
       this.addSystemMessage(`🚪 离开房间 ${this.gameState.currentRoomId}`);
 
@@ -892,43 +1152,23 @@ class WebSocketManager {
     }
   }
 
-  // 更改名称
-  changeName(newName: string) {
-    if (this.gameState.currentPlayerId) {
-      this.send({ type: "change_name", new_name: newName });
-      this.addSystemMessage(`👤 尝试更改名称为: ${newName}`);
-      return true;
-    }
-    return false;
-  }
-
   // 切换强制开始
   toggleForceStart() {
     const messageType = this.gameState.isForcingStart
-      ? "deforce_start"
+      ? "de_force_start"
       : "force_start";
     this.send({
       type: messageType,
-      room_id: this.gameState.currentRoomId || "global",
+      room_id: String(this.gameState.currentRoomId || "global"),
     });
     this.addSystemMessage(`🚀 尝试: ${messageType}`);
   }
 
-  // 发送移动消息
-  sendMove(direction: string) {
-    this.send({
-      type: "move",
-      direction: direction,
-      from: { x: 10, y: 20 },
-    });
-    this.addSystemMessage(`🚶 发送移动: ${direction}`);
-  }
-
   // 切换组别
   changeGroup(roomId: string | number, targetGroupId: number) {
     this.send({
       type: "change_group",
-      room_id: roomId,
+      room_id: String(roomId),
       target_group_id: targetGroupId,
     });
     console.log(`发送切换组别请求: 房间 ${roomId}, 目标组 ${targetGroupId}`);
@@ -937,7 +1177,7 @@ class WebSocketManager {
     setTimeout(() => {
       this.send({
         type: "should_start",
-        room_id: roomId,
+        room_id: String(roomId),
       });
       console.log(`队伍变更后，请求检查游戏开始条件: 房间 ${roomId}`);
     }, 100); // 延迟一点确保组别切换完成
@@ -992,7 +1232,7 @@ class WebSocketManager {
   sendGameAction(roomId: string | number, action: string) {
     return this.send({
       type: "game_action",
-      room_id: roomId,
+      room_id: String(roomId),
       action: action,
     });
   }
@@ -1009,7 +1249,7 @@ class WebSocketManager {
   ) {
     return this.send({
       type: "game_move",
-      room_id: roomId,
+      room_id: String(roomId),
       from_x: fromX,
       from_y: fromY,
       to_x: toX,
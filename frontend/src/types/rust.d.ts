@@ -1,39 +1,84 @@
 /* This file is generated and managed by tsync */
 
+interface ChatMessage {
+  id: number;
+  room_id: string;
+  sender_id: string;
+  username: string;
+  content: string;
+  created_at: Date;
+}
+
 /** Struct representing a row in table `rooms` */
 interface Rooms {
   /** Field representing column `id` */
   id?: number;
   /** Field representing column `name` */
   name: string;
-  /** Field representing column `password` */
-  password?: string;
+  /** Field representing column `password_hash` */
+  password_hash?: string;
   /** Field representing column `is_public` */
   is_public: boolean;
   /** Field representing column `created_at` */
   created_at: Date;
+  /** Field representing column `room_id` */
+  room_id: string;
+  /** Field representing column `max_players` */
+  max_players: number;
+  /** Field representing column `room_color` */
+  room_color: string;
+  /** Field representing column `host_player_id` */
+  host_player_id: string;
+  /** Field representing column `host_player_name` */
+  host_player_name: string;
+  /** Field representing column `is_persistent` */
+  is_persistent: boolean;
 }
 
 /** Create Struct for a row in table `rooms` for [`Rooms`] */
 interface CreateRooms {
   /** Field representing column `name` */
   name: string;
-  /** Field representing column `password` */
-  password?: string;
+  /** Field representing column `password_hash` */
+  password_hash?: string;
   /** Field representing column `is_public` */
   is_public: boolean;
+  /** Field representing column `room_id` */
+  room_id: string;
+  /** Field representing column `max_players` */
+  max_players: number;
+  /** Field representing column `room_color` */
+  room_color: string;
+  /** Field representing column `host_player_id` */
+  host_player_id: string;
+  /** Field representing column `host_player_name` */
+  host_player_name: string;
+  /** Field representing column `is_persistent` */
+  is_persistent: boolean;
 }
 
 /** Update Struct for a row in table `rooms` for [`Rooms`] */
 interface UpdateRooms {
   /** Field representing column `name` */
   name?: string;
-  /** Field representing column `password` */
-  password?: string;
+  /** Field representing column `password_hash` */
+  password_hash?: string;
   /** Field representing column `is_public` */
   is_public?: boolean;
   /** Field representing column `created_at` */
   created_at?: Date;
+  /** Field representing column `room_id` */
+  room_id?: string;
+  /** Field representing column `max_players` */
+  max_players?: number;
+  /** Field representing column `room_color` */
+  room_color?: string;
+  /** Field representing column `host_player_id` */
+  host_player_id?: string;
+  /** Field representing column `host_player_name` */
+  host_player_name?: string;
+  /** Field representing column `is_persistent` */
+  is_persistent?: boolean;
 }
 
 /** Result of a `.paginate` function */
@@ -49,3 +94,470 @@ interface PaginationResult<T> {
   /** Number of total possible pages, given the `page_size` and `total_items` */
   num_pages: number;
 }
+
+interface RoomInfo {
+  room_id: string;
+  name: string;
+  host_name: string;
+  status: string;
+  player_count: number;
+  max_players: number;
+  room_color: string;
+  required_to_start: number;
+  is_active: boolean;
+  has_password: boolean;
+  game_turn?: number;
+  alive_players?: number;
+}
+
+/** 客户端发送给服务器的消息 */
+type ClientMessage =
+  | ClientMessage__Hello
+  | ClientMessage__JoinRoom
+  | ClientMessage__LeaveRoom
+  | ClientMessage__Chat
+  | ClientMessage__Ping
+  | ClientMessage__Whisper
+  | ClientMessage__QuickPlay
+  | ClientMessage__JoinQueue
+  | ClientMessage__LeaveQueue
+  | ClientMessage__SubscribeLobby
+  | ClientMessage__UnsubscribeLobby
+  | ClientMessage__Invite
+  | ClientMessage__DeclineInvite
+  | ClientMessage__CreateInviteLink
+  | ClientMessage__GetRoomInfo
+  | ClientMessage__ForceStart
+  | ClientMessage__DeForceStart
+  | ClientMessage__ShouldStart
+  | ClientMessage__GameAction
+  | ClientMessage__RequestMapSnapshot
+  | ClientMessage__GameMove
+  | ClientMessage__SetAdmin
+  | ClientMessage__RemoveAdmin
+  | ClientMessage__KickPlayer
+  | ClientMessage__MutePlayer
+  | ClientMessage__UnmutePlayer
+  | ClientMessage__ChangeGroup
+  | ClientMessage__UpdateRoomSettings;
+
+/** 连接建立后声明客户端使用的协议版本和支持的功能 */
+type ClientMessage__Hello = {
+  type: "hello";
+  protocol_version: number;
+  features?: Array<string>;
+};
+type ClientMessage__JoinRoom = {
+  type: "join_room";
+  room_id: string;
+  player_name: string;
+  password?: string;
+  invite_token?: string;
+};
+type ClientMessage__LeaveRoom = {
+  type: "leave_room";
+  room_id: string;
+};
+type ClientMessage__Chat = {
+  type: "chat";
+  room_id: string;
+  content: string;
+  channel?: string;
+};
+/** 在地图上放置标记 */
+type ClientMessage__Ping = {
+  type: "ping";
+  room_id: string;
+  x: number;
+  y: number;
+  marker: string;
+};
+type ClientMessage__Whisper = {
+  type: "whisper";
+  target_player_name: string;
+  content: string;
+};
+type ClientMessage__QuickPlay = {
+  type: "quick_play";
+};
+type ClientMessage__JoinQueue = {
+  type: "join_queue";
+};
+type ClientMessage__LeaveQueue = {
+  type: "leave_queue";
+};
+type ClientMessage__SubscribeLobby = {
+  type: "subscribe_lobby";
+};
+type ClientMessage__UnsubscribeLobby = {
+  type: "unsubscribe_lobby";
+};
+type ClientMessage__Invite = {
+  type: "invite";
+  room_id: string;
+  target_player_name: string;
+};
+type ClientMessage__DeclineInvite = {
+  type: "decline_invite";
+  token: string;
+};
+type ClientMessage__CreateInviteLink = {
+  type: "create_invite_link";
+  room_id: string;
+};
+type ClientMessage__GetRoomInfo = {
+  type: "get_room_info";
+  room_id: string;
+};
+type ClientMessage__ForceStart = {
+  type: "force_start";
+  room_id: string;
+};
+type ClientMessage__DeForceStart = {
+  type: "de_force_start";
+  room_id: string;
+};
+type ClientMessage__ShouldStart = {
+  type: "should_start";
+  room_id: string;
+};
+type ClientMessage__GameAction = {
+  type: "game_action";
+  room_id: string;
+  action: string;
+};
+/** 地图增量的 seq 不连续时请求完整快照，在下一次地图更新时发送 */
+type ClientMessage__RequestMapSnapshot = {
+  type: "request_map_snapshot";
+  room_id: string;
+};
+type ClientMessage__GameMove = {
+  type: "game_move";
+  room_id: string;
+  from_x: number;
+  from_y: number;
+  to_x: number;
+  to_y: number;
+  move_id: number;
+  is_half_move?: boolean;
+};
+type ClientMessage__SetAdmin = {
+  type: "set_admin";
+  room_id: string;
+  target_player_name: string;
+};
+type ClientMessage__RemoveAdmin = {
+  type: "remove_admin";
+  room_id: string;
+};
+type ClientMessage__KickPlayer = {
+  type: "kick_player";
+  room_id: string;
+  target_player_name: string;
+};
+type ClientMessage__MutePlayer = {
+  type: "mute_player";
+  room_id: string;
+  target_player_name: string;
+  duration_minutes?: number;
+};
+type ClientMessage__UnmutePlayer = {
+  type: "unmute_player";
+  room_id: string;
+  target_player_name: string;
+};
+type ClientMessage__ChangeGroup = {
+  type: "change_group";
+  room_id: string;
+  target_group_id: number;
+};
+/** 只修改提供了的字段 */
+type ClientMessage__UpdateRoomSettings = {
+  type: "update_room_settings";
+  room_id: string;
+  name?: string;
+  max_players?: number;
+  room_color?: string;
+  password?: string;
+  is_public?: boolean;
+  is_persistent?: boolean;
+};
+
+/** 房间中一个分组的玩家 */
+interface GroupPlayers {
+  id: number;
+  players: Array<string>;
+}
+
+/** 地图标记，只发送给队友和观战者 */
+interface PingMarker {
+  x: number;
+  y: number;
+  marker: string;
+  username: string;
+  expires_turn: number;
+}
+
+/** 服务器发送给客户端的消息 */
+type ServerMessage =
+  | ServerMessage__Connected
+  | ServerMessage__Handshake
+  | ServerMessage__ProtocolMismatch
+  | ServerMessage__Ok
+  | ServerMessage__Error
+  | ServerMessage__ProtocolError
+  | ServerMessage__JoinRoomSuccess
+  | ServerMessage__JoinRoomError
+  | ServerMessage__JoinRoom
+  | ServerMessage__LeaveRoom
+  | ServerMessage__RoomInfo
+  | ServerMessage__ChatMessage
+  | ServerMessage__ChatHistory
+  | ServerMessage__Whisper
+  | ServerMessage__UsernameChanged
+  | ServerMessage__FriendUpdate
+  | ServerMessage__RoomInvite
+  | ServerMessage__InviteLink
+  | ServerMessage__QueueStatus
+  | ServerMessage__MatchFound
+  | ServerMessage__QuickPlay
+  | ServerMessage__LobbyRooms
+  | ServerMessage__RoomCreated
+  | ServerMessage__RoomUpdated
+  | ServerMessage__RoomRemoved
+  | ServerMessage__RedirectToHome
+  | ServerMessage__RedirectToGame
+  | ServerMessage__StartGame
+  | ServerMessage__EndGame
+  | ServerMessage__GameTurnUpdate
+  | ServerMessage__MapUpdate
+  | ServerMessage__MapDelta
+  | ServerMessage__MoveOk
+  | ServerMessage__GameWin
+  | ServerMessage__PlayerEliminated;
+
+/** 认证成功，会话已建立 */
+type ServerMessage__Connected = {
+  type: "connected";
+  user_id: string;
+  username: string;
+  protocol_version: number;
+  min_protocol_version: number;
+  features: Array<string>;
+};
+/** 对 hello 的回应，包含协商后的协议版本和双方都支持的功能 */
+type ServerMessage__Handshake = {
+  type: "handshake";
+  protocol_version: number;
+  features: Array<string>;
+};
+/** 客户端协议版本过旧，发送后服务器关闭连接 */
+type ServerMessage__ProtocolMismatch = {
+  type: "protocol_mismatch";
+  message: string;
+  client_version: number;
+  min_protocol_version: number;
+  protocol_version: number;
+};
+type ServerMessage__Ok = {
+  type: "ok";
+};
+type ServerMessage__Error = {
+  type: "error";
+  message: string;
+};
+/** 客户端发送的消息无法解析 */
+type ServerMessage__ProtocolError = {
+  type: "protocol_error";
+  error: string;
+  message: string;
+  request_type?: string;
+};
+type ServerMessage__JoinRoomSuccess = {
+  type: "join_room_success";
+  room_id: string;
+  message: string;
+};
+type ServerMessage__JoinRoomError = {
+  type: "join_room_error";
+  room_id: string;
+  error: string;
+};
+type ServerMessage__JoinRoom = {
+  type: "join_room";
+  room_id: string;
+  player_id: string;
+  player_name: string;
+};
+type ServerMessage__LeaveRoom = {
+  type: "leave_room";
+  room_id: string;
+  player_id: string;
+};
+type ServerMessage__RoomInfo = {
+  type: "room_info";
+  room_id: string;
+  name: string;
+  host_player_name: string;
+  admin_player_name?: string;
+  status: string;
+  players: Array<string>;
+  player_count: number;
+  force_start_players: Array<string>;
+  required_to_start: number;
+  groups: Array<GroupPlayers>;
+  max_players: number;
+  muted_players: Array<string>;
+};
+type ServerMessage__ChatMessage = {
+  type: "chat_message";
+  room_id: string;
+  user_id: string;
+  username: string;
+  message: string;
+  content: string;
+  channel?: string;
+};
+/** 加入房间或连接时的聊天记录 */
+type ServerMessage__ChatHistory = {
+  type: "chat_history";
+  room_id: string;
+  messages: Array<ChatMessage>;
+};
+type ServerMessage__Whisper = {
+  type: "whisper";
+  sender_id: string;
+  username: string;
+  recipient_id: string;
+  recipient_name: string;
+  content: string;
+};
+/** 用户名被修改 */
+type ServerMessage__UsernameChanged = {
+  type: "username_changed";
+  user_id: string;
+  username: string;
+};
+/** 好友请求、好友关系或在线状态变化 */
+type ServerMessage__FriendUpdate = {
+  type: "friend_update";
+  user_id: string;
+  username: string;
+  event: string;
+  presence?: Presence;
+};
+type ServerMessage__RoomInvite = {
+  type: "room_invite";
+  room_id: string;
+  room_name: string;
+  inviter_name: string;
+  token: string;
+  expires_at: number;
+};
+type ServerMessage__InviteLink = {
+  type: "invite_link";
+  room_id: string;
+  token: string;
+  expires_at: number;
+};
+/** 排位匹配队列状态 */
+type ServerMessage__QueueStatus = {
+  type: "queue_status";
+  status: string;
+  rating?: number;
+};
+type ServerMessage__MatchFound = {
+  type: "match_found";
+  room_id: string;
+  opponent_name: string;
+  rating: number;
+  opponent_rating: number;
+};
+/** 快速开始选中的房间 */
+type ServerMessage__QuickPlay = {
+  type: "quick_play";
+  room_id: string;
+  name: string;
+  created: boolean;
+};
+/** 订阅大厅时的完整公开房间列表 */
+type ServerMessage__LobbyRooms = {
+  type: "lobby_rooms";
+  rooms: Array<RoomInfo>;
+};
+type ServerMessage__RoomCreated = {
+  type: "room_created";
+  room: RoomInfo;
+};
+type ServerMessage__RoomUpdated = {
+  type: "room_updated";
+  room: RoomInfo;
+};
+/** 房间被删除或不再公开 */
+type ServerMessage__RoomRemoved = {
+  type: "room_removed";
+  room_id: string;
+};
+type ServerMessage__RedirectToHome = {
+  type: "redirect_to_home";
+  reason: string;
+};
+type ServerMessage__RedirectToGame = {
+  type: "redirect_to_game";
+  room_id: string;
+};
+type ServerMessage__StartGame = {
+  type: "start_game";
+  room_id: string;
+};
+type ServerMessage__EndGame = {
+  type: "end_game";
+  room_id: string;
+};
+type ServerMessage__GameTurnUpdate = {
+  type: "game_turn_update";
+  room_id: string;
+  turn: number;
+  turn_half: boolean;
+  actions: Array<[string, string]>;
+};
+/** 完整的地图快照 */
+type ServerMessage__MapUpdate = {
+  type: "map_update";
+  room_id: string;
+  seq: number;
+  visible_tiles: Array<[number, number, string, number, string | undefined, boolean]>;
+  successful_move_sends: Array<number>;
+  player_powers: Array<[string, number, number, string]>;
+  pings: Array<PingMarker>;
+};
+/** 与上一次地图更新相比变化的格子，只在协商了 map_delta 时发送 */
+type ServerMessage__MapDelta = {
+  type: "map_delta";
+  room_id: string;
+  seq: number;
+  changed_tiles: Array<[number, number, string, number, string | undefined, boolean]>;
+  removed_tiles: Array<[number, number]>;
+  successful_move_sends: Array<number>;
+  player_powers: Array<[string, number, number, string]>;
+  pings: Array<PingMarker>;
+};
+type ServerMessage__MoveOk = {
+  type: "move_ok";
+};
+type ServerMessage__GameWin = {
+  type: "game_win";
+  room_id: string;
+  winner: string;
+};
+type ServerMessage__PlayerEliminated = {
+  type: "player_eliminated";
+  room_id: string;
+  eliminated_player: string;
+  eliminated_by: string;
+};
+
+interface Presence {
+  status: string;
+  room_id?: string;
+}
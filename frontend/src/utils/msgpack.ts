@@ -0,0 +1,148 @@
+/**
+ * MessagePack 解码
+ * 协商了 msgpack 功能后，服务器以二进制帧发送地图和回合更新，字段名与 JSON 消息相同，
+ * 地图格子压缩为整数，需用 unpackTiles 还原
+ */
+
+import type { VisibleTile } from "../hooks/wsManager";
+
+const textDecoder = new TextDecoder();
+
+// 格子类型代码即在此表中的下标，与后端 protocol.rs 中的 TILE_KINDS 保持一致
+const TILE_KINDS = ["unknown", "w", "t", "m", "g", "v", "c_settlement", "c_smallcity", "c_largecity"];
+
+// 二进制帧中的格子 [x, y, 格子类型代码, 兵力, 队伍序号, 是否有视野]
+export type PackedTile = [number, number, number, number, number, boolean];
+
+class MsgpackReader {
+  private view: DataView;
+  private bytes: Uint8Array;
+  private offset = 0;
+
+  constructor(buffer: ArrayBuffer) {
+    this.view = new DataView(buffer);
+    this.bytes = new Uint8Array(buffer);
+  }
+
+  read(): unknown {
+    const byte = this.readUint8();
+
+    if (byte <= 0x7f) return byte; // positive fixint
+    if (byte >= 0xe0) return byte - 0x100; // negative fixint
+    if ((byte & 0xf0) === 0x80) return this.readMap(byte & 0x0f);
+    if ((byte & 0xf0) === 0x90) return this.readArray(byte & 0x0f);
+    if ((byte & 0xe0) === 0xa0) return this.readString(byte & 0x1f);
+
+    switch (byte) {
+      case 0xc0:
+        return null;
+      case 0xc2:
+        return false;
+      case 0xc3:
+        return true;
+      case 0xc4:
+        return this.readBytes(this.readUint8());
+      case 0xc5:
+        return this.readBytes(this.readUint16());
+      case 0xc6:
+        return this.readBytes(this.readUint32());
+      case 0xca:
+        return this.advance(4, (at) => this.view.getFloat32(at));
+      case 0xcb:
+        return this.advance(8, (at) => this.view.getFloat64(at));
+      case 0xcc:
+        return this.readUint8();
+      case 0xcd:
+        return this.readUint16();
+      case 0xce:
+        return this.readUint32();
+      case 0xcf:
+        return this.advance(8, (at) => Number(this.view.getBigUint64(at)));
+      case 0xd0:
+        return this.advance(1, (at) => this.view.getInt8(at));
+      case 0xd1:
+        return this.advance(2, (at) => this.view.getInt16(at));
+      case 0xd2:
+        return this.advance(4, (at) => this.view.getInt32(at));
+      case 0xd3:
+        return this.advance(8, (at) => Number(this.view.getBigInt64(at)));
+      case 0xd9:
+        return this.readString(this.readUint8());
+      case 0xda:
+        return this.readString(this.readUint16());
+      case 0xdb:
+        return this.readString(this.readUint32());
+      case 0xdc:
+        return this.readArray(this.readUint16());
+      case 0xdd:
+        return this.readArray(this.readUint32());
+      case 0xde:
+        return this.readMap(this.readUint16());
+      case 0xdf:
+        return this.readMap(this.readUint32());
+      default:
+        throw new Error(`不支持的 MessagePack 类型: 0x${byte.toString(16)}`);
+    }
+  }
+
+  private advance<T>(size: number, read: (at: number) => T): T {
+    const value = read(this.offset);
+    this.offset += size;
+    return value;
+  }
+
+  private readUint8() {
+    return this.advance(1, (at) => this.view.getUint8(at));
+  }
+
+  private readUint16() {
+    return this.advance(2, (at) => this.view.getUint16(at));
+  }
+
+  private readUint32() {
+    return this.advance(4, (at) => this.view.getUint32(at));
+  }
+
+  private readBytes(length: number) {
+    return this.advance(length, (at) => this.bytes.slice(at, at + length));
+  }
+
+  private readString(length: number) {
+    return this.advance(length, (at) =>
+      textDecoder.decode(this.bytes.subarray(at, at + length))
+    );
+  }
+
+  private readArray(length: number) {
+    const array: unknown[] = [];
+    for (let i = 0; i < length; i++) {
+      array.push(this.read());
+    }
+    return array;
+  }
+
+  private readMap(length: number) {
+    const map: Record<string, unknown> = {};
+    for (let i = 0; i < length; i++) {
+      const key = String(this.read());
+      map[key] = this.read();
+    }
+    return map;
+  }
+}
+
+export function decodeMsgpack(buffer: ArrayBuffer): unknown {
+  return new MsgpackReader(buffer).read();
+}
+
+// 按对局的队伍表还原格子，队伍序号 0 表示无归属，n 表示 teams 中的第 n 项
+export function unpackTiles(tiles: PackedTile[], teams: string[]): VisibleTile[] {
+  return tiles.map(([x, y, kind, count, team, hasVision]) => [
+    x,
+    y,
+    TILE_KINDS[kind] ?? "unknown",
+    count,
+    team > 0 ? teams[team - 1] ?? null : null,
+    hasVision,
+  ]);
+}
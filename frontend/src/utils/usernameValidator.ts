@@ -1,25 +1,72 @@
 /**
  * 用户名验证工具
- * 集中管理所有用户名验证规则
+ * 集中管理所有用户名验证规则，规则可通过 loadRules() 从服务端同步
  */
 
+import { buildApiUrl, API_ENDPOINTS } from "../config/api";
+
 export interface ValidationResult {
   isValid: boolean;
   error?: string;
 }
 
+// 服务端 /api/users/username_rules 返回的规则
+interface UsernameRules {
+  min_length: number;
+  max_length: number;
+  forbidden_chars: string;
+  reserved_names: string[];
+}
+
 export class UsernameValidator {
-  // 禁用的内容列表
+  // 禁用的内容列表（完整列表只在服务端校验，不下发给前端）
   private static readonly FORBIDDEN_CONTENT = ["ek"];
 
   // 禁用的特殊字符
-  private static readonly FORBIDDEN_CHARS = /[<>"'&]/;
+  private static FORBIDDEN_CHARS = /[<>"'&]/;
+
+  // 系统保留的用户名
+  private static RESERVED_NAMES = ["system", "系统", "系统广播", "Unknown", "admin", "管理员"];
 
   // 最小长度
-  private static readonly MIN_LENGTH = 2;
+  private static MIN_LENGTH = 2;
 
   // 最大长度
-  private static readonly MAX_LENGTH = 20;
+  private static MAX_LENGTH = 20;
+
+  private static rulesLoaded: Promise<void> | null = null;
+
+  /**
+   * 从服务端加载校验规则，只请求一次；失败时继续使用内置规则
+   */
+  static loadRules(): Promise<void> {
+    if (!UsernameValidator.rulesLoaded) {
+      UsernameValidator.rulesLoaded = fetch(buildApiUrl(API_ENDPOINTS.USERNAME_RULES))
+        .then((response) => {
+          if (!response.ok) {
+            throw new Error(`HTTP ${response.status}`);
+          }
+          return response.json() as Promise<UsernameRules>;
+        })
+        .then((rules) => {
+          UsernameValidator.MIN_LENGTH = rules.min_length;
+          UsernameValidator.MAX_LENGTH = rules.max_length;
+          UsernameValidator.RESERVED_NAMES = rules.reserved_names;
+          const escaped = rules.forbidden_chars.replace(/[\\\]^-]/g, "\\$&");
+          UsernameValidator.FORBIDDEN_CHARS = new RegExp(`[${escaped}]`);
+        })
+        .catch((error) => {
+          console.error("加载用户名规则失败，使用内置规则:", error);
+          UsernameValidator.rulesLoaded = null;
+        });
+    }
+    return UsernameValidator.rulesLoaded;
+  }
+
+  // 与服务端一致的归一化：去除空白和标点并转小写
+  private static normalize(text: string): string {
+    return text.toLowerCase().replace(/[^\p{L}\p{N}]/gu, "");
+  }
 
   /**
    * 验证用户名是否符合所有规则
@@ -37,24 +84,38 @@ export class UsernameValidator {
 
     const trimmedUsername = username.trim();
 
-    // 检查长度
-    if (trimmedUsername.length < UsernameValidator.MIN_LENGTH) {
+    // 检查长度（按字符计，与服务端一致）
+    const length = Array.from(trimmedUsername).length;
+    if (length < UsernameValidator.MIN_LENGTH) {
       return {
         isValid: false,
         error: `用户名至少需要${UsernameValidator.MIN_LENGTH}个字符`,
       };
     }
 
-    if (trimmedUsername.length > UsernameValidator.MAX_LENGTH) {
+    if (length > UsernameValidator.MAX_LENGTH) {
       return {
         isValid: false,
         error: `用户名不能超过${UsernameValidator.MAX_LENGTH}个字符`,
       };
     }
 
+    // 检查保留名称
+    const normalized = UsernameValidator.normalize(trimmedUsername);
+    if (
+      UsernameValidator.RESERVED_NAMES.some(
+        (reserved) => UsernameValidator.normalize(reserved) === normalized
+      )
+    ) {
+      return {
+        isValid: false,
+        error: "该用户名为系统保留，请选择其他用户名",
+      };
+    }
+
     // 检查禁用内容
     for (const forbidden of UsernameValidator.FORBIDDEN_CONTENT) {
-      if (trimmedUsername.toLowerCase().includes(forbidden.toLowerCase())) {
+      if (normalized.includes(UsernameValidator.normalize(forbidden))) {
         return {
           isValid: false,
           error: `用户名包含禁用内容"${forbidden}"，请选择其他用户名`,
@@ -100,9 +161,9 @@ export class UsernameValidator {
    * @returns 是否包含禁用内容
    */
   static containsForbiddenContent(username: string): boolean {
-    const lowerUsername = username.toLowerCase();
+    const normalized = UsernameValidator.normalize(username);
     return UsernameValidator.FORBIDDEN_CONTENT.some((forbidden) =>
-      lowerUsername.includes(forbidden.toLowerCase())
+      normalized.includes(UsernameValidator.normalize(forbidden))
     );
   }
 }
@@ -111,3 +172,4 @@ export class UsernameValidator {
 export const validateUsername = UsernameValidator.validate;
 export const isValidUsername = UsernameValidator.isValid;
 export const getUsernameError = UsernameValidator.getError;
+export const loadUsernameRules = UsernameValidator.loadRules;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE game_players;
+DROP TABLE games;
@@ -0,0 +1,26 @@
+-- 对局记录表
+CREATE TABLE games (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    room_id TEXT NOT NULL,
+    winner_team TEXT,
+    player_count INTEGER NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP NOT NULL
+);
+
+-- 每局中每个玩家的成绩
+CREATE TABLE game_players (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    game_id INTEGER NOT NULL REFERENCES games(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    username TEXT NOT NULL,
+    group_id INTEGER NOT NULL,
+    team_id TEXT NOT NULL,
+    placement INTEGER NOT NULL,
+    is_winner BOOLEAN NOT NULL DEFAULT FALSE,
+    cities_captured INTEGER NOT NULL DEFAULT 0,
+    generals_captured INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX idx_game_players_user_id ON game_players(user_id);
+CREATE INDEX idx_game_players_game_id ON game_players(game_id);
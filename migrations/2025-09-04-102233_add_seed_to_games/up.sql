@@ -0,0 +1,2 @@
+-- 为对局记录添加地图seed
+ALTER TABLE games ADD COLUMN map_seed BIGINT;
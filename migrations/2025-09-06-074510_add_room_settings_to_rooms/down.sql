@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX idx_rooms_room_id;
+ALTER TABLE rooms DROP COLUMN is_persistent;
+ALTER TABLE rooms DROP COLUMN host_player_name;
+ALTER TABLE rooms DROP COLUMN host_player_id;
+ALTER TABLE rooms DROP COLUMN room_color;
+ALTER TABLE rooms DROP COLUMN max_players;
+ALTER TABLE rooms DROP COLUMN room_id;
@@ -0,0 +1,12 @@
+-- 为房间表添加游戏房间ID和房间设置，用于持久化GameServer中的房间
+ALTER TABLE rooms ADD COLUMN room_id TEXT NOT NULL DEFAULT '';
+ALTER TABLE rooms ADD COLUMN max_players INTEGER NOT NULL DEFAULT 16;
+ALTER TABLE rooms ADD COLUMN room_color TEXT NOT NULL DEFAULT '#4F46E5';
+ALTER TABLE rooms ADD COLUMN host_player_id TEXT NOT NULL DEFAULT '';
+ALTER TABLE rooms ADD COLUMN host_player_name TEXT NOT NULL DEFAULT '';
+ALTER TABLE rooms ADD COLUMN is_persistent BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- 旧数据没有对应的游戏房间，直接清除
+DELETE FROM rooms WHERE room_id = '';
+
+CREATE UNIQUE INDEX idx_rooms_room_id ON rooms(room_id);
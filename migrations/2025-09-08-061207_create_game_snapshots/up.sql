@@ -0,0 +1,7 @@
+-- 进行中对局的快照，服务器重启后用于恢复对局
+CREATE TABLE game_snapshots (
+    room_id TEXT PRIMARY KEY NOT NULL,
+    game_turn INTEGER NOT NULL,
+    snapshot TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,3 @@
+DROP INDEX idx_sessions_user_id;
+DROP TABLE sessions;
+ALTER TABLE users DROP COLUMN password_hash;
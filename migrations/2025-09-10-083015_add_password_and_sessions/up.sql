@@ -0,0 +1,12 @@
+-- 用户密码（argon2 哈希）。旧账户为空，首次登录时设置
+ALTER TABLE users ADD COLUMN password_hash TEXT;
+
+-- 登录会话，token 由服务器随机生成
+CREATE TABLE sessions (
+    token TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_sessions_user_id ON sessions(user_id);
@@ -0,0 +1,2 @@
+-- 游客账户：无密码，仅凭会话token登录，可升级为正式账户
+ALTER TABLE users ADD COLUMN is_guest BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+DROP INDEX idx_username_history_user_id;
+DROP TABLE username_history;
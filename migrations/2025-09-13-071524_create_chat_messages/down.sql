@@ -0,0 +1,2 @@
+DROP INDEX idx_chat_messages_room_id;
+DROP TABLE chat_messages;
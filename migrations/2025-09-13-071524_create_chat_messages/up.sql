@@ -0,0 +1,11 @@
+-- 全局聊天记录（房间聊天只保存在内存中）
+CREATE TABLE chat_messages (
+    id INTEGER PRIMARY KEY NOT NULL,
+    room_id TEXT NOT NULL,
+    sender_id TEXT NOT NULL,
+    username TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_chat_messages_room_id ON chat_messages(room_id, id);
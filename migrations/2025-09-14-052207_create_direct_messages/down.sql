@@ -0,0 +1,3 @@
+DROP INDEX idx_direct_messages_recipient_sender;
+DROP INDEX idx_direct_messages_sender_recipient;
+DROP TABLE direct_messages;
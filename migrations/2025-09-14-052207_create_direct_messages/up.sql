@@ -0,0 +1,11 @@
+-- 玩家之间的私信记录
+CREATE TABLE direct_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    sender_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    recipient_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_direct_messages_sender_recipient ON direct_messages(sender_id, recipient_id, id);
+CREATE INDEX idx_direct_messages_recipient_sender ON direct_messages(recipient_id, sender_id, id);
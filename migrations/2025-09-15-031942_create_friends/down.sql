@@ -0,0 +1,2 @@
+DROP INDEX idx_friends_friend_id;
+DROP TABLE friends;
@@ -0,0 +1,10 @@
+-- 好友关系：每对用户一行，user_id 为发起请求的一方
+CREATE TABLE friends (
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    friend_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'pending', -- pending / accepted
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, friend_id)
+);
+
+CREATE INDEX idx_friends_friend_id ON friends(friend_id);
@@ -0,0 +1,2 @@
+-- 已经哈希的密码无法还原为明文
+ALTER TABLE rooms RENAME COLUMN password_hash TO password;
@@ -0,0 +1,2 @@
+-- 房间密码改为保存 argon2 哈希。已有的明文密码在服务器启动恢复房间时重新哈希
+ALTER TABLE rooms RENAME COLUMN password TO password_hash;
@@ -0,0 +1,2 @@
+-- 1v1 排位分，新账户从 1000 分开始
+ALTER TABLE users ADD COLUMN rating INTEGER NOT NULL DEFAULT 1000;
@@ -0,0 +1 @@
+DROP INDEX idx_friends_pair;
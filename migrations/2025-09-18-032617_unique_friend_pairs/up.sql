@@ -0,0 +1,9 @@
+-- 同一对用户互相发起过请求时合并为一行已确认的好友关系
+UPDATE friends SET status = 'accepted'
+WHERE EXISTS (SELECT 1 FROM friends f WHERE f.user_id = friends.friend_id AND f.friend_id = friends.user_id);
+DELETE FROM friends
+WHERE user_id > friend_id
+  AND EXISTS (SELECT 1 FROM friends f WHERE f.user_id = friends.friend_id AND f.friend_id = friends.user_id);
+
+-- 每对用户最多一行，不区分方向
+CREATE UNIQUE INDEX idx_friends_pair ON friends(MIN(user_id, friend_id), MAX(user_id, friend_id));